@@ -0,0 +1,322 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::{ParseContext, PathContext};
+use crate::fsutil::write_private;
+use crate::md5::md5;
+use crate::paths::data_directory;
+use crate::{Error, Result};
+
+pub const ACCOUNTS_FILE_NAME: &str = "accounts.json";
+
+const ENTITLEMENTS_URL: &str = "https://api.minecraftservices.com/entitlements/mcstore";
+
+const MICROSOFT_TOKEN_URL: &str = "https://login.microsoftonline.com/consumers/oauth2/v2.0/token";
+const XBOX_LIVE_AUTH_URL: &str = "https://user.auth.xboxlive.com/user/authenticate";
+const XSTS_AUTH_URL: &str = "https://xsts.auth.xboxlive.com/xsts/authorize";
+const MINECRAFT_LOGIN_URL: &str =
+    "https://api.minecraftservices.com/authentication/login_with_xbox";
+
+/// How long before it expires a session is refreshed, so it doesn't run out
+/// just after the game starts.
+const EXPIRY_MARGIN_SECS: u64 = 5 * 60;
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Account {
+    pub name: String,
+    pub uuid: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_token: Option<String>,
+    /// Xbox user id, needed by the game for online features such as Realms.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub xuid: Option<String>,
+    /// Client id of the Microsoft application the token was issued to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    /// Microsoft refresh token, for getting a new access token without
+    /// signing in again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    /// When `access_token` expires, in seconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+}
+
+impl Account {
+    /// An account that only works offline. The uuid is derived from the name
+    /// so the same player keeps the same save data between launches.
+    pub fn offline(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            uuid: offline_uuid(name),
+            access_token: None,
+            xuid: None,
+            client_id: None,
+            refresh_token: None,
+            expires_at: None,
+        }
+    }
+
+    /// Whether the access token has expired or is about to. Tokens without a
+    /// known expiry are assumed to still work.
+    pub fn needs_refresh(&self) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        self.access_token.is_some()
+            && self
+                .expires_at
+                .is_some_and(|expires_at| expires_at <= now + EXPIRY_MARGIN_SECS)
+    }
+
+    /// Gets a new access token through the Microsoft, Xbox Live and Minecraft
+    /// sign-in chain using the stored refresh token, and stores it along with
+    /// the new refresh token and expiry.
+    pub async fn refresh(&mut self, http_client: &reqwest::Client) -> Result<()> {
+        let (Some(refresh_token), Some(client_id)) = (&self.refresh_token, &self.client_id) else {
+            return Err(Error::SessionExpired {
+                account: self.name.clone(),
+                reason: String::from("no refresh token is stored"),
+            });
+        };
+
+        let microsoft: serde_json::Value = post_form(
+            http_client,
+            MICROSOFT_TOKEN_URL,
+            &[
+                ("client_id", client_id.as_str()),
+                ("grant_type", "refresh_token"),
+                ("refresh_token", refresh_token.as_str()),
+                ("scope", "XboxLive.signin offline_access"),
+            ],
+        )
+        .await?;
+
+        let xbox_live = post_json(
+            http_client,
+            XBOX_LIVE_AUTH_URL,
+            &serde_json::json!({
+                "Properties": {
+                    "AuthMethod": "RPS",
+                    "SiteName": "user.auth.xboxlive.com",
+                    "RpsTicket": format!("d={}", json_str(&microsoft, "/access_token")?),
+                },
+                "RelyingParty": "http://auth.xboxlive.com",
+                "TokenType": "JWT",
+            }),
+        )
+        .await?;
+
+        let xsts = post_json(
+            http_client,
+            XSTS_AUTH_URL,
+            &serde_json::json!({
+                "Properties": {
+                    "SandboxId": "RETAIL",
+                    "UserTokens": [json_str(&xbox_live, "/Token")?],
+                },
+                "RelyingParty": "rp://api.minecraftservices.com/",
+                "TokenType": "JWT",
+            }),
+        )
+        .await?;
+
+        let minecraft = post_json(
+            http_client,
+            MINECRAFT_LOGIN_URL,
+            &serde_json::json!({
+                "identityToken": format!(
+                    "XBL3.0 x={};{}",
+                    json_str(&xsts, "/DisplayClaims/xui/0/uhs")?,
+                    json_str(&xsts, "/Token")?
+                ),
+            }),
+        )
+        .await?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        self.access_token = Some(json_str(&minecraft, "/access_token")?.to_owned());
+        self.expires_at = minecraft["expires_in"]
+            .as_u64()
+            .map(|expires_in| now + expires_in);
+        // Microsoft usually hands out a new refresh token with each use.
+        if let Some(refresh_token) = microsoft["refresh_token"].as_str() {
+            self.refresh_token = Some(refresh_token.to_owned());
+        }
+
+        Ok(())
+    }
+}
+
+async fn post_form(
+    http_client: &reqwest::Client,
+    url: &str,
+    form: &[(&str, &str)],
+) -> Result<serde_json::Value> {
+    Ok(http_client
+        .post(url)
+        .form(form)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?)
+}
+
+async fn post_json(
+    http_client: &reqwest::Client,
+    url: &str,
+    body: &serde_json::Value,
+) -> Result<serde_json::Value> {
+    Ok(http_client
+        .post(url)
+        .json(body)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?)
+}
+
+fn json_str<'a>(json: &'a serde_json::Value, pointer: &str) -> Result<&'a str> {
+    json.pointer(pointer)
+        .and_then(|value| value.as_str())
+        .ok_or_else(|| Error::InvalidResponse {
+            url: String::from("sign-in"),
+            reason: format!("has no {}", pointer),
+        })
+}
+
+/// Asks Minecraft services whether the account behind `access_token` owns
+/// the game.
+pub async fn owns_minecraft(http_client: &reqwest::Client, access_token: &str) -> Result<bool> {
+    let entitlements: serde_json::Value = http_client
+        .get(ENTITLEMENTS_URL)
+        .bearer_auth(access_token)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    Ok(entitlements["items"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .any(|item| {
+            matches!(
+                item["name"].as_str(),
+                Some("product_minecraft" | "game_minecraft")
+            )
+        }))
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct Accounts {
+    pub accounts: Vec<Account>,
+}
+
+impl Accounts {
+    pub fn default_path() -> PathBuf {
+        data_directory().join(ACCOUNTS_FILE_NAME)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let accounts = fs::read_to_string(path).with_path(path)?;
+        serde_json::from_str(&accounts).parsing(path.display())
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_path(parent)?;
+        }
+
+        write_private(path, serde_json::to_string_pretty(self)?).with_path(path)?;
+
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Result<&Account> {
+        self.accounts
+            .iter()
+            .find(|account| account.name == name)
+            .ok_or_else(|| Error::UnknownAccount(name.to_owned()))
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Result<&mut Account> {
+        self.accounts
+            .iter_mut()
+            .find(|account| account.name == name)
+            .ok_or_else(|| Error::UnknownAccount(name.to_owned()))
+    }
+
+    /// Adds `account`, replacing any stored account with the same name.
+    pub fn add(&mut self, account: Account) {
+        self.accounts
+            .retain(|existing| existing.name != account.name);
+        self.accounts.push(account);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<Account> {
+        let index = self
+            .accounts
+            .iter()
+            .position(|account| account.name == name)
+            .ok_or_else(|| Error::UnknownAccount(name.to_owned()))?;
+
+        Ok(self.accounts.remove(index))
+    }
+}
+
+/// The uuid the game and other launchers give an offline player, Java's
+/// `UUID.nameUUIDFromBytes("OfflinePlayer:" + name)`, so their worlds keep
+/// the same player data.
+fn offline_uuid(name: &str) -> String {
+    let mut bytes = md5(format!("OfflinePlayer:{}", name).as_bytes());
+
+    // Name-based (version 3) uuid with the RFC 4122 variant.
+    bytes[6] = (bytes[6] & 0x0f) | 0x30;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex = bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offline_uuids_match_the_game() {
+        assert_eq!(
+            offline_uuid("Notch"),
+            "b50ad385-829d-3141-a216-7e7d7539ba7f"
+        );
+        assert_eq!(
+            offline_uuid("Steve"),
+            "5627dd98-e6be-3c21-b8a8-e92344183641"
+        );
+    }
+}
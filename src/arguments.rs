@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+
+use crate::rules::rules_allow;
+
+/// Used for metas that predate `arguments.jvm`.
+const DEFAULT_JVM_ARGUMENTS: &[&str] = &[
+    "-Djava.library.path=${natives_directory}",
+    "-Djna.tmpdir=${natives_directory}",
+    "-Dio.netty.native.workdir=${natives_directory}",
+    "-Dminecraft.launcher.brand=${launcher_name}",
+    "-Dminecraft.launcher.version=${launcher_version}",
+    "-cp",
+    "${classpath}",
+];
+
+/// The meta's JVM and game arguments with their rules applied, still holding
+/// `${...}` placeholders.
+#[derive(Debug, Default)]
+pub struct ArgumentTemplate {
+    pub jvm: Vec<String>,
+    pub game: Vec<String>,
+}
+
+fn flatten(entries: &serde_json::Value, features: &[&str], arguments: &mut Vec<String>) {
+    for entry in entries.as_array().into_iter().flatten() {
+        if let Some(argument) = entry.as_str() {
+            arguments.push(argument.to_owned());
+            continue;
+        }
+
+        if !rules_allow(entry.get("rules"), features) {
+            continue;
+        }
+
+        match &entry["value"] {
+            serde_json::Value::String(argument) => arguments.push(argument.clone()),
+            serde_json::Value::Array(values) => arguments.extend(
+                values
+                    .iter()
+                    .filter_map(|value| value.as_str())
+                    .map(String::from),
+            ),
+            _ => {}
+        }
+    }
+}
+
+/// Whether any argument in the meta is gated on `feature`, i.e. whether this
+/// version knows about it at all.
+pub fn meta_has_feature(meta: &serde_json::Value, feature: &str) -> bool {
+    ["jvm", "game"]
+        .iter()
+        .flat_map(|kind| meta["arguments"][kind].as_array().into_iter().flatten())
+        .flat_map(|entry| entry["rules"].as_array().into_iter().flatten())
+        .any(|rule| rule["features"].get(feature).is_some())
+}
+
+pub fn meta_arguments(meta: &serde_json::Value, features: &[&str]) -> ArgumentTemplate {
+    let mut template = ArgumentTemplate::default();
+
+    match meta.get("arguments") {
+        Some(arguments) => {
+            flatten(&arguments["jvm"], features, &mut template.jvm);
+            flatten(&arguments["game"], features, &mut template.game);
+        }
+        None => {
+            template.game = meta["minecraftArguments"]
+                .as_str()
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(String::from)
+                .collect();
+        }
+    }
+
+    if template.jvm.is_empty() {
+        template.jvm = DEFAULT_JVM_ARGUMENTS
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+    }
+
+    template
+}
+
+/// Replaces each `${name}` in `template`, or returns `None` if one of them
+/// has no value.
+pub fn substitute(template: &str, variables: &HashMap<&str, String>) -> Option<String> {
+    let mut substituted = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("${") {
+        let Some(length) = rest[start..].find('}') else {
+            break;
+        };
+
+        substituted.push_str(&rest[..start]);
+        substituted.push_str(variables.get(&rest[start + 2..start + length])?);
+        rest = &rest[start + length + 1..];
+    }
+
+    substituted.push_str(rest);
+    Some(substituted)
+}
+
+/// Substitutes every argument. An argument whose value isn't known is left
+/// out along with the `--flag` before it, rather than passing the game an
+/// empty or literal `${...}` value.
+pub fn apply_template(arguments: &[String], variables: &HashMap<&str, String>) -> Vec<String> {
+    let mut applied: Vec<String> = Vec::new();
+
+    for argument in arguments {
+        match substitute(argument, variables) {
+            Some(value) => applied.push(value),
+            None => {
+                if applied.last().is_some_and(|last| last.starts_with("--")) {
+                    applied.pop();
+                }
+            }
+        }
+    }
+
+    applied
+}
@@ -0,0 +1,921 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use blazinglyassmc::{
+    AssetFilter, InstallOptions, IpPreference, LaunchOptions, VerifyScope,
+    MINECRAFT_1_20_4_META_URL,
+};
+
+pub const USAGE: &str = "\
+usage:
+    blazinglyassmc [--yes | -y] COMMAND ...
+    blazinglyassmc [create] [--version ID | --meta-url URL] [--refresh-manifest]
+                          [--manifest-url URL]
+                          [--dir DIR] [--template FILE] [--assets-filter GLOB]...
+                          [--skip-asset-check] [--verify-hashes] [--no-assets]
+                          [--trust-cache] [--restart] [--download-only]
+                          [--concurrency N|auto] [--downloader builtin|aria2c]
+                          [--asset-index-url URL [--asset-index-id ID]]
+                          [--lwjgl-version VERSION] [--serve-status ADDR]
+                          [--timeout SECONDS] [--strip-meta]
+                          [--combined-progress [-v | --verbose]]
+                          [--prefer-ipv4 | --prefer-ipv6]
+    blazinglyassmc launch [--dir DIR] [--account NAME] [--gc ZGC|G1|Shenandoah]
+                          [--jvm-arg ARG]... [--wrapper ARG]...
+                          [--startup-timeout SECONDS]
+                          [--world NAME] [--demo] [--print-classpath]
+                          [--print-command] [--trust-cache] [--strict-mods]
+                          [--game-dir DIR] [--clean-natives] [--strict]
+                          [--serve-status ADDR] [--notify-url URL]
+    blazinglyassmc update DIR --to ID [--refresh-manifest] [--manifest-url URL]
+    blazinglyassmc versions [--since YYYY-MM-DD] [--type TYPE]
+                            [--refresh-manifest] [--manifest-url URL]
+    blazinglyassmc verify [--dir DIR] [--no-cache]
+    blazinglyassmc repair DIR [--assets-only | --libraries-only]
+    blazinglyassmc doctor [--dir DIR]
+    blazinglyassmc config show [--dir DIR] [--json] [--account NAME]
+                               [--gc ZGC|G1|Shenandoah] [--jvm-arg ARG]...
+                               [--wrapper ARG]... [--game-dir DIR]
+    blazinglyassmc du DIR
+    blazinglyassmc diff DIR DIR
+    blazinglyassmc seed-store --from DIR
+    blazinglyassmc refresh-launchers [DIR]...
+    blazinglyassmc shortcut DIR
+    blazinglyassmc crashes [DIR]... [--limit N]
+    blazinglyassmc export DIR FILE
+    blazinglyassmc import FILE [--dir DIR]
+    blazinglyassmc server list [--dir DIR]
+    blazinglyassmc server add NAME ADDRESS [--dir DIR]
+    blazinglyassmc server download --version ID [--dir DIR] [--accept-eula]
+                                   [--refresh-manifest]
+    blazinglyassmc accounts list
+    blazinglyassmc accounts add NAME
+    blazinglyassmc accounts remove NAME
+
+--version and --to take a version id from Mojang's version manifest, like
+1.21 or latest. The manifest is cached; --refresh-manifest fetches it again.
+--manifest-url looks versions up in the manifest at URL instead, e.g. a mirror
+or one listing custom client builds. create and update save it as the
+instance's manifest_url, which later updates keep using.
+update keeps saves, mods, config and resource packs and warns about mods that
+don't declare support for the new version.
+
+versions lists the manifest's versions newest first. --since leaves out those
+released before the date and --type keeps one type: release, snapshot,
+old_beta or old_alpha.
+
+--template seeds the new instance from a LauncherConfig.toml-style FILE that
+can also have [options] and [keybinds] tables for options.txt and [[mods]] and
+[[resourcepacks]] entries with a url to download.
+
+--assets-filter only downloads assets whose path matches GLOB, or skips them
+when GLOB starts with !. For example minecraft/sounds/** or !minecraft/lang/*.
+
+create re-downloads assets whose size doesn't match the index. With
+--verify-hashes it hashes them too, which is slower but catches any damage.
+--no-assets skips them altogether for quick installs that don't need textures
+or sounds.
+
+A create that fails partway picks up where it stopped when run again, skipping
+the client, libraries, natives or assets if they already finished. --restart
+runs every phase again. Libraries and assets that failed to download are
+listed in the instance's create-errors.json, with the url, what went wrong and
+how many retries each had. Running create again only downloads what's missing.
+
+--concurrency downloads N assets at once instead of the config's
+assets_concurrency. auto times the first few downloads and picks a number from
+how long they took, from 16 to 64, or up to 100 over HTTP/2.
+
+--timeout aborts the libraries or assets download if it hasn't finished
+within SECONDS, for connections that wedge without ever timing out. The
+config's phase_timeout_secs (an hour by default) does the same for every
+create and repair; 0 turns it off.
+
+--downloader aria2c hands the assets to aria2c, with every mirror and their
+hashes, and downloads whatever it misses itself. Without aria2c installed it
+uses the built-in downloader, which is also the default.
+
+--download-only installs and verifies the instance without copying start.exe
+into it, for provisioning instances in CI or on a build server.
+
+--asset-index-url installs the asset index at URL instead of the one the meta
+names and keeps the instance on it, e.g. during a rollout where the meta
+points at a broken index. --asset-index-id names it, the meta's id by default.
+
+--lwjgl-version swaps the version's LWJGL libraries for VERSION from Maven
+Central and keeps the instance on it, for testing whether another LWJGL works
+around a GPU driver bug. This is unsupported and the game may not start.
+
+--strip-meta keeps only the parts of the meta the launcher reads in the
+instance's version.json, which is much smaller, and saves strip_meta = true in
+the config so updates keep it stripped. Set it to false and update the instance
+to get the full meta back.
+
+--combined-progress, or combined_progress = true in the config, shows one
+progress line for the whole install, the client, libraries and assets
+together, instead of one per download. -v keeps the line per download as well,
+each followed by how far the whole install is.
+
+--prefer-ipv4 and --prefer-ipv6 connect downloads over that IP family first,
+falling back to the other, for networks where one is broken and stalls
+connections before they fall back on their own. ip_preference in the config
+does the same for create, update and repair: system (the default),
+prefer-ipv4, prefer-ipv6, or ipv4-only and ipv6-only to never fall back.
+
+--trust-cache skips checking the instance's files when its instance.lock.json
+matches the version asked for: create doesn't install anything and launch
+skips verify_on_launch. Without a matching lock both carry on as normal.
+
+check_mods = true in the config warns about mods installed twice and mods
+missing a dependency before every launch. --strict-mods checks them too and
+refuses to launch when something is wrong.
+
+launch warns when the OS is clearly too old for the version, like a 32-bit OS
+for a version that needs Java 21. --strict refuses to launch instead.
+
+--game-dir runs the game in DIR, so saves, mods, config and screenshots live
+there while the client, libraries and assets stay in the instance.
+game_directory in the config does the same for every launch, relative to the
+instance.
+
+launch extracts the natives again when their jars changed since the last
+time, and reuses them otherwise. --clean-natives wipes and extracts them
+regardless.
+
+--world opens the save NAME straight away on versions that support quick
+play (1.20 and later).
+
+Accounts with an access token are checked for owning the game first. Those
+that don't launch in demo mode, or not at all with refuse_unowned set. A token
+that has expired, or will within five minutes, is refreshed first with the
+account's refresh_token, and launch stops if that fails.
+
+Versions that ship a log4j older than 2.16 launch with its message lookups
+turned off (Log4Shell), by -Dlog4j2.formatMsgNoLookups=true or a logging config
+of the launcher's own. log4j_mitigation = false in the config skips that.
+
+--gc is checked against the Java launch finds: Oracle's JDK has no
+Shenandoah, and OpenJ9 builds like IBM Semeru pick their own collector, so
+they get no gc flags at all. -XX flags from --jvm-arg and the config's jvm_args
+are tried with that Java first, with a warning if it rejects one.
+
+Each key=value line of jvm.properties in the instance is passed as
+-Dkey=value, ahead of the jvm_args and --jvm-arg so those can override it.
+Lines starting with # are comments.
+
+--wrapper runs java through another command: --wrapper gamemoderun runs
+gamemoderun java .... Each --wrapper adds one argument, so --wrapper mangohud
+--wrapper prime-run chains both. It replaces the config's wrapper_command,
+which does the same for every launch.
+
+--print-command prints the exact java command launch would run, with the
+access token redacted, and exits. It uses the stored session without
+refreshing it or checking ownership, skips verify_on_launch and writes
+nothing to the instance, passing what argfiles would hold inline instead.
+
+The access token is otherwise on the game's command line, where other users
+can see it in the process list. token_argfile = true in the config passes the
+game's arguments in an argfile only the user can read, game.args in the
+instance, instead. It needs Java 9 or newer.
+
+--startup-timeout kills the game if it hasn't logged in within SECONDS, and
+otherwise keeps the launcher attached to it until it exits. Handy for smoke
+tests.
+
+--serve-status serves what create or launch is doing as JSON at
+http://ADDR/status, e.g. --serve-status 127.0.0.1:8080, for a supervising tool
+to poll: the phase (downloading, ready, launching, running or exited), the
+progress of the current download, and the game's pid and exit code. launch
+stays attached to the game while serving. Either keeps serving for 10 seconds
+after it's done so the last status can be read.
+
+on_launch and on_exit in the config are commands run when the game starts and
+when it exits, with BLAZINGLYASSMC_EVENT, BLAZINGLYASSMC_INSTANCE,
+BLAZINGLYASSMC_PID and, on exit, BLAZINGLYASSMC_EXIT_CODE and
+BLAZINGLYASSMC_DURATION_SECS set. --notify-url, or notify_url in the config,
+posts the same events as JSON objects with event (launch or exit), instance,
+pid, exit_code and duration_secs. Either exit hook keeps launch attached to the
+game until it exits.
+
+verify checks the client, libraries and assets against their hashes. Files
+that haven't changed since they last passed aren't hashed again unless
+--no-cache is given.
+
+repair hashes the same files, without the cache, and downloads the missing
+and corrupt ones again. --assets-only and --libraries-only (natives included)
+leave the rest of the instance alone.
+
+server download puts a dedicated server for the version in DIR (server by
+default) with a starter server.properties. Its eula.txt only says yes with
+--accept-eula, after reading https://aka.ms/MinecraftEULA.
+
+config show prints the config a launch would run with, noting where each
+field came from: its default, the file, a BLAZINGLYASSMC_CONFIG_<FIELD>
+environment variable (like BLAZINGLYASSMC_CONFIG_MAX_MEMORY_MB=4096, which
+overrides the file for any launch) or the launch flags given to it. The file
+is only read. --json prints the config and the sources as JSON instead.
+
+du shows how much disk the instance in DIR takes up, split into assets,
+libraries, mods, saves and everything else. Files also linked from outside the
+instance, which deleting it wouldn't free, are counted as shared.
+
+diff compares two instances' versions, loaders, asset indexes, library
+versions, mods and config, from their instance.lock.json and config, and
+prints whatever differs, with - for something only the other one has.
+
+seed-store adds the asset objects and libraries of another launcher's
+directory DIR (a .minecraft, a MultiMC folder or an instance) to the store in
+the data directory, after checking their hashes. Creates link files from the
+store instead of downloading them.
+
+refresh-launchers copies this launcher over the start.exe of every instance
+in each DIR (an instance or a folder of instances, the current directory by
+default), so a launcher update reaches instances created before it. Any that
+can't be replaced, usually because they're running, are skipped.
+
+shortcut adds a shortcut that launches the instance DIR, named after it: on
+the desktop and in the Start menu on Windows, in the applications menu and on
+the desktop on Linux, with the game's icon once its assets are installed.
+
+export zips up an instance's config, options, mods, resource packs, mod
+configs and instance.lock.json into FILE. import unpacks one into DIR
+(instance by default) and downloads the version its lock pins. Config fields
+that run commands or send events (java_path, jvm_args, wrapper_command,
+on_launch, on_exit, notify_url) are cleared unless confirmed at the prompt;
+--yes doesn't keep them. Importing over an instance that already has a config
+asks first.
+
+crashes lists the newest crash reports in each DIR (an instance, the shared
+reports_directory, or a folder of instances), the current directory by
+default.
+
+Commands that destroy something, like update replacing the client and
+libraries, ask first. --yes (or -y), anywhere on the command line, answers yes
+to every question; without it they refuse when stdin isn't a terminal.
+
+Progress, warnings and status messages go to stderr, so stdout only carries
+results: lists, reports and what the --print flags print.
+
+Run from a terminal with no arguments, blazinglyassmc shows a full-screen menu
+(arrow keys and enter) to launch the instances in the current directory,
+create one from the manifest's versions or set the username.
+Otherwise, without a subcommand, blazinglyassmc.exe creates an instance and any
+other executable name (the copied start.exe) launches the instance it lives
+in.";
+
+/// A parsed command line.
+pub struct Cli {
+    /// Answer yes to every confirmation.
+    pub yes: bool,
+    pub command: Command,
+}
+
+pub enum Command {
+    Create(CreateArgs),
+    Launch(LaunchArgs),
+    Accounts(AccountsCommand),
+    Doctor(DoctorArgs),
+    Update(UpdateArgs),
+    Verify(VerifyArgs),
+    Server(ServerArgs),
+    Crashes(CrashesArgs),
+    Export(ExportArgs),
+    Import(ImportArgs),
+    Repair(RepairArgs),
+    Config(ConfigArgs),
+    Versions(VersionsArgs),
+    Du(DuArgs),
+    Diff(DiffArgs),
+    SeedStore(SeedStoreArgs),
+    RefreshLaunchers(RefreshLaunchersArgs),
+    Shortcut(ShortcutArgs),
+}
+
+pub struct DuArgs {
+    pub directory: PathBuf,
+}
+
+pub struct DiffArgs {
+    pub a: PathBuf,
+    pub b: PathBuf,
+}
+
+pub struct RefreshLaunchersArgs {
+    pub directories: Vec<PathBuf>,
+}
+
+pub struct ShortcutArgs {
+    pub directory: PathBuf,
+}
+
+pub struct SeedStoreArgs {
+    pub from: PathBuf,
+}
+
+pub struct VersionsArgs {
+    /// A `YYYY-MM-DD` date.
+    pub since: Option<String>,
+    pub kind: Option<String>,
+    pub refresh_manifest: bool,
+    pub manifest_url: Option<String>,
+}
+
+pub struct ConfigArgs {
+    pub directory: PathBuf,
+    pub json: bool,
+    /// The launch flags that override config fields.
+    pub options: LaunchOptions,
+}
+
+pub struct ExportArgs {
+    pub directory: PathBuf,
+    pub archive: PathBuf,
+}
+
+pub struct ImportArgs {
+    pub archive: PathBuf,
+    pub directory: PathBuf,
+}
+
+pub struct CrashesArgs {
+    pub directories: Vec<PathBuf>,
+    pub limit: usize,
+}
+
+pub enum ServerCommand {
+    List,
+    Add {
+        name: String,
+        address: String,
+    },
+    /// Downloads a dedicated server rather than touching servers.dat.
+    Download {
+        version: String,
+        refresh_manifest: bool,
+        accept_eula: bool,
+    },
+}
+
+pub struct ServerArgs {
+    pub directory: PathBuf,
+    pub command: ServerCommand,
+}
+
+pub struct RepairArgs {
+    pub directory: PathBuf,
+    pub scope: VerifyScope,
+}
+
+pub struct VerifyArgs {
+    pub directory: PathBuf,
+    pub use_cache: bool,
+}
+
+pub struct UpdateArgs {
+    pub directory: PathBuf,
+    pub version: String,
+    pub refresh_manifest: bool,
+    /// Manifest to resolve `version` in, the instance's by default.
+    pub manifest_url: Option<String>,
+}
+
+pub struct DoctorArgs {
+    pub directory: PathBuf,
+}
+
+/// Where to get the meta for a new instance from.
+pub enum MetaSource {
+    Url(String),
+    /// A version id to look up in the version manifest.
+    Version(String),
+}
+
+pub struct CreateArgs {
+    pub meta: MetaSource,
+    pub refresh_manifest: bool,
+    pub directory: PathBuf,
+    pub template: Option<PathBuf>,
+    pub options: InstallOptions,
+    /// Verify the instance instead of copying the launcher into it.
+    pub download_only: bool,
+    pub serve_status: Option<SocketAddr>,
+}
+
+pub struct LaunchArgs {
+    /// Defaults to the directory of the running executable.
+    pub directory: Option<PathBuf>,
+    pub options: LaunchOptions,
+    pub startup_timeout: Option<u64>,
+    /// Print the classpath, one entry per line, instead of launching.
+    pub print_classpath: bool,
+    /// Print the java command line instead of running it.
+    pub print_command: bool,
+    /// Serve the launch's status here, staying attached to the game.
+    pub serve_status: Option<SocketAddr>,
+    /// Post the launch and exit events here, overriding the config's
+    /// `notify_url`.
+    pub notify_url: Option<String>,
+}
+
+pub enum AccountsCommand {
+    List,
+    /// Adds an offline account.
+    Add(String),
+    Remove(String),
+}
+
+fn value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("{} expects a value", flag))
+}
+
+fn parse_address(args: &mut impl Iterator<Item = String>, arg: &str) -> Result<SocketAddr, String> {
+    let address = value(args, arg)?;
+
+    address.parse().map_err(|_| {
+        format!(
+            "{} expects an address like 127.0.0.1:8080, got {}",
+            arg, address
+        )
+    })
+}
+
+fn parse_create(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut create_args = CreateArgs {
+        meta: MetaSource::Url(String::from(MINECRAFT_1_20_4_META_URL)),
+        refresh_manifest: false,
+        directory: PathBuf::from("instance"),
+        template: None,
+        options: InstallOptions {
+            resumable: true,
+            ..InstallOptions::default()
+        },
+        download_only: false,
+        serve_status: None,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--meta-url" => create_args.meta = MetaSource::Url(value(&mut args, &arg)?),
+            "--version" => create_args.meta = MetaSource::Version(value(&mut args, &arg)?),
+            "--refresh-manifest" => create_args.refresh_manifest = true,
+            "--manifest-url" => create_args.options.manifest_url = Some(value(&mut args, &arg)?),
+            "--dir" => create_args.directory = PathBuf::from(value(&mut args, &arg)?),
+            "--template" => create_args.template = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--assets-filter" => create_args
+                .options
+                .assets_filter
+                .get_or_insert_with(AssetFilter::default)
+                .add_pattern(&value(&mut args, &arg)?),
+            "--skip-asset-check" => create_args.options.skip_asset_check = true,
+            "--verify-hashes" => create_args.options.verify_hashes = true,
+            "--no-assets" => create_args.options.no_assets = true,
+            "--trust-cache" => create_args.options.trust_cache = true,
+            "--restart" => create_args.options.restart = true,
+            "--download-only" => create_args.download_only = true,
+            "--downloader" => create_args.options.downloader = value(&mut args, &arg)?.parse()?,
+            "--concurrency" => {
+                create_args.options.concurrency = Some(value(&mut args, &arg)?.parse()?)
+            }
+            "--timeout" => {
+                let seconds = value(&mut args, &arg)?;
+                create_args.options.phase_timeout_secs = Some(
+                    seconds
+                        .parse()
+                        .map_err(|_| format!("--timeout expects seconds, got {}", seconds))?,
+                );
+            }
+            "--asset-index-url" => {
+                create_args.options.asset_index_url = Some(value(&mut args, &arg)?)
+            }
+            "--asset-index-id" => {
+                create_args.options.asset_index_id = Some(value(&mut args, &arg)?)
+            }
+            "--lwjgl-version" => create_args.options.lwjgl_version = Some(value(&mut args, &arg)?),
+            "--strip-meta" => create_args.options.strip_meta = true,
+            "--combined-progress" => create_args.options.combined_progress = true,
+            "-v" | "--verbose" => create_args.options.verbose = true,
+            "--prefer-ipv4" => create_args.options.ip_preference = Some(IpPreference::PreferIpv4),
+            "--prefer-ipv6" => create_args.options.ip_preference = Some(IpPreference::PreferIpv6),
+            "--serve-status" => create_args.serve_status = Some(parse_address(&mut args, &arg)?),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    if create_args.options.asset_index_id.is_some() && create_args.options.asset_index_url.is_none()
+    {
+        return Err(String::from("--asset-index-id needs --asset-index-url"));
+    }
+
+    Ok(Command::Create(create_args))
+}
+
+fn parse_launch(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut launch_args = LaunchArgs {
+        directory: None,
+        options: LaunchOptions::default(),
+        startup_timeout: None,
+        print_classpath: false,
+        print_command: false,
+        serve_status: None,
+        notify_url: None,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dir" => launch_args.directory = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--account" => launch_args.options.account = Some(value(&mut args, &arg)?),
+            "--gc" => launch_args.options.gc = Some(value(&mut args, &arg)?.parse()?),
+            "--jvm-arg" => launch_args.options.jvm_args.push(value(&mut args, &arg)?),
+            "--wrapper" => launch_args.options.wrapper.push(value(&mut args, &arg)?),
+            "--world" => launch_args.options.world = Some(value(&mut args, &arg)?),
+            "--demo" => launch_args.options.demo = true,
+            "--trust-cache" => launch_args.options.trust_cache = true,
+            "--strict-mods" => launch_args.options.strict_mods = true,
+            "--strict" => launch_args.options.strict = true,
+            "--clean-natives" => launch_args.options.clean_natives = true,
+            "--game-dir" => {
+                launch_args.options.game_directory = Some(PathBuf::from(value(&mut args, &arg)?))
+            }
+            "--print-classpath" => launch_args.print_classpath = true,
+            "--print-command" => launch_args.print_command = true,
+            "--serve-status" => launch_args.serve_status = Some(parse_address(&mut args, &arg)?),
+            "--notify-url" => launch_args.notify_url = Some(value(&mut args, &arg)?),
+            "--startup-timeout" => {
+                let seconds = value(&mut args, &arg)?;
+                launch_args.startup_timeout =
+                    Some(seconds.parse().map_err(|_| {
+                        format!("--startup-timeout expects seconds, got {}", seconds)
+                    })?);
+            }
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    Ok(Command::Launch(launch_args))
+}
+
+fn parse_doctor(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut doctor_args = DoctorArgs {
+        directory: PathBuf::from("instance"),
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dir" => doctor_args.directory = PathBuf::from(value(&mut args, &arg)?),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    Ok(Command::Doctor(doctor_args))
+}
+
+fn parse_verify(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut verify_args = VerifyArgs {
+        directory: PathBuf::from("instance"),
+        use_cache: true,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dir" => verify_args.directory = PathBuf::from(value(&mut args, &arg)?),
+            "--no-cache" => verify_args.use_cache = false,
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    Ok(Command::Verify(verify_args))
+}
+
+fn parse_repair(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut directory = None;
+    let mut scope = VerifyScope::All;
+
+    for arg in args.by_ref() {
+        match arg.as_str() {
+            "--assets-only" | "--libraries-only" if scope != VerifyScope::All => {
+                return Err(String::from(
+                    "--assets-only and --libraries-only can't be combined",
+                ))
+            }
+            "--assets-only" => scope = VerifyScope::Assets,
+            "--libraries-only" => scope = VerifyScope::Libraries,
+            _ if directory.is_none() && !arg.starts_with("--") => {
+                directory = Some(PathBuf::from(arg))
+            }
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    Ok(Command::Repair(RepairArgs {
+        directory: directory.ok_or("repair expects an instance directory")?,
+        scope,
+    }))
+}
+
+fn parse_update(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut directory = None;
+    let mut version = None;
+    let mut refresh_manifest = false;
+    let mut manifest_url = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--to" => version = Some(value(&mut args, &arg)?),
+            "--refresh-manifest" => refresh_manifest = true,
+            "--manifest-url" => manifest_url = Some(value(&mut args, &arg)?),
+            _ if directory.is_none() && !arg.starts_with("--") => {
+                directory = Some(PathBuf::from(arg))
+            }
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    Ok(Command::Update(UpdateArgs {
+        directory: directory.ok_or("update expects an instance directory")?,
+        version: version.ok_or("update expects --to")?,
+        refresh_manifest,
+        manifest_url,
+    }))
+}
+
+fn parse_versions(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut versions_args = VersionsArgs {
+        since: None,
+        kind: None,
+        refresh_manifest: false,
+        manifest_url: None,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--since" => {
+                let since = value(&mut args, &arg)?;
+                let is_date = since.len() == 10
+                    && since.char_indices().all(|(i, c)| match i {
+                        4 | 7 => c == '-',
+                        _ => c.is_ascii_digit(),
+                    });
+
+                if !is_date {
+                    return Err(format!("--since expects a YYYY-MM-DD date, got {}", since));
+                }
+
+                versions_args.since = Some(since);
+            }
+            "--type" => versions_args.kind = Some(value(&mut args, &arg)?),
+            "--refresh-manifest" => versions_args.refresh_manifest = true,
+            "--manifest-url" => versions_args.manifest_url = Some(value(&mut args, &arg)?),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    Ok(Command::Versions(versions_args))
+}
+
+fn parse_crashes(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut crashes_args = CrashesArgs {
+        directories: Vec::new(),
+        limit: 10,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--limit" => {
+                let limit = value(&mut args, &arg)?;
+                crashes_args.limit = limit
+                    .parse()
+                    .map_err(|_| format!("--limit expects a number, got {}", limit))?;
+            }
+            _ if !arg.starts_with("--") => crashes_args.directories.push(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    if crashes_args.directories.is_empty() {
+        crashes_args.directories.push(PathBuf::from("."));
+    }
+
+    Ok(Command::Crashes(crashes_args))
+}
+
+fn parse_du(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let directory = PathBuf::from(value(&mut args, "du")?);
+
+    if let Some(arg) = args.next() {
+        return Err(format!("unexpected argument {}", arg));
+    }
+
+    Ok(Command::Du(DuArgs { directory }))
+}
+
+fn parse_shortcut(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let directory = PathBuf::from(value(&mut args, "shortcut")?);
+
+    if let Some(arg) = args.next() {
+        return Err(format!("unexpected argument {}", arg));
+    }
+
+    Ok(Command::Shortcut(ShortcutArgs { directory }))
+}
+
+fn parse_diff(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let a = PathBuf::from(value(&mut args, "diff")?);
+    let b = PathBuf::from(value(&mut args, "diff")?);
+
+    if let Some(arg) = args.next() {
+        return Err(format!("unexpected argument {}", arg));
+    }
+
+    Ok(Command::Diff(DiffArgs { a, b }))
+}
+
+fn parse_seed_store(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut from = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--from" => from = Some(PathBuf::from(value(&mut args, &arg)?)),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    let from = from.ok_or("seed-store needs --from DIR")?;
+
+    Ok(Command::SeedStore(SeedStoreArgs { from }))
+}
+
+fn parse_refresh_launchers(args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut directories = Vec::new();
+
+    for arg in args {
+        match arg.as_str() {
+            _ if !arg.starts_with("--") => directories.push(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    if directories.is_empty() {
+        directories.push(PathBuf::from("."));
+    }
+
+    Ok(Command::RefreshLaunchers(RefreshLaunchersArgs {
+        directories,
+    }))
+}
+
+fn parse_export(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let directory = PathBuf::from(value(&mut args, "export")?);
+    let archive = PathBuf::from(value(&mut args, "export")?);
+
+    if let Some(arg) = args.next() {
+        return Err(format!("unexpected argument {}", arg));
+    }
+
+    Ok(Command::Export(ExportArgs { directory, archive }))
+}
+
+fn parse_import(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let archive = PathBuf::from(value(&mut args, "import")?);
+    let mut directory = PathBuf::from("instance");
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dir" => directory = PathBuf::from(value(&mut args, &arg)?),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    Ok(Command::Import(ImportArgs { archive, directory }))
+}
+
+fn parse_server_download(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut directory = PathBuf::from("server");
+    let mut version = None;
+    let mut refresh_manifest = false;
+    let mut accept_eula = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dir" => directory = PathBuf::from(value(&mut args, &arg)?),
+            "--version" => version = Some(value(&mut args, &arg)?),
+            "--refresh-manifest" => refresh_manifest = true,
+            "--accept-eula" => accept_eula = true,
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    Ok(Command::Server(ServerArgs {
+        directory,
+        command: ServerCommand::Download {
+            version: version.ok_or("server download expects --version")?,
+            refresh_manifest,
+            accept_eula,
+        },
+    }))
+}
+
+fn parse_server(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let command = match args.next().as_deref() {
+        Some("list") => ServerCommand::List,
+        Some("add") => ServerCommand::Add {
+            name: value(&mut args, "server add")?,
+            address: value(&mut args, "server add")?,
+        },
+        Some("download") => return parse_server_download(args),
+        Some(other) => return Err(format!("unknown server command {}", other)),
+        None => return Err(String::from("server expects a command")),
+    };
+    let mut directory = PathBuf::from("instance");
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dir" => directory = PathBuf::from(value(&mut args, &arg)?),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    Ok(Command::Server(ServerArgs { directory, command }))
+}
+
+fn parse_config(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    match args.next().as_deref() {
+        Some("show") => {}
+        Some(other) => return Err(format!("unknown config command {}", other)),
+        None => return Err(String::from("config expects a command")),
+    }
+
+    let mut config_args = ConfigArgs {
+        directory: PathBuf::from("instance"),
+        json: false,
+        options: LaunchOptions::default(),
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dir" => config_args.directory = PathBuf::from(value(&mut args, &arg)?),
+            "--json" => config_args.json = true,
+            "--account" => config_args.options.account = Some(value(&mut args, &arg)?),
+            "--gc" => config_args.options.gc = Some(value(&mut args, &arg)?.parse()?),
+            "--jvm-arg" => config_args.options.jvm_args.push(value(&mut args, &arg)?),
+            "--wrapper" => config_args.options.wrapper.push(value(&mut args, &arg)?),
+            "--game-dir" => {
+                config_args.options.game_directory = Some(PathBuf::from(value(&mut args, &arg)?))
+            }
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    Ok(Command::Config(config_args))
+}
+
+fn parse_accounts(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let command = match args.next().as_deref() {
+        Some("list") => AccountsCommand::List,
+        Some("add") => AccountsCommand::Add(value(&mut args, "accounts add")?),
+        Some("remove") => AccountsCommand::Remove(value(&mut args, "accounts remove")?),
+        Some(other) => return Err(format!("unknown accounts command {}", other)),
+        None => return Err(String::from("accounts expects a command")),
+    };
+
+    if let Some(arg) = args.next() {
+        return Err(format!("unexpected argument {}", arg));
+    }
+
+    Ok(Command::Accounts(command))
+}
+
+/// Parses the arguments after the executable name. With no subcommand the
+/// executable name decides, as it always has.
+pub fn parse(exe_name: &str, mut args: Vec<String>) -> Result<Cli, String> {
+    let count = args.len();
+    args.retain(|arg| arg != "--yes" && arg != "-y");
+    let yes = args.len() != count;
+
+    let mut args = args.into_iter().peekable();
+
+    let command = match args.peek().map(String::as_str) {
+        Some("create") => parse_create(args.skip(1)),
+        Some("launch") => parse_launch(args.skip(1)),
+        Some("accounts") => parse_accounts(args.skip(1)),
+        Some("doctor") => parse_doctor(args.skip(1)),
+        Some("update") => parse_update(args.skip(1)),
+        Some("verify") => parse_verify(args.skip(1)),
+        Some("repair") => parse_repair(args.skip(1)),
+        Some("server") => parse_server(args.skip(1)),
+        Some("crashes") => parse_crashes(args.skip(1)),
+        Some("export") => parse_export(args.skip(1)),
+        Some("import") => parse_import(args.skip(1)),
+        Some("config") => parse_config(args.skip(1)),
+        Some("versions") => parse_versions(args.skip(1)),
+        Some("du") => parse_du(args.skip(1)),
+        Some("diff") => parse_diff(args.skip(1)),
+        Some("seed-store") => parse_seed_store(args.skip(1)),
+        Some("refresh-launchers") => parse_refresh_launchers(args.skip(1)),
+        Some("shortcut") => parse_shortcut(args.skip(1)),
+        _ if exe_name == "blazinglyassmc.exe" => parse_create(args),
+        _ => parse_launch(args),
+    }?;
+
+    Ok(Cli { yes, command })
+}
@@ -0,0 +1,137 @@
+//! Comparing two instances, for tracking down why one behaves differently
+//! from another that looks the same.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use crate::config::load_config;
+use crate::lock::InstanceLock;
+use crate::meta::coordinate_key;
+use crate::mods::installed_mods;
+use crate::{Error, Result};
+
+/// One thing that isn't the same in both instances. `None` is something only
+/// the other instance has.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Difference {
+    /// `version`, `loader`, `asset index`, `library`, `mod` or `config`.
+    pub category: &'static str,
+    /// What differs within the category, like a library's coordinate
+    /// without its version or a config key.
+    pub name: String,
+    pub a: Option<String>,
+    pub b: Option<String>,
+}
+
+/// Everything compared about one instance, keyed by category and name.
+type Facts = BTreeMap<(&'static str, String), String>;
+
+fn joined(values: BTreeSet<String>) -> String {
+    values.into_iter().collect::<Vec<_>>().join(", ")
+}
+
+/// Config values keyed by their dotted path, like `host_overrides.example.com`.
+fn flatten_config(prefix: &str, table: &toml::Table, facts: &mut Facts) {
+    for (key, value) in table {
+        let key = match prefix {
+            "" => key.clone(),
+            prefix => format!("{}.{}", prefix, key),
+        };
+
+        match value {
+            toml::Value::Table(table) => flatten_config(&key, table, facts),
+            toml::Value::String(value) => {
+                facts.insert(("config", key), value.clone());
+            }
+            value => {
+                facts.insert(("config", key), value.to_string());
+            }
+        }
+    }
+}
+
+fn instance_facts(instance_directory: &Path) -> Result<Facts> {
+    let lock = InstanceLock::load(instance_directory)?
+        .ok_or_else(|| Error::MissingLock(instance_directory.to_owned()))?;
+    let config = load_config(instance_directory)?;
+    let mut facts = Facts::new();
+
+    facts.insert(("version", String::new()), lock.version);
+    if let Some(loader) = lock.loader {
+        facts.insert(("loader", String::new()), loader);
+    }
+    if let Some(asset_index) = lock.asset_index {
+        facts.insert(("asset index", String::new()), asset_index);
+    }
+
+    // The same library can be listed more than once, for different OSes.
+    let mut libraries = BTreeMap::<String, BTreeSet<String>>::new();
+    for name in &lock.libraries {
+        let version = name.split(':').nth(2).unwrap_or_default().to_owned();
+        libraries
+            .entry(coordinate_key(name))
+            .or_default()
+            .insert(version);
+    }
+    for (key, versions) in libraries {
+        facts.insert(("library", key), joined(versions));
+    }
+
+    let mut mods = BTreeMap::<String, BTreeSet<String>>::new();
+    for installed in installed_mods(&config.game_directory(instance_directory)) {
+        mods.entry(installed.id)
+            .or_default()
+            .insert(installed.version);
+    }
+    for (id, versions) in mods {
+        facts.insert(("mod", id), joined(versions));
+    }
+
+    if let toml::Value::Table(table) = toml::Value::try_from(&config)? {
+        flatten_config("", &table, &mut facts);
+    }
+
+    Ok(facts)
+}
+
+/// The differences between the instances at `a` and `b` in their version,
+/// loader, asset index, libraries, mods and config, as recorded by their
+/// instance.lock.json and LauncherConfig.toml.
+pub fn compare_instances(a: &Path, b: &Path) -> Result<Vec<Difference>> {
+    let mut a = instance_facts(a)?;
+    let mut b = instance_facts(b)?;
+
+    let keys = a.keys().chain(b.keys()).cloned().collect::<BTreeSet<_>>();
+    let mut differences = Vec::new();
+
+    for key in keys {
+        let (a, b) = (a.remove(&key), b.remove(&key));
+
+        if a != b {
+            let (category, name) = key;
+            differences.push(Difference {
+                category,
+                name,
+                a,
+                b,
+            });
+        }
+    }
+
+    // In the order someone debugging would look at them.
+    let order = [
+        "version",
+        "loader",
+        "asset index",
+        "library",
+        "mod",
+        "config",
+    ];
+    differences.sort_by_key(|difference| {
+        order
+            .iter()
+            .position(|category| *category == difference.category)
+    });
+
+    Ok(differences)
+}
@@ -0,0 +1,580 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::de::{self, Visitor};
+
+use crate::error::{ParseContext, PathContext};
+use crate::fsutil::write_atomic;
+use crate::java::GcPreset;
+use crate::manifest::VERSION_MANIFEST_URL;
+use crate::{Error, Result};
+
+pub const CONFIG_FILE_NAME: &str = "LauncherConfig.toml";
+
+/// Prefix of the environment variables that override config fields for a
+/// launch, like `BLAZINGLYASSMC_CONFIG_MAX_MEMORY_MB=4096`.
+pub const CONFIG_ENV_PREFIX: &str = "BLAZINGLYASSMC_CONFIG_";
+
+pub const OFFICIAL_ASSETS_URL: &str = "https://resources.download.minecraft.net";
+pub const OFFICIAL_LIBRARIES_URL: &str = "https://libraries.minecraft.net";
+
+/// How asset objects are stored under `assets/objects`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetLayout {
+    /// `objects/<first two characters of the hash>/<hash>`, where the game
+    /// looks for them.
+    #[default]
+    Nested,
+    /// `objects/<hash>`. The game gets a nested view of links to them under
+    /// `assets/view` instead.
+    Flat,
+}
+
+/// Which addresses downloads connect to, for hosts that have both IPv4 and
+/// IPv6 ones.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IpPreference {
+    /// In the order the system resolver gives them.
+    #[default]
+    System,
+    /// IPv4 first, falling back to IPv6.
+    PreferIpv4,
+    /// IPv6 first, falling back to IPv4.
+    PreferIpv6,
+    Ipv4Only,
+    Ipv6Only,
+}
+
+impl IpPreference {
+    /// `addresses` as connections should try them.
+    pub(crate) fn apply(self, mut addresses: Vec<SocketAddr>) -> Vec<SocketAddr> {
+        // A stable sort keeps the resolver's order within each family.
+        match self {
+            IpPreference::System => {}
+            IpPreference::PreferIpv4 => addresses.sort_by_key(|address| !address.is_ipv4()),
+            IpPreference::PreferIpv6 => addresses.sort_by_key(|address| !address.is_ipv6()),
+            IpPreference::Ipv4Only => addresses.retain(SocketAddr::is_ipv4),
+            IpPreference::Ipv6Only => addresses.retain(SocketAddr::is_ipv6),
+        }
+        addresses
+    }
+}
+
+/// Fields missing from an instance's file take their default, so configs
+/// written by older versions keep loading as fields are added.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct LauncherConfig {
+    /// Offline username, used when no account is selected.
+    pub username: String,
+    /// Name of the stored account to launch with unless `--account` is given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+    pub gc: GcPreset,
+    /// Reported to the game as `minecraft.launcher.brand`, defaults to this
+    /// crate's name. Some servers inspect it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub launcher_brand: Option<String>,
+    /// Reported to the game as `minecraft.launcher.version`, defaults to this
+    /// crate's version.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub launcher_version: Option<String>,
+    /// Passed to the JVM as `-Xmx`.
+    pub max_memory_mb: u32,
+    /// Java executable to launch with instead of the one on the PATH.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub java_path: Option<String>,
+    /// Extra JVM arguments, placed after the memory and GC flags.
+    pub jvm_args: Vec<String>,
+    /// A command and its arguments to run Java through, like
+    /// `["gamemoderun"]` or `["prime-run"]`. Empty runs Java directly.
+    pub wrapper_command: Vec<String>,
+    /// A command and its arguments to run when the game starts, with the
+    /// event in `BLAZINGLYASSMC_*` environment variables. Empty runs nothing.
+    pub on_launch: Vec<String>,
+    /// Like `on_launch`, run when the game exits, with its exit code and how
+    /// long it ran. Keeps the launcher attached to the game.
+    pub on_exit: Vec<String>,
+    /// URL that the launch and the exit are each posted to as JSON. Keeps the
+    /// launcher attached to the game.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notify_url: Option<String>,
+    /// Directories or jars for `--module-path`, relative to the instance,
+    /// for mods that need modules the game doesn't ship.
+    pub module_path: Vec<String>,
+    /// Modules for `--add-modules`, e.g. `ALL-MODULE-PATH`.
+    pub add_modules: Vec<String>,
+    /// Entry point to launch instead of the meta's `mainClass`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub main_class: Option<String>,
+    /// Base URLs tried in order for asset objects.
+    pub asset_mirrors: Vec<String>,
+    /// Base URLs tried in order for libraries hosted on libraries.minecraft.net.
+    pub library_mirrors: Vec<String>,
+    /// Copy asset objects into the legacy virtual layout instead of linking
+    /// them, for filesystems that don't cope with links.
+    pub copy_legacy_assets: bool,
+    /// Seconds before a download counts as too slow and the next mirror is
+    /// tried.
+    pub download_timeout_secs: u64,
+    /// Seconds the whole libraries or assets phase may take before it's
+    /// aborted, a backstop for downloads wedged on a connection that never
+    /// times out. 0 waits as long as it takes.
+    pub phase_timeout_secs: u64,
+    /// Asset objects downloaded at once. They are small, so many in flight
+    /// keeps the connection busy. Downloads start at a quarter of this and
+    /// ramp up, halving again whenever the server answers 429.
+    pub assets_concurrency: usize,
+    /// Libraries downloaded at once. They are fewer and much larger. Ramps
+    /// up and backs off like `assets_concurrency`.
+    pub libraries_concurrency: usize,
+    /// Downloaded assets written to disk together, on one thread off the
+    /// download tasks. Each lands under its final name only once complete.
+    pub assets_write_batch: usize,
+    /// fsync each asset before it's renamed into place, and its directory
+    /// once per batch. Slower, but nothing is lost to a power cut.
+    pub sync_assets: bool,
+    /// Show the client, libraries and assets downloads as one progress total
+    /// for the whole install instead of a line each.
+    pub combined_progress: bool,
+    /// Download over HTTP/2 from mirrors that support it, with every request
+    /// to a host sharing one connection. `false` sticks to HTTP/1.1 and a
+    /// connection per download in flight.
+    pub http2: bool,
+    /// Whether downloads connect over IPv4 or IPv6 first, or only over one,
+    /// for networks where one of them is broken and stalls connections:
+    /// `system`, `prefer-ipv4`, `prefer-ipv6`, `ipv4-only` or `ipv6-only`.
+    pub ip_preference: IpPreference,
+    /// Retries allowed across a whole create or repair, all downloads
+    /// together. Once they're used up it stops, since that many failures
+    /// means the connection is the problem rather than any one file.
+    pub retry_budget: usize,
+    /// Open files to raise the soft limit to before downloading, as far as
+    /// the hard limit allows. Download concurrency is capped to fit whatever
+    /// limit is in effect. 0 leaves the limit as it is.
+    pub open_files_limit: u64,
+    /// Addresses to use for hosts instead of resolving them, for pinning a
+    /// fast CDN edge when DNS picks a bad one, e.g.
+    /// `"resources.download.minecraft.net" = "203.0.113.7"`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub host_overrides: BTreeMap<String, IpAddr>,
+    /// Turn off log4j's message lookups (Log4Shell) for versions that ship a
+    /// log4j older than 2.16.
+    pub log4j_mitigation: bool,
+    /// Always pass the JVM arguments in an `@argfile` rather than only when
+    /// the command line would be too long for the OS. Needs Java 9 or newer.
+    pub use_argfile: bool,
+    /// Pass the main class and the game's arguments in an `@argfile` only the
+    /// user can read, so the access token doesn't show up in the process
+    /// list. Needs Java 9 or newer.
+    pub token_argfile: bool,
+    /// Verify the instance's files before every launch. Cheap after the first
+    /// time thanks to the checksum cache.
+    pub verify_on_launch: bool,
+    /// Look for duplicate mods and missing dependencies in `mods` before
+    /// every launch and warn about them.
+    pub check_mods: bool,
+    /// Refuse to launch accounts that don't own the game instead of starting
+    /// the demo.
+    pub refuse_unowned: bool,
+    /// Shared directory for crash reports and logs. Each instance gets its
+    /// own folder in it, linked from its `crash-reports` and `logs`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reports_directory: Option<String>,
+    /// A 64x64 or 64x32 PNG to play with offline, relative to the instance.
+    /// It needs a skin loader mod such as OfflineSkins or CustomSkinLoader.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skin_path: Option<String>,
+    /// Initial window size. Both have to be set for either to apply.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub window_width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub window_height: Option<u32>,
+    /// `guiScale` for a fresh options.txt, for high-DPI screens where the
+    /// game's default is tiny. 0 is the game's "auto".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gui_scale: Option<u32>,
+    /// Where the game keeps saves, mods, config and screenshots, when not in
+    /// the instance itself. Relative paths are from the instance.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub game_directory: Option<String>,
+    /// Version manifest to look versions up in instead of Mojang's, for
+    /// custom client builds or a mirror. Used by update and to resolve the
+    /// versions loader metas inherit from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest_url: Option<String>,
+    /// Asset index to install instead of the one the meta points at, to keep
+    /// an instance on a known-good index.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset_index_url: Option<String>,
+    /// Id to save that index under and pass to the game. The meta's by
+    /// default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset_index_id: Option<String>,
+    /// LWJGL version to use instead of the one the meta lists, from Maven
+    /// Central, for trying out a fix for a GPU driver bug. Unsupported: the
+    /// game may crash or fail to start. Unset it and update the instance to
+    /// go back.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lwjgl_version: Option<String>,
+    /// Keep only the parts of the meta the launcher reads in version.json,
+    /// for instances that get copied around. Newer launchers may need the
+    /// rest, so the full meta is the default.
+    pub strip_meta: bool,
+    /// `flat` for tools that expect every object in one directory. Changing
+    /// it downloads the assets again in the new layout.
+    pub asset_layout: AssetLayout,
+}
+
+impl Default for LauncherConfig {
+    fn default() -> Self {
+        Self {
+            username: String::from("Username"),
+            account: None,
+            gc: GcPreset::default(),
+            launcher_brand: None,
+            launcher_version: None,
+            max_memory_mb: 2048,
+            java_path: None,
+            jvm_args: Vec::new(),
+            wrapper_command: Vec::new(),
+            on_launch: Vec::new(),
+            on_exit: Vec::new(),
+            notify_url: None,
+            module_path: Vec::new(),
+            add_modules: Vec::new(),
+            main_class: None,
+            asset_mirrors: vec![String::from(OFFICIAL_ASSETS_URL)],
+            library_mirrors: vec![String::from(OFFICIAL_LIBRARIES_URL)],
+            copy_legacy_assets: false,
+            download_timeout_secs: 30,
+            phase_timeout_secs: 3600,
+            assets_concurrency: 16,
+            libraries_concurrency: 4,
+            assets_write_batch: 64,
+            sync_assets: false,
+            combined_progress: false,
+            http2: true,
+            ip_preference: IpPreference::System,
+            retry_budget: 100,
+            open_files_limit: 4096,
+            host_overrides: BTreeMap::new(),
+            log4j_mitigation: true,
+            use_argfile: false,
+            token_argfile: false,
+            verify_on_launch: false,
+            check_mods: false,
+            refuse_unowned: false,
+            reports_directory: None,
+            skin_path: None,
+            window_width: None,
+            window_height: None,
+            gui_scale: None,
+            game_directory: None,
+            manifest_url: None,
+            asset_index_url: None,
+            asset_index_id: None,
+            lwjgl_version: None,
+            strip_meta: false,
+            asset_layout: AssetLayout::Nested,
+        }
+    }
+}
+
+/// Writes `config` into the instance unless it already has a config, and
+/// returns whichever config the instance ends up with.
+pub fn create_config(instance_directory: &Path, config: &LauncherConfig) -> Result<LauncherConfig> {
+    let config_path = instance_directory.join(CONFIG_FILE_NAME);
+
+    if config_path.exists() {
+        return load_config(instance_directory);
+    }
+
+    write_atomic(&config_path, toml::to_string(config)?).with_path(&config_path)?;
+
+    Ok(config.clone())
+}
+
+/// Overwrites the instance config with `config`.
+pub fn save_config(instance_directory: &Path, config: &LauncherConfig) -> Result<()> {
+    let config_path = instance_directory.join(CONFIG_FILE_NAME);
+    write_atomic(&config_path, toml::to_string(config)?).with_path(&config_path)?;
+
+    Ok(())
+}
+
+/// A deserializer that only records the field names a derived
+/// `Deserialize` for a struct asks for.
+struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+impl<'de> de::Deserializer<'de> for FieldNames<'_> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _: V) -> std::result::Result<V::Value, Self::Error> {
+        Err(de::Error::custom("not a struct"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        fields: &'static [&'static str],
+        _: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        *self.0 = fields;
+        Err(de::Error::custom("only the field names were wanted"))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+        ignored_any
+    }
+}
+
+/// Every field a config can have, including the optional ones that aren't
+/// written while unset.
+pub(crate) fn config_fields() -> &'static [&'static str] {
+    let mut fields: &'static [&'static str] = &[];
+    let _ = <LauncherConfig as serde::Deserialize>::deserialize(FieldNames(&mut fields));
+    fields
+}
+
+fn toml_type(value: &toml::Value) -> &'static str {
+    match value {
+        toml::Value::String(_) => "a string",
+        toml::Value::Integer(_) => "an integer",
+        toml::Value::Float(_) => "a number",
+        toml::Value::Boolean(_) => "true or false",
+        toml::Value::Datetime(_) => "a date",
+        toml::Value::Array(_) => "a list",
+        toml::Value::Table(_) => "a table",
+    }
+}
+
+/// Why `on_disk` isn't a valid config, naming the first field to blame, like
+/// `max_memory_mb must be an integer, got a string`.
+fn config_problem(on_disk: &toml::Table, error: toml::de::Error) -> String {
+    let defaults = toml::Table::try_from(LauncherConfig::default()).unwrap_or_default();
+
+    for (key, value) in on_disk {
+        let field = toml::Table::from_iter([(key.clone(), value.clone())]);
+        let Err(e) = field.try_into::<LauncherConfig>() else {
+            continue;
+        };
+
+        return match defaults.get(key) {
+            Some(default) if toml_type(default) != toml_type(value) => format!(
+                "{} must be {}, got {}",
+                key,
+                toml_type(default),
+                toml_type(value)
+            ),
+            _ => format!("{}: {}", key, e.message()),
+        };
+    }
+
+    error.message().to_owned()
+}
+
+/// Configs already warned about, so loading one several times in a launch
+/// doesn't repeat the warnings.
+static WARNED_CONFIGS: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+/// Points out fields the config doesn't have, most likely typos, which would
+/// otherwise be silently ignored.
+fn warn_unknown_fields(config_path: &Path, on_disk: &toml::Table) {
+    let Ok(mut warned) = WARNED_CONFIGS.lock() else {
+        return;
+    };
+    if !warned.insert(config_path.to_owned()) {
+        return;
+    }
+
+    let fields = config_fields();
+    for key in on_disk.keys().filter(|key| !fields.contains(&key.as_str())) {
+        eprintln!(
+            "warning: ignoring unknown config field {} in {}",
+            key,
+            config_path.display()
+        );
+    }
+}
+
+/// `migrated` with the fields of `on_disk` the config doesn't have put back,
+/// so a typo survives the rewrite until it's fixed. Plain values go before
+/// the config's own, tables after them, where they can't capture its fields.
+fn keep_unknown_fields(migrated: String, on_disk: toml::Table) -> Result<String> {
+    let fields = config_fields();
+    let is_table = |value: &toml::Value| match value {
+        toml::Value::Table(_) => true,
+        toml::Value::Array(values) => {
+            !values.is_empty() && values.iter().all(toml::Value::is_table)
+        }
+        _ => false,
+    };
+
+    let (tables, values): (toml::Table, toml::Table) = on_disk
+        .into_iter()
+        .filter(|(key, _)| !fields.contains(&key.as_str()))
+        .partition(|(_, value)| is_table(value));
+
+    let mut contents = toml::to_string(&values)?;
+    contents.push_str(&migrated);
+    if !tables.is_empty() {
+        contents.push('\n');
+        contents.push_str(&toml::to_string(&tables)?);
+    }
+
+    Ok(contents)
+}
+
+/// The config at `config_path`, and the table it was parsed from.
+pub(crate) fn parse_config(config_path: &Path) -> Result<(LauncherConfig, toml::Table)> {
+    let config_str = fs::read_to_string(config_path).with_path(config_path)?;
+
+    let on_disk: toml::Table = toml::from_str(&config_str).parsing(config_path.display())?;
+    match on_disk.clone().try_into() {
+        Ok(config) => Ok((config, on_disk)),
+        Err(e) => Err(Error::InvalidConfig {
+            reason: config_problem(&on_disk, e),
+            path: config_path.to_owned(),
+        }),
+    }
+}
+
+/// Reads the instance config as [`load_config`] does, but leaves the file as
+/// it is and keeps quiet about unknown fields, for looking at instances
+/// without touching them.
+pub fn read_config(instance_directory: &Path) -> Result<LauncherConfig> {
+    parse_config(&instance_directory.join(CONFIG_FILE_NAME)).map(|(config, _)| config)
+}
+
+/// Lays the [`CONFIG_ENV_PREFIX`] environment variables over `config`, each
+/// value read as TOML, or as a string when it isn't one. Returns the fields
+/// they set.
+pub fn apply_environment(config: &mut LauncherConfig) -> Result<Vec<&'static str>> {
+    let fields = config_fields();
+    let mut table: toml::Table = toml::from_str(&toml::to_string(&*config)?)?;
+    let mut set = Vec::new();
+
+    for (name, value) in env::vars_os() {
+        let (Some(name), Some(value)) = (name.to_str(), value.to_str()) else {
+            continue;
+        };
+        let Some(field) = name.strip_prefix(CONFIG_ENV_PREFIX) else {
+            continue;
+        };
+        let Some(field) = fields
+            .iter()
+            .find(|known| known.eq_ignore_ascii_case(field))
+        else {
+            eprintln!("warning: ignoring {}, the config has no such field", name);
+            continue;
+        };
+
+        let value = toml::from_str::<toml::Table>(&format!("value = {}", value))
+            .ok()
+            .and_then(|mut parsed| parsed.remove("value"))
+            .unwrap_or_else(|| toml::Value::String(value.to_owned()));
+        table.insert(field.to_string(), value);
+        set.push(*field);
+    }
+
+    if !set.is_empty() {
+        *config = table
+            .try_into()
+            .parsing(format!("{}* environment variables", CONFIG_ENV_PREFIX))?;
+    }
+
+    Ok(set)
+}
+
+/// Loads the instance config. When the file predates some of the fields, it
+/// is rewritten with their defaults filled in so they can be edited.
+pub fn load_config(instance_directory: &Path) -> Result<LauncherConfig> {
+    let config_path = instance_directory.join(CONFIG_FILE_NAME);
+    let (config, on_disk) = parse_config(&config_path)?;
+    warn_unknown_fields(&config_path, &on_disk);
+
+    let migrated_str = toml::to_string(&config)?;
+    let migrated: toml::Table = toml::from_str(&migrated_str)?;
+
+    if migrated.keys().any(|key| !on_disk.contains_key(key)) {
+        write_atomic(&config_path, keep_unknown_fields(migrated_str, on_disk)?)
+            .with_path(&config_path)?;
+    }
+
+    Ok(config)
+}
+
+impl LauncherConfig {
+    pub fn launcher_brand(&self) -> &str {
+        self.launcher_brand
+            .as_deref()
+            .unwrap_or(env!("CARGO_PKG_NAME"))
+    }
+
+    /// The directory the game is run in, which is the instance itself unless
+    /// `game_directory` is set.
+    pub fn game_directory(&self, instance_directory: &Path) -> PathBuf {
+        match &self.game_directory {
+            Some(game_directory) => instance_directory.join(game_directory),
+            None => instance_directory.to_owned(),
+        }
+    }
+
+    /// The version manifest to resolve versions with, Mojang's unless
+    /// `manifest_url` is set.
+    pub fn manifest_url(&self) -> &str {
+        self.manifest_url.as_deref().unwrap_or(VERSION_MANIFEST_URL)
+    }
+
+    /// The Java executable to launch the game with.
+    pub fn java_command(&self) -> &str {
+        match &self.java_path {
+            Some(java_path) => java_path,
+            None if cfg!(windows) => "javaw",
+            None => "java",
+        }
+    }
+
+    pub fn launcher_version(&self) -> &str {
+        self.launcher_version
+            .as_deref()
+            .unwrap_or(env!("CARGO_PKG_VERSION"))
+    }
+
+    /// The set fields that have launching run something besides the game or
+    /// send anything anywhere, which a config from someone else shouldn't be
+    /// trusted with.
+    pub fn command_fields(&self) -> Vec<&'static str> {
+        [
+            ("java_path", self.java_path.is_some()),
+            ("jvm_args", !self.jvm_args.is_empty()),
+            ("wrapper_command", !self.wrapper_command.is_empty()),
+            ("on_launch", !self.on_launch.is_empty()),
+            ("on_exit", !self.on_exit.is_empty()),
+            ("notify_url", self.notify_url.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(field, set)| set.then_some(field))
+        .collect()
+    }
+
+    /// Resets the fields [`command_fields`](Self::command_fields) lists.
+    pub fn clear_commands(&mut self) {
+        let defaults = Self::default();
+        self.java_path = defaults.java_path;
+        self.jvm_args = defaults.jvm_args;
+        self.wrapper_command = defaults.wrapper_command;
+        self.on_launch = defaults.on_launch;
+        self.on_exit = defaults.on_exit;
+        self.notify_url = defaults.notify_url;
+    }
+}
@@ -0,0 +1,29 @@
+//! Asking before commands destroy anything.
+
+use std::io::{self, IsTerminal, Write};
+
+use blazinglyassmc::{Error, Result};
+
+/// Asks whether to go ahead with `action`. `yes` (from `--yes`) answers for
+/// the user. Without it a stdin that isn't a terminal answers no, so scripts
+/// never destroy anything they didn't ask to.
+pub fn confirm(action: &str, yes: bool) -> Result<()> {
+    if yes {
+        return Ok(());
+    }
+
+    if !io::stdin().is_terminal() {
+        return Err(Error::NotConfirmed(action.to_owned()));
+    }
+
+    eprint!("{}? [y/N]: ", action);
+    io::stderr().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+
+    match answer.trim() {
+        "y" | "Y" | "yes" => Ok(()),
+        _ => Err(Error::NotConfirmed(action.to_owned())),
+    }
+}
@@ -0,0 +1,127 @@
+//! Finding and summarising the game's crash reports.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::config::LauncherConfig;
+use crate::fsutil::redirect_directory;
+use crate::Result;
+
+pub const CRASH_REPORTS_DIRECTORY: &str = "crash-reports";
+pub const LOGS_DIRECTORY: &str = "logs";
+
+#[derive(Clone, Debug)]
+pub struct CrashReport {
+    pub path: PathBuf,
+    /// Name of the directory the report's `crash-reports` folder is in.
+    pub instance: String,
+    /// The report's own `Time:` line.
+    pub time: String,
+    pub description: String,
+    /// The first line of the stack trace, e.g. `java.lang.NullPointerException: ...`.
+    pub exception: String,
+    pub modified: SystemTime,
+}
+
+/// Points the crash report and log folders of the game directory at the
+/// configured shared directory. Nothing happens when none is configured.
+pub fn redirect_reports(game_directory: &Path, config: &LauncherConfig) -> Result<()> {
+    let Some(reports_directory) = &config.reports_directory else {
+        return Ok(());
+    };
+
+    let instance = std::path::absolute(game_directory)?;
+    let target = Path::new(reports_directory).join(instance.file_name().unwrap_or_default());
+
+    for directory in [CRASH_REPORTS_DIRECTORY, LOGS_DIRECTORY] {
+        redirect_directory(&instance.join(directory), &target.join(directory))?;
+    }
+
+    Ok(())
+}
+
+fn parse_crash_report(path: &Path, instance: &str) -> Option<CrashReport> {
+    let report = fs::read_to_string(path).ok()?;
+    let mut lines = report.lines();
+
+    let field = |name: &str| {
+        report
+            .lines()
+            .find_map(|line| line.strip_prefix(name))
+            .unwrap_or_default()
+            .trim()
+            .to_owned()
+    };
+
+    // The exception is the first line after the description.
+    let exception = lines
+        .by_ref()
+        .find(|line| line.starts_with("Description:"))
+        .and_then(|_| lines.find(|line| !line.trim().is_empty()))
+        .unwrap_or_default()
+        .trim()
+        .to_owned();
+
+    Some(CrashReport {
+        path: path.to_owned(),
+        instance: instance.to_owned(),
+        time: field("Time:"),
+        description: field("Description:"),
+        exception,
+        modified: fs::metadata(path).and_then(|m| m.modified()).ok()?,
+    })
+}
+
+fn reports_in(directory: &Path, reports: &mut Vec<CrashReport>) {
+    let crash_reports = directory.join(CRASH_REPORTS_DIRECTORY);
+    let Ok(entries) = fs::read_dir(&crash_reports) else {
+        return;
+    };
+
+    let instance = std::path::absolute(directory)
+        .ok()
+        .and_then(|directory| {
+            directory
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+        .unwrap_or_default();
+
+    reports.extend(
+        entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "txt"))
+            .filter_map(|path| parse_crash_report(&path, &instance)),
+    );
+}
+
+/// Crash reports under each of `roots`, newest first. A root can be an
+/// instance, a shared reports directory, or a folder of either.
+pub fn find_crash_reports(roots: &[PathBuf]) -> Vec<CrashReport> {
+    let mut reports = Vec::new();
+
+    for root in roots {
+        reports_in(root, &mut reports);
+
+        for entry in fs::read_dir(root)
+            .into_iter()
+            .flatten()
+            .filter_map(|e| e.ok())
+        {
+            if entry.path().is_dir() && entry.file_name() != CRASH_REPORTS_DIRECTORY {
+                reports_in(&entry.path(), &mut reports);
+            }
+        }
+    }
+
+    // An instance and the shared directory its reports are linked to can
+    // both be given.
+    let mut seen = HashSet::new();
+    reports.retain(|report| seen.insert(report.path.canonicalize().unwrap_or_default()));
+
+    reports.sort_by_key(|report| std::cmp::Reverse(report.modified));
+    reports
+}
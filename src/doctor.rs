@@ -0,0 +1,262 @@
+use std::path::Path;
+use std::time::Duration;
+
+use crate::config::{load_config, CONFIG_FILE_NAME};
+use crate::java::detect_java;
+use crate::lock::InstanceLock;
+use crate::meta::{load_instance_meta, parse_libraries, Library, META_FILE_NAME};
+
+const REACHABILITY_URLS: &[&str] = &[
+    "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json",
+    "https://libraries.minecraft.net/",
+    "https://resources.download.minecraft.net/",
+];
+
+// A full install with assets is a little under a gigabyte.
+const MIN_FREE_BYTES: u64 = 1024 * 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug)]
+pub struct Check {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// What to do about it, for checks that didn't pass.
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &str, detail: String) -> Self {
+        Self {
+            name: name.to_owned(),
+            status: CheckStatus::Pass,
+            detail,
+            hint: None,
+        }
+    }
+
+    fn problem(name: &str, status: CheckStatus, detail: String, hint: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            status,
+            detail,
+            hint: Some(hint.to_owned()),
+        }
+    }
+}
+
+/// Usernames the game accepts: 3 to 16 letters, digits and underscores.
+pub fn is_valid_username(username: &str) -> bool {
+    (3..=16).contains(&username.len())
+        && username
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn check_config(instance_directory: &Path) -> Check {
+    let name = "config";
+
+    match load_config(instance_directory) {
+        Ok(config) if is_valid_username(&config.username) => {
+            Check::pass(name, format!("username {}", config.username))
+        }
+        Ok(config) => Check::problem(
+            name,
+            CheckStatus::Fail,
+            format!(
+                "username {:?} is not a valid Minecraft username",
+                config.username
+            ),
+            "use 3 to 16 letters, digits or underscores",
+        ),
+        Err(e) => Check::problem(
+            name,
+            CheckStatus::Fail,
+            e.to_string(),
+            &format!("fix or delete {} to regenerate it", CONFIG_FILE_NAME),
+        ),
+    }
+}
+
+fn check_java(instance_directory: &Path) -> Check {
+    let name = "java";
+
+    let java_path = load_config(instance_directory)
+        .ok()
+        .and_then(|config| config.java_path);
+    let java = java_path.as_deref().unwrap_or("java");
+
+    let required = load_instance_meta(instance_directory)
+        .ok()
+        .and_then(|meta| meta["javaVersion"]["majorVersion"].as_u64());
+
+    match (detect_java(java), required) {
+        (Ok(found), Some(required)) if (found.major as u64) < required => Check::problem(
+            name,
+            CheckStatus::Fail,
+            format!(
+                "found Java {}, this version needs Java {}",
+                found.version, required
+            ),
+            "install a newer Java or point java_path at one",
+        ),
+        (Ok(found), _) => Check::pass(name, format!("{} Java {}", found.vendor, found.version)),
+        (Err(e), _) => Check::problem(
+            name,
+            CheckStatus::Fail,
+            e.to_string(),
+            "install Java or set java_path in the config",
+        ),
+    }
+}
+
+async fn check_network(http_client: &reqwest::Client) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    for url in REACHABILITY_URLS {
+        let host = url.split('/').nth(2).unwrap_or(url);
+
+        // Any response at all means the host is reachable.
+        let check = match http_client
+            .head(*url)
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+        {
+            Ok(response) => Check::pass(host, format!("reachable ({})", response.status())),
+            Err(e) => Check::problem(
+                host,
+                CheckStatus::Fail,
+                e.to_string(),
+                "check your connection, proxy or firewall",
+            ),
+        };
+
+        checks.push(check);
+    }
+
+    checks
+}
+
+#[cfg(unix)]
+fn free_space(path: &Path) -> Option<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+
+    #[allow(clippy::unnecessary_cast)]
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn free_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+fn check_disk(instance_directory: &Path) -> Check {
+    let name = "disk";
+
+    // The instance may not exist yet; look at the closest directory that does.
+    let existing = instance_directory
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .unwrap_or(Path::new("."));
+
+    match free_space(existing) {
+        Some(free) if free < MIN_FREE_BYTES => Check::problem(
+            name,
+            CheckStatus::Warn,
+            format!("{} MiB free", free / 1024 / 1024),
+            "free up at least a gigabyte before creating an instance",
+        ),
+        Some(free) => Check::pass(name, format!("{} MiB free", free / 1024 / 1024)),
+        None => Check::problem(
+            name,
+            CheckStatus::Warn,
+            String::from("could not determine free space"),
+            "make sure there is at least a gigabyte free",
+        ),
+    }
+}
+
+fn check_instance(instance_directory: &Path) -> Check {
+    let name = "instance";
+    let recreate = "run create again to download the missing files";
+
+    let meta = match load_instance_meta(instance_directory) {
+        Ok(meta) => meta,
+        Err(e) => {
+            return Check::problem(
+                name,
+                CheckStatus::Fail,
+                format!("could not read {}: {}", META_FILE_NAME, e),
+                recreate,
+            )
+        }
+    };
+
+    if let Ok(Some(lock)) = InstanceLock::load(instance_directory) {
+        if let Err(e) = lock.check_meta(&meta) {
+            return Check::problem(name, CheckStatus::Fail, e.to_string(), recreate);
+        }
+    }
+
+    let client_jar = instance_directory.join("client.jar");
+    let client_size = meta["downloads"]["client"]["size"].as_u64();
+    let client_ok = client_jar
+        .metadata()
+        .is_ok_and(|metadata| Some(metadata.len()) == client_size);
+
+    if !client_ok {
+        return Check::problem(
+            name,
+            CheckStatus::Fail,
+            String::from("client.jar is missing or has the wrong size"),
+            recreate,
+        );
+    }
+
+    let libraries_directory = instance_directory.join("libraries");
+    let missing_libraries = parse_libraries(&meta)
+        .unwrap_or_default()
+        .iter()
+        .filter(|library| library.applies())
+        .flat_map(Library::artifacts)
+        .filter(|artifact| !libraries_directory.join(&artifact.path).exists())
+        .count();
+
+    if missing_libraries > 0 {
+        return Check::problem(
+            name,
+            CheckStatus::Fail,
+            format!("{} libraries are missing", missing_libraries),
+            recreate,
+        );
+    }
+
+    Check::pass(name, String::from("client and libraries present"))
+}
+
+/// Runs every check against the instance at `instance_directory`.
+pub async fn run_doctor(http_client: &reqwest::Client, instance_directory: &Path) -> Vec<Check> {
+    let mut checks = vec![
+        check_java(instance_directory),
+        check_config(instance_directory),
+    ];
+    checks.extend(check_network(http_client).await);
+    checks.push(check_disk(instance_directory));
+    checks.push(check_instance(instance_directory));
+    checks
+}
@@ -0,0 +1,1526 @@
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::future::Future;
+use std::io::{self, Write};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::StreamExt;
+use tokio::sync::mpsc;
+
+use crate::config::{
+    AssetLayout, IpPreference, LauncherConfig, OFFICIAL_ASSETS_URL, OFFICIAL_LIBRARIES_URL,
+};
+use crate::error::PathContext;
+use crate::filter::AssetFilter;
+use crate::fsutil::{
+    file_stamp, link_or_copy, raise_open_files_limit, stays_inside, temp_path, write_atomic,
+};
+use crate::meta::{embedded_asset_index, fetch_json, Library, ASSET_INDEX_REQUIRED_KEYS};
+use crate::progress::{Progress, ProgressDisplay};
+use crate::sha1::{sha1_file, Sha1};
+use crate::store::take_from_store;
+use crate::throttle::ConcurrencyLimit;
+use crate::zip::ZipArchive;
+use crate::{Error, InstallOptions, Result};
+
+pub struct AssetIndexDownload<'a> {
+    pub id: &'a str,
+    pub url: &'a str,
+}
+
+/// The client or server jar from a meta's `downloads`.
+#[derive(Clone, Copy)]
+pub struct JarDownload<'a> {
+    pub file_name: &'a str,
+    pub url: &'a str,
+    pub sha1: &'a str,
+    pub size: u64,
+}
+
+pub fn http_client() -> Result<reqwest::Client> {
+    Ok(client_builder().build()?)
+}
+
+/// [`http_client`] with the config's `host_overrides` pinned, for the
+/// downloads of an instance. Hosts that offer HTTP/2 get every download
+/// multiplexed over one connection, unless the config's `http2` is off.
+pub fn http_client_for(config: &LauncherConfig) -> Result<reqwest::Client> {
+    let builder = config
+        .host_overrides
+        .iter()
+        .fold(client_builder(), |builder, (host, ip)| {
+            // The port is ignored; requests keep using the URL's.
+            builder.resolve(host, SocketAddr::new(*ip, 0))
+        });
+
+    let builder = match config.ip_preference {
+        IpPreference::System => builder,
+        preference => builder.dns_resolver(Arc::new(PreferenceResolver(preference))),
+    };
+
+    let builder = if config.http2 {
+        // Lets a stream's window grow past the 64 KiB default, so the
+        // larger objects aren't held back by it.
+        builder.http2_adaptive_window(true)
+    } else {
+        builder.http1_only()
+    };
+
+    Ok(builder.build()?)
+}
+
+/// Resolves hosts like the system does, then orders or filters their
+/// addresses by an [`IpPreference`]. Connections try the first address's
+/// family first and only fall back to the other after a delay, so a broken
+/// family that comes first stalls every new connection.
+struct PreferenceResolver(IpPreference);
+
+impl reqwest::dns::Resolve for PreferenceResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let preference = self.0;
+
+        Box::pin(async move {
+            let host = name.as_str().to_owned();
+            let addresses = tokio::task::spawn_blocking(move || {
+                (host.as_str(), 0)
+                    .to_socket_addrs()
+                    .map(Iterator::collect::<Vec<_>>)
+            })
+            .await??;
+
+            // Only the -only preferences drop addresses.
+            let addresses = preference.apply(addresses);
+            if addresses.is_empty() {
+                let family = match preference {
+                    IpPreference::Ipv6Only => "IPv6",
+                    _ => "IPv4",
+                };
+                return Err(format!("{} has no {} address", name.as_str(), family).into());
+            }
+
+            Ok(Box::new(addresses.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+fn client_builder() -> reqwest::ClientBuilder {
+    // Metas and asset indexes compress well, and mirrors that compress them
+    // get them decoded transparently.
+    reqwest::Client::builder()
+        .user_agent(concat!(
+            env!("CARGO_PKG_NAME"),
+            "/",
+            env!("CARGO_PKG_VERSION")
+        ))
+        .gzip(true)
+        .deflate(true)
+}
+
+const ATTEMPTS_PER_MIRROR: u32 = 3;
+
+/// Where a create that couldn't download everything lists what failed.
+pub const ERROR_REPORT_FILE_NAME: &str = "create-errors.json";
+
+/// Retries left for a whole run, shared by every download in it, so a broken
+/// network fails fast rather than after every file has had its own retries.
+#[derive(Debug)]
+pub struct RetryBudget {
+    left: AtomicUsize,
+    total: usize,
+}
+
+impl RetryBudget {
+    pub fn new(total: usize) -> Self {
+        Self {
+            left: AtomicUsize::new(total),
+            total,
+        }
+    }
+
+    /// Takes one retry, failing with [`Error::TooManyFailures`] once there
+    /// are none left.
+    fn spend(&self) -> Result<()> {
+        self.left
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| {
+                left.checked_sub(1)
+            })
+            .map(|_| ())
+            .map_err(|_| Error::TooManyFailures(self.total))
+    }
+}
+
+/// A download that failed for good: the last url tried, and how many
+/// retries it had before giving up.
+struct FetchFailure {
+    error: Error,
+    /// What last went wrong with the file itself, when it was the run
+    /// running out of retries that stopped it.
+    cause: Option<Error>,
+    url: String,
+    retried: u32,
+}
+
+impl From<FetchFailure> for Error {
+    fn from(failure: FetchFailure) -> Self {
+        failure.error
+    }
+}
+
+/// One entry of [`ERROR_REPORT_FILE_NAME`].
+#[derive(Debug, serde::Serialize)]
+struct FailedDownload {
+    /// Relative to the instance.
+    path: PathBuf,
+    url: String,
+    /// A short name for what went wrong, like `http 404` or `timeout`.
+    kind: String,
+    error: String,
+    retries: u32,
+}
+
+fn failure_kind(error: &Error) -> String {
+    match error {
+        Error::Network { source, .. } => match source.status() {
+            Some(status) => format!("http {}", status.as_u16()),
+            None if source.is_timeout() => String::from("timeout"),
+            None if source.is_connect() => String::from("connect"),
+            None => String::from("network"),
+        },
+        Error::InvalidResponse { .. } => String::from("invalid response"),
+        Error::Verification { .. } => String::from("checksum"),
+        _ => String::from("other"),
+    }
+}
+
+/// What every download of one create or repair shares: the retries left,
+/// the files that failed for good, and whether the server answered over
+/// HTTP/2.
+#[derive(Debug)]
+pub struct DownloadRun {
+    retries: RetryBudget,
+    failures: Mutex<Vec<FailedDownload>>,
+    multiplexed: AtomicBool,
+}
+
+impl DownloadRun {
+    pub fn new(retry_budget: usize) -> Self {
+        Self {
+            retries: RetryBudget::new(retry_budget),
+            failures: Mutex::new(Vec::new()),
+            multiplexed: AtomicBool::new(false),
+        }
+    }
+
+    /// Whether a download so far came over HTTP/2, so the downloads in
+    /// flight share a connection instead of taking one each.
+    fn multiplexed(&self) -> bool {
+        self.multiplexed.load(Ordering::Relaxed)
+    }
+
+    fn record(&self, path: &Path, failure: &FetchFailure) {
+        let error = failure.cause.as_ref().unwrap_or(&failure.error);
+
+        if let Ok(mut failures) = self.failures.lock() {
+            failures.push(FailedDownload {
+                path: path.to_owned(),
+                url: failure.url.clone(),
+                kind: failure_kind(error),
+                error: error.to_string(),
+                retries: failure.retried,
+            });
+        }
+    }
+
+    /// Lists the failed downloads in [`ERROR_REPORT_FILE_NAME`] in
+    /// `instance_directory`, or removes the one an earlier run left when
+    /// nothing failed.
+    pub fn save_report(&self, instance_directory: &Path) -> Result<()> {
+        let path = instance_directory.join(ERROR_REPORT_FILE_NAME);
+        let Ok(mut failures) = self.failures.lock() else {
+            return Ok(());
+        };
+
+        if failures.is_empty() {
+            return match fs::remove_file(&path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e).with_path(&path),
+                _ => Ok(()),
+            };
+        }
+
+        for failure in failures.iter_mut() {
+            if let Ok(relative) = failure.path.strip_prefix(instance_directory) {
+                failure.path = relative.to_owned();
+            }
+        }
+        failures.sort_by(|a, b| a.path.cmp(&b.path));
+
+        write_atomic(&path, serde_json::to_string_pretty(&*failures)?).with_path(&path)?;
+        eprintln!(
+            "listed {} failed downloads in {}",
+            failures.len(),
+            path.display()
+        );
+
+        Ok(())
+    }
+
+    /// Passes `result` through, saving the report first when it failed.
+    pub fn report_failure<T>(&self, instance_directory: &Path, result: Result<T>) -> Result<T> {
+        if result.is_err() {
+            if let Err(e) = self.save_report(instance_directory) {
+                eprintln!("warning: could not save the download errors: {}", e);
+            }
+        }
+
+        result
+    }
+}
+
+/// Fails when a body of `actual` bytes isn't the `expected` size.
+fn check_length(url: &str, what: &str, expected: Option<u64>, actual: u64) -> Result<()> {
+    match expected {
+        Some(expected) if expected != actual => Err(Error::InvalidResponse {
+            url: url.to_owned(),
+            reason: format!("{} {} bytes, expected {}", what, actual, expected),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Fetches `url`, checking the body against `size` (when known) and the
+/// response's own Content-Length, both before and after reading it. A proxy
+/// serving the wrong file or a cut-off transfer fails here, in time to be
+/// retried, rather than as a confusing hash mismatch later.
+async fn fetch_bytes(
+    http_client: &reqwest::Client,
+    url: &str,
+    size: Option<u64>,
+    timeout: Duration,
+    progress: &Progress,
+    run: &DownloadRun,
+) -> Result<Vec<u8>> {
+    let mut response = http_client
+        .get(url)
+        .timeout(timeout)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    if response.version() == reqwest::Version::HTTP_2 {
+        run.multiplexed.store(true, Ordering::Relaxed);
+    }
+
+    let content_length = response.content_length();
+    if let Some(content_length) = content_length {
+        check_length(url, "has a Content-Length of", size, content_length)?;
+    }
+
+    let mut data = Vec::with_capacity(content_length.or(size).unwrap_or(0) as usize);
+
+    loop {
+        match response.chunk().await {
+            Ok(Some(chunk)) => {
+                progress.add(chunk.len() as u64);
+                data.extend_from_slice(&chunk);
+            }
+            Ok(None) => break,
+            Err(e) => {
+                progress.sub(data.len() as u64);
+                return Err(e.into());
+            }
+        }
+    }
+
+    let checked = check_length(url, "sent", content_length, data.len() as u64)
+        .and_then(|()| check_length(url, "sent", size, data.len() as u64));
+
+    if let Err(e) = checked {
+        progress.sub(data.len() as u64);
+        return Err(e);
+    }
+
+    Ok(data)
+}
+
+/// Fetches the body of one of `urls` with [`retry_urls`].
+async fn fetch_with_retry(
+    http_client: &reqwest::Client,
+    urls: &[String],
+    size: Option<u64>,
+    timeout: Duration,
+    progress: &Progress,
+    limit: &ConcurrencyLimit,
+    run: &DownloadRun,
+) -> std::result::Result<Vec<u8>, FetchFailure> {
+    retry_urls(urls, limit, run, |url| async move {
+        fetch_bytes(http_client, &url, size, timeout, progress, run).await
+    })
+    .await
+}
+
+/// Runs `fetch` on each of `urls` in order, retrying a few times with backoff
+/// before failing over to the next one. A 4xx moves on straight away since
+/// retrying the same mirror won't help, except a 429, which lowers `limit`
+/// and waits. Every retry comes out of the run's retries.
+async fn retry_urls<T, F, Fut>(
+    urls: &[String],
+    limit: &ConcurrencyLimit,
+    run: &DownloadRun,
+    mut fetch: F,
+) -> std::result::Result<T, FetchFailure>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let retries = &run.retries;
+    let mut last_error = None;
+    let mut retried = 0;
+
+    for url in urls {
+        for attempt in 0..ATTEMPTS_PER_MIRROR {
+            let permit = limit.acquire().await;
+            let result = fetch(url.clone()).await;
+            drop(permit);
+
+            match result {
+                Ok(data) => {
+                    limit.succeeded();
+                    return Ok(data);
+                }
+                Err(Error::Network {
+                    url: source_url,
+                    source,
+                }) if source.status() == Some(reqwest::StatusCode::TOO_MANY_REQUESTS) => {
+                    limit.throttled();
+                    last_error = Some(Error::Network {
+                        url: source_url,
+                        source,
+                    });
+                    if let Err(error) = retries.spend() {
+                        return Err(FetchFailure {
+                            error,
+                            cause: last_error,
+                            url: url.clone(),
+                            retried,
+                        });
+                    }
+                    retried += 1;
+                    tokio::time::sleep(Duration::from_millis(1000 << attempt)).await;
+                }
+                Err(Error::Network {
+                    url: source_url,
+                    source,
+                }) if source.status().is_some_and(|s| s.is_client_error()) => {
+                    eprintln!("{} failed: {}", url, source);
+                    last_error = Some(Error::Network {
+                        url: source_url,
+                        source,
+                    });
+                    break;
+                }
+                Err(e) => {
+                    eprintln!("{} failed (attempt {}): {}", url, attempt + 1, e);
+                    last_error = Some(e);
+                    if let Err(error) = retries.spend() {
+                        return Err(FetchFailure {
+                            error,
+                            cause: last_error,
+                            url: url.clone(),
+                            retried,
+                        });
+                    }
+                    retried += 1;
+                    tokio::time::sleep(Duration::from_millis(500 << attempt)).await;
+                }
+            }
+        }
+    }
+
+    Err(FetchFailure {
+        error: last_error.expect("retry_urls needs at least one url"),
+        cause: None,
+        url: urls.last().cloned().unwrap_or_default(),
+        retried,
+    })
+}
+
+/// Downloads a single file to `path` with the usual retries, checking it
+/// against `sha1` when one is given. The retries come out of `run`, which
+/// also lists the file if it fails for good.
+pub async fn download_file(
+    http_client: &reqwest::Client,
+    url: &str,
+    path: &Path,
+    sha1: Option<&str>,
+    config: &LauncherConfig,
+    run: &DownloadRun,
+) -> Result<()> {
+    let timeout = Duration::from_secs(config.download_timeout_secs);
+    let data = fetch_with_retry(
+        http_client,
+        &[url.to_owned()],
+        None,
+        timeout,
+        &Progress::new(url, 0),
+        &ConcurrencyLimit::new(1),
+        run,
+    )
+    .await
+    .inspect_err(|failure| run.record(path, failure))?;
+
+    if let Some(expected) = sha1 {
+        let mut hasher = Sha1::new();
+        hasher.update(&data);
+        let actual = hasher.finish_hex();
+
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(Error::Verification {
+                path: path.to_owned(),
+                expected: expected.to_owned(),
+                actual,
+            });
+        }
+    }
+
+    fs::create_dir_all(path.parent().unwrap()).with_path(path)?;
+    write_atomic(path, &data).with_path(path)?;
+
+    Ok(())
+}
+
+/// Candidate urls for an asset object, one per configured mirror.
+fn asset_urls(mirrors: &[String], hash: &str) -> Vec<String> {
+    let official = [String::from(OFFICIAL_ASSETS_URL)];
+    let mirrors = if mirrors.is_empty() {
+        &official
+    } else {
+        mirrors
+    };
+
+    mirrors
+        .iter()
+        .map(|mirror| format!("{}/{}/{}", mirror.trim_end_matches('/'), &hash[0..2], hash))
+        .collect()
+}
+
+/// Candidate urls for a library. Only libraries from Mojang's library host
+/// can be mirrored; anything else is fetched from where the meta says.
+fn library_urls(mirrors: &[String], url: &str) -> Vec<String> {
+    match url.strip_prefix(OFFICIAL_LIBRARIES_URL) {
+        Some(path) if !mirrors.is_empty() => mirrors
+            .iter()
+            .map(|mirror| format!("{}{}", mirror.trim_end_matches('/'), path))
+            .collect(),
+        _ => vec![url.to_owned()],
+    }
+}
+
+/// Fetches the asset index and stores it under `indexes/`.
+pub async fn fetch_asset_index(
+    http_client: &reqwest::Client,
+    assets_directory: &Path,
+    asset_index_download: AssetIndexDownload<'_>,
+) -> Result<serde_json::Value> {
+    let indexes_path = assets_directory.join("indexes");
+    fs::create_dir_all(&indexes_path).with_path(&indexes_path)?;
+
+    let asset_index_json = match embedded_asset_index() {
+        Some(json) => json?,
+        None => {
+            fetch_json(
+                http_client,
+                asset_index_download.url,
+                ASSET_INDEX_REQUIRED_KEYS,
+            )
+            .await?
+        }
+    };
+
+    let index_path = indexes_path.join(format!("{}.json", asset_index_download.id));
+    fs::write(
+        &index_path,
+        serde_json::to_string_pretty(&asset_index_json)?,
+    )
+    .with_path(&index_path)?;
+
+    Ok(asset_index_json)
+}
+
+pub async fn download_assets(
+    http_client: reqwest::Client,
+    assets_directory: &Path,
+    asset_index_json: &serde_json::Value,
+    options: &InstallOptions,
+    run: &DownloadRun,
+    config: &LauncherConfig,
+) -> Result<()> {
+    let objects_path = assets_directory.join("objects");
+    fs::create_dir_all(&objects_path).with_path(&objects_path)?;
+
+    let asset_objects = asset_index_json["objects"]
+        .as_object()
+        .ok_or_else(|| Error::InvalidMeta(String::from("asset index objects is not a map")))?;
+
+    let mut pending = Vec::new();
+    let mut filtered_out = 0;
+    let mut from_store = 0;
+
+    for (k, v) in asset_objects.iter() {
+        if options
+            .assets_filter
+            .as_ref()
+            .is_some_and(|filter| !filter.matches(k))
+        {
+            filtered_out += 1;
+            continue;
+        }
+
+        let hash = v["hash"]
+            .as_str()
+            .ok_or_else(|| Error::InvalidMeta(String::from("asset object without a hash")))?
+            .to_owned();
+        let asset_path = asset_object_path(&objects_path, &hash, config.asset_layout);
+
+        let size = v["size"].as_u64().unwrap_or(0);
+
+        // A size mismatch is almost always a truncated download and costs
+        // nothing to spot. Hashing everything is left to those who ask.
+        let intact = match fs::metadata(&asset_path) {
+            Ok(metadata) if metadata.len() == size => {
+                !options.verify_hashes || sha1_file(&asset_path).is_ok_and(|actual| actual == hash)
+            }
+            _ => false,
+        };
+
+        if intact {
+            continue;
+        }
+
+        if take_from_store(&hash, size, &asset_path) {
+            from_store += 1;
+        } else {
+            pending.push((hash, asset_path, size));
+        }
+    }
+
+    if filtered_out > 0 {
+        eprintln!("skipped {} assets excluded by the filter", filtered_out);
+    }
+    if from_store > 0 {
+        eprintln!("linked {} assets from the store", from_store);
+    }
+
+    create_parent_directories(pending.iter().map(|(_, path, _)| path)).await?;
+
+    let concurrency = options
+        .concurrency
+        .unwrap_or(Concurrency::Fixed(config.assets_concurrency));
+
+    if options.downloader == Downloader::Aria2c {
+        let jobs = match concurrency {
+            Concurrency::Fixed(concurrency) => concurrency,
+            Concurrency::Auto => config.assets_concurrency,
+        };
+
+        if download_with_aria2c(assets_directory, &pending, jobs, config).await? {
+            pending.retain(|(_, path, size)| {
+                fs::metadata(path).map_or(true, |metadata| metadata.len() != *size)
+            });
+        }
+    }
+
+    let progress = Progress::new("assets", pending.iter().map(|(_, _, size)| size).sum());
+    let display = ProgressDisplay::start(progress.clone());
+    let writer = FileWriter::start(config.assets_write_batch, config.sync_assets);
+    let download = AssetDownload {
+        http_client: &http_client,
+        config,
+        progress: &progress,
+        writer: &writer,
+        run,
+    };
+
+    let mut pending = pending.into_iter();
+    let mut results = Vec::new();
+
+    let limit = match concurrency {
+        Concurrency::Fixed(concurrency) => {
+            ConcurrencyLimit::new(cap_to_open_files(concurrency, false, config))
+        }
+        Concurrency::Auto => {
+            let probe = pending.by_ref().take(PROBE_ASSETS).collect::<Vec<_>>();
+            let probed = probe.len();
+            let started = Instant::now();
+            results.extend(
+                download
+                    .run(
+                        probe,
+                        &ConcurrencyLimit::starting_at(PROBE_CONCURRENCY, PROBE_CONCURRENCY),
+                    )
+                    .await,
+            );
+
+            let multiplexed = run.multiplexed();
+            let concurrency = cap_to_open_files(
+                auto_concurrency(started.elapsed(), probed, multiplexed),
+                multiplexed,
+                config,
+            );
+            eprintln!("downloading up to {} assets at once", concurrency);
+            ConcurrencyLimit::starting_at(concurrency, AUTO_MIN_CONCURRENCY)
+        }
+    };
+
+    results.extend(download.run(pending.collect(), &limit).await);
+    display.finish().await;
+
+    // The writer's own error explains why any writes were refused.
+    writer.finish().await?;
+    results.into_iter().collect()
+}
+
+/// What downloads the asset objects.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Downloader {
+    /// The launcher's own downloader.
+    #[default]
+    Builtin,
+    /// aria2c, when it's installed. Whatever it doesn't manage is left to
+    /// the built-in one.
+    Aria2c,
+}
+
+impl FromStr for Downloader {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "builtin" => Ok(Downloader::Builtin),
+            "aria2c" => Ok(Downloader::Aria2c),
+            _ => Err(format!(
+                "unknown downloader {}, expected builtin or aria2c",
+                s
+            )),
+        }
+    }
+}
+
+const ARIA2_INPUT_FILE_NAME: &str = "aria2-input.txt";
+
+/// Hands `objects` to aria2c in one input file, with every mirror and the
+/// hash of each. `false` when aria2c isn't installed. Its progress goes to
+/// stderr like the launcher's own.
+async fn download_with_aria2c(
+    assets_directory: &Path,
+    objects: &[(String, PathBuf, u64)],
+    jobs: usize,
+    config: &LauncherConfig,
+) -> Result<bool> {
+    if objects.is_empty() {
+        return Ok(true);
+    }
+
+    let mut input = String::new();
+    for (hash, path, _) in objects {
+        let (Some(directory), Some(name)) = (path.parent(), path.file_name()) else {
+            continue;
+        };
+
+        input.push_str(&asset_urls(&config.asset_mirrors, hash).join("\t"));
+        input.push_str(&format!(
+            "\n  dir={}\n  out={}\n  checksum=sha-1={}\n",
+            std::path::absolute(directory)?.display(),
+            name.to_string_lossy(),
+            hash
+        ));
+    }
+
+    let input_path = assets_directory.join(ARIA2_INPUT_FILE_NAME);
+    fs::write(&input_path, input).with_path(&input_path)?;
+
+    let mut command = std::process::Command::new("aria2c");
+    command
+        .arg(format!("--input-file={}", input_path.display()))
+        .arg(format!("--max-concurrent-downloads={}", jobs.max(1)))
+        .arg(format!("--timeout={}", config.download_timeout_secs))
+        .args([
+            "--auto-file-renaming=false",
+            "--allow-overwrite=true",
+            "--console-log-level=warn",
+            "--summary-interval=0",
+        ])
+        .stdout(std::io::stderr());
+    if config.ip_preference == IpPreference::Ipv4Only {
+        command.arg("--disable-ipv6=true");
+    }
+
+    let status = tokio::task::spawn_blocking(move || command.status())
+        .await
+        .expect("aria2c wait panicked");
+    let _ = fs::remove_file(&input_path);
+
+    match status {
+        Ok(status) if status.success() => Ok(true),
+        Ok(status) => {
+            eprintln!(
+                "aria2c failed ({}), downloading what's left with the built-in downloader",
+                status
+            );
+            Ok(true)
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            eprintln!("aria2c isn't installed, using the built-in downloader");
+            Ok(false)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// How many asset objects to download at once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Concurrency {
+    /// Time the first few downloads and pick a number from that.
+    Auto,
+    Fixed(usize),
+}
+
+impl FromStr for Concurrency {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Concurrency::Auto),
+            _ => match s.parse() {
+                Ok(concurrency) if concurrency > 0 => Ok(Concurrency::Fixed(concurrency)),
+                _ => Err(format!(
+                    "unknown concurrency {}, expected a number or auto",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+/// Asset objects [`Concurrency::Auto`] times before choosing, and how many of
+/// them it downloads at once.
+const PROBE_ASSETS: usize = 16;
+const PROBE_CONCURRENCY: usize = 4;
+
+/// The fewest and most downloads [`Concurrency::Auto`] picks over HTTP/1.1.
+/// Asset objects are small enough that the time to first byte dominates, so
+/// even a fast server is worth [`AUTO_MIN_CONCURRENCY`].
+const AUTO_MIN_CONCURRENCY: usize = 16;
+const AUTO_MAX_CONCURRENCY: usize = 64;
+
+/// How often [`Concurrency::Auto`] aims for a download to finish.
+const AUTO_FINISH_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Keeps enough downloads in flight for one to finish about every
+/// [`AUTO_FINISH_INTERVAL`] at the request time the probe saw, so a slow or
+/// distant server gets more at once than a fast nearby one. At most
+/// [`AUTO_MAX_CONCURRENCY`], or [`MULTIPLEXED_CONCURRENCY`] over HTTP/2,
+/// where more in flight doesn't mean more connections.
+fn auto_concurrency(probe_time: Duration, probed: usize, multiplexed: bool) -> usize {
+    let max = match multiplexed {
+        true => MULTIPLEXED_CONCURRENCY,
+        false => AUTO_MAX_CONCURRENCY,
+    };
+
+    let request_time = probe_time.as_secs_f64() * PROBE_CONCURRENCY as f64 / probed.max(1) as f64;
+    let concurrency = (request_time / AUTO_FINISH_INTERVAL.as_secs_f64()).ceil() as usize;
+
+    concurrency.clamp(AUTO_MIN_CONCURRENCY, max)
+}
+
+/// Open files kept back for everything besides the downloads in flight:
+/// stdio, the files being written, DNS and the like.
+const RESERVED_FILES: u64 = 64;
+
+/// Open files one download in flight may take up: its connection, and an
+/// idle one the client keeps around. Over HTTP/2 they share one connection
+/// and only the file being written counts.
+const FILES_PER_DOWNLOAD: u64 = 2;
+
+/// The streams most servers let one HTTP/2 connection have open at once.
+const MULTIPLEXED_CONCURRENCY: usize = 100;
+
+/// Lowers `concurrency` to what the open files limit allows, after raising
+/// that limit to the config's `open_files_limit`, so a low default limit
+/// doesn't fail downloads with "too many open files".
+fn cap_to_open_files(concurrency: usize, multiplexed: bool, config: &LauncherConfig) -> usize {
+    // A target of 0 is always met, so it only reads the limit.
+    let Some(limit) = raise_open_files_limit(config.open_files_limit) else {
+        return concurrency;
+    };
+
+    let files_per_download = if multiplexed { 1 } else { FILES_PER_DOWNLOAD };
+    let allowed = (limit.saturating_sub(RESERVED_FILES) / files_per_download).max(1);
+    if concurrency as u64 <= allowed {
+        return concurrency;
+    }
+
+    eprintln!(
+        "the open files limit is {}, downloading at most {} at once",
+        limit, allowed
+    );
+    allowed as usize
+}
+
+/// What every asset object download shares.
+struct AssetDownload<'a> {
+    http_client: &'a reqwest::Client,
+    config: &'a LauncherConfig,
+    progress: &'a Progress,
+    writer: &'a FileWriter,
+    run: &'a DownloadRun,
+}
+
+impl AssetDownload<'_> {
+    /// Downloads `objects` and queues them to be written. Only as many
+    /// downloads exist at any time as `limit` allows at most, however many
+    /// objects there are, rather than a spawned task for each up front.
+    /// Fewer than that run while it ramps up or backs off.
+    async fn run(
+        &self,
+        objects: Vec<(String, PathBuf, u64)>,
+        limit: &ConcurrencyLimit,
+    ) -> Vec<Result<()>> {
+        let timeout = Duration::from_secs(self.config.download_timeout_secs);
+
+        futures::stream::iter(objects)
+            .map(|(hash, asset_path, size)| async move {
+                let urls = asset_urls(&self.config.asset_mirrors, &hash);
+                // 0 is what a missing size parses as.
+                let size = Some(size).filter(|&size| size > 0);
+                let data = fetch_with_retry(
+                    self.http_client,
+                    &urls,
+                    size,
+                    timeout,
+                    self.progress,
+                    limit,
+                    self.run,
+                )
+                .await
+                .inspect_err(|failure| self.run.record(&asset_path, failure))?;
+
+                self.writer.write(asset_path, data).await
+            })
+            .buffer_unordered(limit.max())
+            .collect()
+            .await
+    }
+}
+
+/// Writes finished downloads on a blocking thread, up to `batch` of them at
+/// a time, instead of a blocking `fs::write` on the runtime per file.
+struct FileWriter {
+    sender: mpsc::Sender<(PathBuf, Vec<u8>)>,
+    task: tokio::task::JoinHandle<Result<()>>,
+}
+
+impl FileWriter {
+    fn start(batch: usize, sync: bool) -> Self {
+        let batch = batch.max(1);
+        let (sender, mut receiver) = mpsc::channel(batch);
+
+        let task = tokio::task::spawn_blocking(move || {
+            let mut files = Vec::with_capacity(batch);
+
+            while let Some(file) = receiver.blocking_recv() {
+                files.push(file);
+
+                while files.len() < batch {
+                    match receiver.try_recv() {
+                        Ok(file) => files.push(file),
+                        Err(_) => break,
+                    }
+                }
+
+                write_files(&files, sync)?;
+                files.clear();
+            }
+
+            Ok(())
+        });
+
+        Self { sender, task }
+    }
+
+    /// Queues `data` to be written to `path`.
+    async fn write(&self, path: PathBuf, data: Vec<u8>) -> Result<()> {
+        self.sender
+            .send((path, data))
+            .await
+            .map_err(|_| Error::from(std::io::Error::other("the asset writer stopped")))
+    }
+
+    /// Waits for every queued file to be in place.
+    async fn finish(self) -> Result<()> {
+        drop(self.sender);
+        self.task.await.expect("the asset writer panicked")
+    }
+}
+
+/// Writes each file next to where it goes and renames them into place once
+/// all are written, so a file only ever appears under its name complete.
+fn write_files(files: &[(PathBuf, Vec<u8>)], sync: bool) -> Result<()> {
+    for (path, data) in files {
+        let temp = temp_path(path);
+        let mut file = File::create(&temp).with_path(&temp)?;
+        file.write_all(data).with_path(&temp)?;
+
+        if sync {
+            file.sync_data().with_path(&temp)?;
+        }
+    }
+
+    for (path, _) in files {
+        fs::rename(temp_path(path), path).with_path(path)?;
+    }
+
+    // Makes the renames durable. Windows can't open a directory to sync it.
+    #[cfg(unix)]
+    if sync {
+        let directories = files
+            .iter()
+            .filter_map(|(path, _)| path.parent())
+            .collect::<HashSet<_>>();
+
+        for directory in directories {
+            File::open(directory)
+                .and_then(|directory| directory.sync_all())
+                .with_path(directory)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Creates the directories `paths` go in, each once and all in one blocking
+/// task rather than a `create_dir_all` per file on the runtime's threads.
+async fn create_parent_directories<'a>(paths: impl Iterator<Item = &'a PathBuf>) -> Result<()> {
+    let directories = paths
+        .filter_map(|path| path.parent())
+        .map(Path::to_owned)
+        .collect::<HashSet<_>>();
+
+    tokio::task::spawn_blocking(move || {
+        directories
+            .iter()
+            .try_for_each(|directory| fs::create_dir_all(directory).with_path(directory))
+    })
+    .await
+    .expect("directory creation task panicked")?;
+
+    Ok(())
+}
+
+/// Where old versions expect their assets by name rather than by hash:
+/// `virtual` indexes read from `assets/virtual/<id>`, `map_to_resources` ones
+/// from `resources/` in the game directory. `None` for modern indexes.
+pub fn legacy_assets_directory(
+    instance_directory: &Path,
+    assets_directory: &Path,
+    asset_index_id: &str,
+    asset_index_json: &serde_json::Value,
+) -> Option<PathBuf> {
+    if asset_index_json["map_to_resources"] == true {
+        Some(instance_directory.join("resources"))
+    } else if asset_index_json["virtual"] == true {
+        Some(assets_directory.join("virtual").join(asset_index_id))
+    } else {
+        None
+    }
+}
+
+/// Lays the downloaded objects out under their names for indexes that need
+/// the legacy layout, linking rather than copying unless `copy_only`.
+pub fn materialize_legacy_assets(
+    legacy_directory: &Path,
+    assets_directory: &Path,
+    asset_index_json: &serde_json::Value,
+    layout: AssetLayout,
+    copy_only: bool,
+) -> Result<()> {
+    let objects_path = assets_directory.join("objects");
+    let mut materialized = 0;
+
+    for (key, object) in asset_index_json["objects"]
+        .as_object()
+        .into_iter()
+        .flatten()
+    {
+        let Some(hash) = object["hash"].as_str() else {
+            continue;
+        };
+
+        if !stays_inside(key) {
+            eprintln!("warning: skipping asset {} from outside the assets", key);
+            continue;
+        }
+
+        let source = asset_object_path(&objects_path, hash, layout);
+        let destination = legacy_directory.join(key);
+
+        if !source.exists() || destination.exists() {
+            continue;
+        }
+
+        let parent = destination.parent().unwrap();
+        fs::create_dir_all(parent).with_path(parent)?;
+        link_or_copy(&source, &destination, copy_only)?;
+        materialized += 1;
+    }
+
+    if materialized > 0 {
+        eprintln!(
+            "laid out {} assets under {}",
+            materialized,
+            legacy_directory.display()
+        );
+    }
+
+    Ok(())
+}
+
+pub fn asset_object_path(objects_path: &Path, hash: &str, layout: AssetLayout) -> PathBuf {
+    match layout {
+        AssetLayout::Nested => objects_path.join(&hash[0..2]).join(hash),
+        AssetLayout::Flat => objects_path.join(hash),
+    }
+}
+
+/// Where the game is pointed for flat instances.
+pub const ASSET_VIEW_DIRECTORY: &str = "view";
+
+/// Links the flat objects into the nested layout the game expects, under
+/// `assets/view`, along with the index.
+pub fn materialize_asset_view(
+    assets_directory: &Path,
+    asset_index_id: &str,
+    asset_index_json: &serde_json::Value,
+    copy_only: bool,
+) -> Result<()> {
+    let objects_path = assets_directory.join("objects");
+    let view_directory = assets_directory.join(ASSET_VIEW_DIRECTORY);
+
+    for hash in asset_hashes(asset_index_json) {
+        let source = asset_object_path(&objects_path, hash, AssetLayout::Flat);
+        let destination =
+            asset_object_path(&view_directory.join("objects"), hash, AssetLayout::Nested);
+
+        if !source.exists() || destination.exists() {
+            continue;
+        }
+
+        let parent = destination.parent().unwrap();
+        fs::create_dir_all(parent).with_path(parent)?;
+        link_or_copy(&source, &destination, copy_only)?;
+    }
+
+    let index_name = format!("{}.json", asset_index_id);
+    let view_indexes = view_directory.join("indexes");
+    fs::create_dir_all(&view_indexes).with_path(&view_indexes)?;
+    let view_index = view_indexes.join(&index_name);
+    fs::copy(
+        assets_directory.join("indexes").join(&index_name),
+        &view_index,
+    )
+    .with_path(&view_index)?;
+
+    Ok(())
+}
+
+fn asset_hashes(asset_index_json: &serde_json::Value) -> HashSet<&str> {
+    asset_index_json["objects"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(_, object)| object["hash"].as_str())
+        .collect()
+}
+
+/// How many distinct objects `asset_index_json` has that `previous` doesn't,
+/// and how many the two share.
+pub fn diff_asset_indexes(
+    previous: &serde_json::Value,
+    asset_index_json: &serde_json::Value,
+) -> (usize, usize) {
+    let previous = asset_hashes(previous);
+    let (unchanged, new): (Vec<_>, Vec<_>) = asset_hashes(asset_index_json)
+        .into_iter()
+        .partition(|hash| previous.contains(hash));
+
+    (new.len(), unchanged.len())
+}
+
+/// Index keys of the assets (selected by `filter`) whose object isn't on disk.
+pub fn missing_assets(
+    assets_directory: &Path,
+    asset_index_json: &serde_json::Value,
+    filter: Option<&AssetFilter>,
+    layout: AssetLayout,
+) -> Vec<String> {
+    let objects_path = assets_directory.join("objects");
+
+    asset_index_json["objects"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter(|(key, _)| filter.is_none_or(|filter| filter.matches(key)))
+        .filter(|(_, object)| {
+            object["hash"]
+                .as_str()
+                .is_none_or(|hash| !asset_object_path(&objects_path, hash, layout).exists())
+        })
+        .map(|(key, _)| key.clone())
+        .collect()
+}
+
+pub async fn download_libraries(
+    http_client: reqwest::Client,
+    libraries_directory: &Path,
+    libraries: &[Library],
+    run: &DownloadRun,
+    config: &LauncherConfig,
+) -> Result<()> {
+    let timeout = Duration::from_secs(config.download_timeout_secs);
+    let mut pending = Vec::new();
+
+    for artifact in libraries
+        .iter()
+        .filter(|library| library.applies())
+        .flat_map(Library::artifacts)
+    {
+        let lib_path = libraries_directory.join(&artifact.path);
+        // A jar cut short by an interrupted download has the wrong size.
+        // Loader libraries that don't give one are trusted as they are.
+        let complete = fs::metadata(&lib_path)
+            .is_ok_and(|metadata| artifact.size == 0 || metadata.len() == artifact.size);
+
+        if complete
+            || artifact
+                .sha1
+                .as_ref()
+                .is_some_and(|sha1| take_from_store(sha1, artifact.size, &lib_path))
+        {
+            continue;
+        }
+
+        pending.push((
+            lib_path,
+            library_urls(&config.library_mirrors, &artifact.url),
+            artifact.size,
+        ));
+    }
+
+    create_parent_directories(pending.iter().map(|(path, _, _)| path)).await?;
+
+    let progress = Progress::new("libraries", pending.iter().map(|(_, _, size)| size).sum());
+    let display = ProgressDisplay::start(progress.clone());
+
+    let limit = ConcurrencyLimit::new(cap_to_open_files(
+        config.libraries_concurrency,
+        false,
+        config,
+    ));
+    let results = futures::stream::iter(pending)
+        .map(|(lib_path, urls, size)| {
+            let http_client = &http_client;
+            let progress = &progress;
+            let limit = &limit;
+
+            async move {
+                // Loader libraries often don't say how big they are.
+                let size = Some(size).filter(|&size| size > 0);
+                let data =
+                    fetch_with_retry(http_client, &urls, size, timeout, progress, limit, run)
+                        .await
+                        .inspect_err(|failure| run.record(&lib_path, failure))?;
+
+                write_atomic(&lib_path, data).with_path(&lib_path)?;
+
+                Ok::<(), Error>(())
+            }
+        })
+        .buffer_unordered(config.libraries_concurrency.max(1))
+        .collect::<Vec<_>>()
+        .await;
+    display.finish().await;
+
+    results.into_iter().collect()
+}
+
+/// Unpacks the natives jars of `libraries` into `natives_directory`, for
+/// versions whose LWJGL doesn't load its natives from the classpath itself.
+pub fn extract_natives(
+    libraries_directory: &Path,
+    natives_directory: &Path,
+    libraries: &[Library],
+) -> Result<()> {
+    for library in libraries.iter().filter(|library| library.applies()) {
+        let Some(artifact) = library.native_artifact() else {
+            continue;
+        };
+
+        let archive = ZipArchive::open(&libraries_directory.join(&artifact.path))?;
+
+        for entry in archive.entries() {
+            let excluded = library
+                .extract
+                .exclude
+                .iter()
+                .any(|prefix| entry.name.starts_with(prefix.as_str()));
+            let outside = !stays_inside(&entry.name);
+
+            if entry.is_dir() || excluded || outside {
+                continue;
+            }
+
+            let relative = Path::new(&entry.name);
+            let path = natives_directory.join(relative);
+            fs::create_dir_all(path.parent().unwrap()).with_path(&path)?;
+            fs::write(&path, archive.read(entry)?).with_path(&path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Where the natives directory records the jars it was extracted from.
+const NATIVES_STAMP_FILE_NAME: &str = ".extracted-from";
+
+/// [`extract_natives`] into an emptied `natives_directory`, unless it already
+/// holds the natives of the same jars, judged by their paths, sizes and
+/// modification times. `clean` extracts them regardless. Returns whether it
+/// extracted.
+pub fn refresh_natives(
+    libraries_directory: &Path,
+    natives_directory: &Path,
+    libraries: &[Library],
+    clean: bool,
+) -> Result<bool> {
+    let stamp = libraries
+        .iter()
+        .filter(|library| library.applies())
+        .filter_map(Library::native_artifact)
+        .map(|artifact| {
+            let (size, modified) =
+                file_stamp(&libraries_directory.join(&artifact.path)).unwrap_or_default();
+            format!("{} {} {}\n", artifact.path, size, modified)
+        })
+        .collect::<String>();
+    let stamp_path = natives_directory.join(NATIVES_STAMP_FILE_NAME);
+
+    if !clean && fs::read_to_string(&stamp_path).is_ok_and(|existing| existing == stamp) {
+        return Ok(false);
+    }
+
+    if natives_directory.exists() {
+        fs::remove_dir_all(natives_directory).with_path(natives_directory)?;
+    }
+
+    extract_natives(libraries_directory, natives_directory, libraries)?;
+    fs::create_dir_all(natives_directory).with_path(natives_directory)?;
+    fs::write(&stamp_path, stamp).with_path(&stamp_path)?;
+
+    Ok(true)
+}
+
+/// Removes library files `library_entries` no longer reference, such as the
+/// previous version's jars after an update, and returns how many there were.
+pub fn prune_libraries(libraries_directory: &Path, libraries: &[Library]) -> Result<usize> {
+    if !libraries_directory.exists() {
+        return Ok(0);
+    }
+
+    let wanted = libraries
+        .iter()
+        .flat_map(Library::artifacts)
+        .map(|artifact| libraries_directory.join(&artifact.path))
+        .collect::<HashSet<_>>();
+
+    let mut removed = 0;
+
+    for path in crate::list_files(libraries_directory)? {
+        if !wanted.contains(&path) {
+            fs::remove_file(&path).with_path(&path)?;
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Streams a client or server jar into `<file_name>.part`, resuming a
+/// previous partial download with a Range request where the server allows it,
+/// and only renames it into place once its size and sha1 match the meta.
+/// Failed attempts are retried from where they stopped, out of `run`'s
+/// retries, and a jar that fails for good is listed in its report.
+pub async fn download_jar(
+    http_client: reqwest::Client,
+    directory: &Path,
+    jar: JarDownload<'_>,
+    run: &DownloadRun,
+) -> Result<()> {
+    let jar_path = directory.join(jar.file_name);
+
+    if jar_path.exists() {
+        if fs::metadata(&jar_path).with_path(&jar_path)?.len() == jar.size
+            && sha1_file(&jar_path)
+                .with_path(&jar_path)?
+                .eq_ignore_ascii_case(jar.sha1)
+        {
+            return Ok(());
+        }
+
+        eprintln!(
+            "{} doesn't match the meta, downloading it again",
+            jar.file_name
+        );
+        fs::remove_file(&jar_path).with_path(&jar_path)?;
+    }
+
+    let part_path = directory.join(format!("{}.part", jar.file_name));
+    let (http_client, part) = (&http_client, &part_path);
+
+    retry_urls(
+        &[jar.url.to_owned()],
+        &ConcurrencyLimit::new(1),
+        run,
+        |_| async move { fetch_jar_part(http_client, part, jar).await },
+    )
+    .await
+    .inspect_err(|failure| run.record(&jar_path, failure))?;
+
+    let actual_sha1 = sha1_file(&part_path).with_path(&part_path)?;
+
+    if !actual_sha1.eq_ignore_ascii_case(jar.sha1) {
+        fs::remove_file(&part_path).with_path(&part_path)?;
+        return Err(Error::Verification {
+            path: part_path,
+            expected: jar.sha1.to_owned(),
+            actual: actual_sha1,
+        });
+    }
+
+    fs::rename(&part_path, &jar_path).with_path(&jar_path)?;
+
+    Ok(())
+}
+
+/// One attempt of [`download_jar`]: fetches whatever `part_path` is missing
+/// of the jar. A transfer cut short keeps what arrived for the next attempt.
+async fn fetch_jar_part(
+    http_client: &reqwest::Client,
+    part_path: &Path,
+    jar: JarDownload<'_>,
+) -> Result<()> {
+    let mut downloaded = fs::metadata(part_path).map_or(0, |m| m.len());
+
+    if downloaded > jar.size {
+        fs::remove_file(part_path).with_path(part_path)?;
+        downloaded = 0;
+    }
+
+    if downloaded == jar.size {
+        return Ok(());
+    }
+
+    let mut request = http_client.get(jar.url);
+
+    if downloaded > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-", downloaded));
+    }
+
+    let mut response = request.send().await?.error_for_status()?;
+
+    let partial = downloaded > 0 && response.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+    let remaining = if partial {
+        jar.size - downloaded
+    } else {
+        jar.size
+    };
+    if let Some(content_length) = response.content_length() {
+        check_length(
+            jar.url,
+            "has a Content-Length of",
+            Some(remaining),
+            content_length,
+        )?;
+    }
+
+    let mut file = if partial {
+        eprintln!("resuming {} at {} bytes", jar.file_name, downloaded);
+        OpenOptions::new()
+            .append(true)
+            .open(part_path)
+            .with_path(part_path)?
+    } else {
+        downloaded = 0;
+        File::create(part_path).with_path(part_path)?
+    };
+
+    let progress = Progress::new(jar.file_name, jar.size);
+    progress.add(downloaded);
+    let display = ProgressDisplay::start(progress.clone());
+
+    let result = async {
+        while let Some(chunk) = response.chunk().await? {
+            file.write_all(&chunk).with_path(part_path)?;
+            downloaded += chunk.len() as u64;
+            progress.add(chunk.len() as u64);
+        }
+
+        file.sync_all().with_path(part_path)?;
+        Ok::<(), Error>(())
+    }
+    .await;
+
+    display.finish().await;
+    result?;
+
+    if downloaded > jar.size {
+        fs::remove_file(part_path).with_path(part_path)?;
+    }
+
+    check_length(jar.url, "sent", Some(jar.size), downloaded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The probe's time for `request_ms` requests, [`PROBE_CONCURRENCY`] at
+    /// a time.
+    fn probe_time(request_ms: u64) -> Duration {
+        Duration::from_millis(request_ms * (PROBE_ASSETS / PROBE_CONCURRENCY) as u64)
+    }
+
+    #[test]
+    fn auto_concurrency_follows_the_request_time() {
+        // A fast nearby server still gets the floor.
+        assert_eq!(auto_concurrency(probe_time(2), PROBE_ASSETS, false), 16);
+        assert_eq!(auto_concurrency(probe_time(30), PROBE_ASSETS, false), 30);
+        assert_eq!(auto_concurrency(probe_time(60), PROBE_ASSETS, false), 60);
+        assert_eq!(auto_concurrency(probe_time(500), PROBE_ASSETS, false), 64);
+    }
+
+    #[test]
+    fn auto_concurrency_goes_higher_over_http2() {
+        assert_eq!(auto_concurrency(probe_time(2), PROBE_ASSETS, true), 16);
+        assert_eq!(auto_concurrency(probe_time(80), PROBE_ASSETS, true), 80);
+        assert_eq!(auto_concurrency(probe_time(500), PROBE_ASSETS, true), 100);
+    }
+
+    #[test]
+    fn auto_concurrency_survives_an_empty_probe() {
+        assert_eq!(auto_concurrency(Duration::ZERO, 0, false), 16);
+    }
+}
@@ -0,0 +1,326 @@
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+/// What went wrong, split by kind so callers can tell a network failure worth
+/// retrying from a full disk or a corrupt download.
+#[derive(Debug)]
+pub enum Error {
+    /// A request that couldn't complete, with its url when known.
+    Network {
+        url: Option<String>,
+        source: reqwest::Error,
+    },
+    /// Reading or writing a file failed, at `path` when known.
+    Filesystem {
+        path: Option<PathBuf>,
+        source: io::Error,
+    },
+    /// Json or toml that didn't parse. `what` names the document.
+    Parse {
+        what: String,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// A config that parses as toml but has a field of the wrong type or
+    /// with a value it can't take.
+    InvalidConfig {
+        path: PathBuf,
+        reason: String,
+    },
+    /// A file whose hash isn't the one it should have.
+    Verification {
+        path: PathBuf,
+        expected: String,
+        actual: String,
+    },
+    /// The game's JVM couldn't be started.
+    Launch {
+        source: io::Error,
+    },
+    TomlSer(toml::ser::Error),
+    InvalidResponse {
+        url: String,
+        reason: String,
+    },
+    InvalidMeta(String),
+    UnknownAccount(String),
+    Java(String),
+    IncompleteAssets(usize),
+    /// Files the meta expects that aren't on disk after a create, counted
+    /// per part like `libraries 41/42, client 1/1`.
+    IncompleteInstall(String),
+    /// An instance shortcut couldn't be made.
+    Shortcut(String),
+    VersionMismatch {
+        locked: String,
+        found: String,
+    },
+    UnknownVersion(String),
+    InvalidArchive(String),
+    Startup(String),
+    CorruptInstance(usize),
+    CorruptClient(String),
+    ModConflicts(usize),
+    UnsupportedArch {
+        library: String,
+        arch: String,
+    },
+    UnknownWorld(String),
+    UnsupportedOs(String),
+    NotOwned(String),
+    /// An expired session that couldn't be refreshed.
+    SessionExpired {
+        account: String,
+        reason: String,
+    },
+    InvalidNbt(String),
+    InvalidSkin {
+        path: PathBuf,
+        reason: String,
+    },
+    InvalidAddress(String),
+    InvalidLwjglVersion(String),
+    MetaNotFound {
+        version: Option<String>,
+        url: String,
+    },
+    MissingLock(PathBuf),
+    /// A download phase that ran past `phase_timeout_secs`.
+    PhaseTimeout {
+        phase: String,
+        seconds: u64,
+    },
+    /// Downloads failed more times in a run than `retry_budget` allows.
+    TooManyFailures(usize),
+    /// The instance directory can't be created or written to.
+    NotWritable {
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// The status endpoint couldn't listen on its address.
+    Serve {
+        address: SocketAddr,
+        source: io::Error,
+    },
+    /// A destructive action wasn't confirmed.
+    NotConfirmed(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Network { source, .. } => write!(f, "network error: {}", source),
+            Error::Filesystem {
+                path: Some(path),
+                source,
+            } => write!(f, "{}: {}", path.display(), source),
+            Error::Filesystem { path: None, source } => write!(f, "io error: {}", source),
+            Error::Parse { what, source } => write!(f, "invalid {}: {}", what, source),
+            Error::InvalidConfig { path, reason } => {
+                write!(f, "invalid {}: {}", path.display(), reason)
+            }
+            Error::Verification {
+                path,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} is corrupt: expected {}, got {}",
+                path.display(),
+                expected,
+                actual
+            ),
+            Error::Launch { source } => write!(f, "could not start the game: {}", source),
+            Error::TomlSer(e) => write!(f, "could not serialize config: {}", e),
+            Error::InvalidResponse { url, reason } => write!(f, "{} {}", url, reason),
+            Error::InvalidMeta(reason) => write!(f, "invalid meta: {}", reason),
+            Error::UnknownAccount(name) => write!(f, "no account named {}", name),
+            Error::Java(reason) => write!(f, "java: {}", reason),
+            Error::IncompleteAssets(missing) => {
+                write!(f, "{} assets failed to download, run create again", missing)
+            }
+            Error::Shortcut(reason) => write!(f, "could not create a shortcut: {}", reason),
+            Error::IncompleteInstall(counts) => {
+                write!(f, "the install is incomplete ({}), run create again", counts)
+            }
+            Error::VersionMismatch { locked, found } => write!(
+                f,
+                "instance was installed as {} but its meta is for {}, recreate or update the instance",
+                locked, found
+            ),
+            Error::UnknownVersion(id) => write!(f, "no version {} in the version manifest", id),
+            Error::InvalidArchive(reason) => write!(f, "invalid zip: {}", reason),
+            Error::Startup(reason) => write!(f, "launch failed: {}", reason),
+            Error::MetaNotFound {
+                version: Some(version),
+                ..
+            } => write!(
+                f,
+                "version {} meta not found; the package may have been moved, try --refresh-manifest",
+                version
+            ),
+            Error::MetaNotFound { version: None, url } => write!(
+                f,
+                "meta not found at {}; the package may have been moved, try --version with --refresh-manifest",
+                url
+            ),
+            Error::InvalidNbt(reason) => write!(f, "invalid nbt: {}", reason),
+            Error::InvalidSkin { path, reason } => {
+                write!(f, "{} can't be used as a skin: {}", path.display(), reason)
+            }
+            Error::InvalidAddress(address) => {
+                write!(f, "{} is not a valid server address, expected host or host:port", address)
+            }
+            Error::InvalidLwjglVersion(version) => {
+                write!(f, "{:?} is not a valid LWJGL version", version)
+            }
+            Error::NotOwned(name) => write!(f, "{} doesn't own Minecraft", name),
+            Error::SessionExpired { account, reason } => write!(
+                f,
+                "the session of {} expired and couldn't be refreshed ({}), sign in again",
+                account, reason
+            ),
+            Error::NotConfirmed(action) => write!(
+                f,
+                "didn't {}, pass --yes to go ahead without being asked",
+                action
+            ),
+            Error::NotWritable { path, source } => write!(
+                f,
+                "instance directory {} is not writable: {}",
+                path.display(),
+                source
+            ),
+            Error::Serve { address, source } => {
+                write!(f, "could not serve status on {}: {}", address, source)
+            }
+            Error::MissingLock(path) => write!(
+                f,
+                "{} has no instance.lock.json, create or update it with this launcher first",
+                path.display()
+            ),
+            Error::PhaseTimeout { phase, seconds } => write!(
+                f,
+                "downloading {} took longer than {} seconds and was aborted, run it again to \
+                 pick up where it stopped",
+                phase, seconds
+            ),
+            Error::TooManyFailures(retries) => write!(
+                f,
+                "too many failures ({} retries), check your connection",
+                retries
+            ),
+            Error::UnsupportedOs(problem) => {
+                write!(f, "{}, launch without --strict to try anyway", problem)
+            }
+            Error::UnknownWorld(world) => write!(f, "no world named {:?} in saves", world),
+            Error::CorruptInstance(count) => write!(
+                f,
+                "{} files are missing or corrupt, run verify to see which",
+                count
+            ),
+            Error::CorruptClient(reason) => {
+                write!(f, "client.jar appears corrupt ({}), run repair", reason)
+            }
+            Error::UnsupportedArch { library, arch } => write!(
+                f,
+                "this version has no {} natives of {} for {}, so it can't run on this machine",
+                arch,
+                library,
+                std::env::consts::OS
+            ),
+            Error::ModConflicts(count) => write!(
+                f,
+                "{} problems with the installed mods, fix them or launch without --strict-mods",
+                count
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Network { source, .. } => Some(source),
+            Error::Filesystem { source, .. } => Some(source),
+            Error::Parse { source, .. } => Some(source.as_ref()),
+            Error::Launch { source } => Some(source),
+            Error::NotWritable { source, .. } => Some(source),
+            Error::Serve { source, .. } => Some(source),
+            Error::TomlSer(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(source: reqwest::Error) -> Self {
+        Error::Network {
+            url: source.url().map(|url| url.to_string()),
+            source,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(source: io::Error) -> Self {
+        Error::Filesystem { path: None, source }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(source: serde_json::Error) -> Self {
+        Error::Parse {
+            what: String::from("json"),
+            source: Box::new(source),
+        }
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(source: toml::de::Error) -> Self {
+        Error::Parse {
+            what: String::from("toml"),
+            source: Box::new(source),
+        }
+    }
+}
+
+/// Says which file an io error happened at, for [`Error::Filesystem`].
+pub trait PathContext<T> {
+    fn with_path(self, path: &Path) -> Result<T>;
+}
+
+impl<T> PathContext<T> for io::Result<T> {
+    fn with_path(self, path: &Path) -> Result<T> {
+        self.map_err(|source| Error::Filesystem {
+            path: Some(path.to_owned()),
+            source,
+        })
+    }
+}
+
+/// Says what was being parsed, for [`Error::Parse`].
+pub(crate) trait ParseContext<T> {
+    fn parsing(self, what: impl fmt::Display) -> Result<T>;
+}
+
+impl<T, E: std::error::Error + Send + Sync + 'static> ParseContext<T>
+    for std::result::Result<T, E>
+{
+    fn parsing(self, what: impl fmt::Display) -> Result<T> {
+        self.map_err(|source| Error::Parse {
+            what: what.to_string(),
+            source: Box::new(source),
+        })
+    }
+}
+
+impl From<toml::ser::Error> for Error {
+    fn from(e: toml::ser::Error) -> Self {
+        Error::TomlSer(e)
+    }
+}
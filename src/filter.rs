@@ -0,0 +1,96 @@
+//! Choosing which assets to download by glob patterns over their index keys.
+
+/// Selects assets by their index key (e.g. `minecraft/sounds/ambient/cave/cave1.ogg`).
+/// Patterns starting with `!` exclude; when there are no other patterns
+/// everything not excluded is selected.
+#[derive(Clone, Debug, Default)]
+pub struct AssetFilter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl AssetFilter {
+    pub fn add_pattern(&mut self, pattern: &str) {
+        match pattern.strip_prefix('!') {
+            Some(exclude) => self.exclude.push(exclude.to_owned()),
+            None => self.include.push(pattern.to_owned()),
+        }
+    }
+
+    pub fn matches(&self, key: &str) -> bool {
+        let included =
+            self.include.is_empty() || self.include.iter().any(|pattern| glob_match(pattern, key));
+
+        included && !self.exclude.iter().any(|pattern| glob_match(pattern, key))
+    }
+}
+
+/// Matches `/`-separated paths: `*` and `?` stay within a segment, `**` spans
+/// any number of segments.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    matches(pattern.as_bytes(), text.as_bytes())
+}
+
+fn matches(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => {
+            // `a/**/b` also matches `a/b`.
+            let skips_slash = rest.first() == Some(&b'/') && matches(&rest[1..], text);
+            skips_slash || (0..=text.len()).any(|i| matches(rest, &text[i..]))
+        }
+        [b'*', rest @ ..] => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != b'/')
+            .any(|i| matches(rest, &text[i..])),
+        [b'?', rest @ ..] => !text.is_empty() && text[0] != b'/' && matches(rest, &text[1..]),
+        [c, rest @ ..] => text.first() == Some(c) && matches(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn double_star_spans_segments() {
+        assert!(glob_match(
+            "minecraft/**",
+            "minecraft/sounds/ambient/cave/cave1.ogg"
+        ));
+        assert!(glob_match("**/*.ogg", "minecraft/sounds/step/grass1.ogg"));
+        assert!(glob_match("a/**/b", "a/x/y/b"));
+        assert!(glob_match("a/**/b", "a/b"));
+        assert!(!glob_match("a/**/b", "a/xb"));
+    }
+
+    #[test]
+    fn star_and_question_mark_stay_in_a_segment() {
+        assert!(glob_match(
+            "minecraft/lang/*.json",
+            "minecraft/lang/en_us.json"
+        ));
+        assert!(!glob_match("minecraft/*.json", "minecraft/lang/en_us.json"));
+        assert!(glob_match("icons/icon_??x??.png", "icons/icon_16x16.png"));
+        assert!(!glob_match("a?b", "a/b"));
+    }
+
+    #[test]
+    fn excludes_win_over_includes() {
+        let mut filter = AssetFilter::default();
+        filter.add_pattern("minecraft/sounds/**");
+        filter.add_pattern("!**/music/**");
+
+        assert!(filter.matches("minecraft/sounds/step/grass1.ogg"));
+        assert!(!filter.matches("minecraft/sounds/music/game/calm1.ogg"));
+        assert!(!filter.matches("minecraft/lang/en_us.json"));
+    }
+
+    #[test]
+    fn excludes_alone_select_everything_else() {
+        let mut filter = AssetFilter::default();
+        filter.add_pattern("!**/music/**");
+
+        assert!(filter.matches("minecraft/lang/en_us.json"));
+        assert!(!filter.matches("minecraft/sounds/music/game/calm1.ogg"));
+    }
+}
@@ -0,0 +1,183 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Where [`write_atomic`] writes `path` before renaming it into place.
+pub fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// A file's size and modification time in nanoseconds, which change whenever
+/// it's rewritten.
+pub fn file_stamp(path: &Path) -> Option<(u64, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+
+    Some((metadata.len(), modified.as_nanos() as u64))
+}
+
+/// Writes through a temporary file and renames it into place, so readers never
+/// see a half-written file.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let temp = temp_path(path);
+    fs::write(&temp, contents)?;
+    fs::rename(temp, path)
+}
+
+/// [`write_atomic`] for files only the user should read, such as tokens. The
+/// temporary file is created afresh with mode 0600, so the contents are never
+/// readable by others, not even while being written.
+pub fn write_private(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let temp = temp_path(path);
+    match fs::remove_file(&temp) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let mut file = options.open(&temp)?;
+    file.write_all(contents.as_ref())?;
+    file.sync_all()?;
+    fs::rename(temp, path)
+}
+
+/// Whether `name`, from an archive or index, stays inside the directory it's
+/// joined onto. Names that are absolute, rooted, carry a drive prefix or climb
+/// out with `..` would replace or escape it.
+pub fn stays_inside(name: &str) -> bool {
+    let relative = Path::new(name);
+
+    !relative.is_absolute()
+        && !relative
+            .components()
+            .any(|component| matches!(component, Component::Prefix(_) | Component::RootDir))
+        && !name.split(['/', '\\']).any(|part| part == "..")
+}
+
+/// Raises the soft limit on open files to `target`, or as close as the hard
+/// limit allows, and returns the limit then in effect. `None` when there's no
+/// limit to read, as on Windows.
+#[cfg(unix)]
+pub fn raise_open_files_limit(target: u64) -> Option<u64> {
+    let mut limit: libc::rlimit = unsafe { std::mem::zeroed() };
+
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } != 0 {
+        return None;
+    }
+
+    #[allow(clippy::unnecessary_cast)]
+    let (soft, hard) = (limit.rlim_cur as u64, limit.rlim_max as u64);
+    if soft >= target {
+        return Some(soft);
+    }
+
+    let raised = target.min(hard);
+    limit.rlim_cur = raised as libc::rlim_t;
+
+    if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &limit) } == 0 {
+        Some(raised)
+    } else {
+        Some(soft)
+    }
+}
+
+#[cfg(not(unix))]
+pub fn raise_open_files_limit(_target: u64) -> Option<u64> {
+    None
+}
+
+/// Creates `directory` if needed and checks a file can be written in it, by
+/// creating and deleting one.
+pub fn check_writable(directory: &Path) -> io::Result<()> {
+    fs::create_dir_all(directory)?;
+
+    let probe = directory.join(".write-test");
+    fs::write(&probe, b"")?;
+    fs::remove_file(probe)
+}
+
+/// Makes `destination` a hardlink to `source`, then tries a symlink, and
+/// copies when the filesystem supports neither (e.g. across devices).
+/// `copy_only` skips straight to copying.
+pub fn link_or_copy(source: &Path, destination: &Path, copy_only: bool) -> io::Result<()> {
+    if !copy_only {
+        if fs::hard_link(source, destination).is_ok() {
+            return Ok(());
+        }
+
+        let source = source.canonicalize()?;
+
+        #[cfg(unix)]
+        if std::os::unix::fs::symlink(&source, destination).is_ok() {
+            return Ok(());
+        }
+
+        #[cfg(windows)]
+        if std::os::windows::fs::symlink_file(&source, destination).is_ok() {
+            return Ok(());
+        }
+    }
+
+    fs::copy(source, destination).map(|_| ())
+}
+
+/// Replaces the directory at `link` with a symlink to `target`, moving
+/// anything already in it across first. A link that already points at
+/// `target` is left alone.
+pub fn redirect_directory(link: &Path, target: &Path) -> io::Result<()> {
+    fs::create_dir_all(target)?;
+    let target = std::path::absolute(target)?;
+
+    if fs::read_link(link).is_ok_and(|existing| existing == target) {
+        return Ok(());
+    }
+
+    if link.is_symlink() {
+        #[cfg(windows)]
+        fs::remove_dir(link)?;
+        #[cfg(not(windows))]
+        fs::remove_file(link)?;
+    } else if link.is_dir() {
+        for entry in fs::read_dir(link)? {
+            let entry = entry?;
+            fs::rename(entry.path(), target.join(entry.file_name()))?;
+        }
+        fs::remove_dir(link)?;
+    }
+
+    #[cfg(windows)]
+    return std::os::windows::fs::symlink_dir(target, link);
+    #[cfg(not(windows))]
+    return std::os::unix::fs::symlink(target, link);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_that_escape_are_caught() {
+        for name in [
+            "minecraft/sounds/step.ogg",
+            "icons/icon_16x16.png",
+            "a..b/c",
+        ] {
+            assert!(stays_inside(name), "{}", name);
+        }
+        for name in [
+            "../escape",
+            "sounds/../../escape",
+            "/etc/passwd",
+            "a\\..\\b",
+        ] {
+            assert!(!stays_inside(name), "{}", name);
+        }
+    }
+}
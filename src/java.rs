@@ -0,0 +1,229 @@
+use std::fmt;
+use std::process::Command;
+use std::str::FromStr;
+
+use crate::{Error, Result};
+
+#[derive(Debug)]
+pub struct JavaInstallation {
+    pub version: String,
+    pub major: u32,
+    pub vendor: JavaVendor,
+}
+
+/// Who built a Java installation, as far as `java -version` says. Builds
+/// that don't name themselves are [`JavaVendor::OpenJdk`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JavaVendor {
+    /// Oracle's own JDK, which leaves out Shenandoah.
+    Oracle,
+    /// Eclipse Temurin, or AdoptOpenJDK before it.
+    Temurin,
+    Zulu,
+    Corretto,
+    Microsoft,
+    RedHat,
+    GraalVm,
+    /// IBM Semeru or anything else on the OpenJ9 VM, which has its own
+    /// collectors instead of HotSpot's.
+    OpenJ9,
+    OpenJdk,
+}
+
+impl JavaVendor {
+    fn detect(output: &str) -> Self {
+        // The VM line comes before the vendor check, as OpenJ9 builds also
+        // call themselves OpenJDK.
+        let vendors = [
+            ("OpenJ9", JavaVendor::OpenJ9),
+            ("J9 VM", JavaVendor::OpenJ9),
+            ("GraalVM", JavaVendor::GraalVm),
+            ("Temurin", JavaVendor::Temurin),
+            ("AdoptOpenJDK", JavaVendor::Temurin),
+            ("Zulu", JavaVendor::Zulu),
+            ("Corretto", JavaVendor::Corretto),
+            ("Microsoft", JavaVendor::Microsoft),
+            ("Red_Hat", JavaVendor::RedHat),
+            ("Red Hat", JavaVendor::RedHat),
+            ("Java(TM)", JavaVendor::Oracle),
+        ];
+
+        vendors
+            .into_iter()
+            .find(|(marker, _)| output.contains(marker))
+            .map_or(JavaVendor::OpenJdk, |(_, vendor)| vendor)
+    }
+}
+
+impl fmt::Display for JavaVendor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            JavaVendor::Oracle => "Oracle",
+            JavaVendor::Temurin => "Temurin",
+            JavaVendor::Zulu => "Zulu",
+            JavaVendor::Corretto => "Corretto",
+            JavaVendor::Microsoft => "Microsoft",
+            JavaVendor::RedHat => "Red Hat",
+            JavaVendor::GraalVm => "GraalVM",
+            JavaVendor::OpenJ9 => "OpenJ9",
+            JavaVendor::OpenJdk => "OpenJDK",
+        })
+    }
+}
+
+/// Runs `java -version` and parses the version it reports.
+pub fn detect_java(java: &str) -> Result<JavaInstallation> {
+    let output = Command::new(java)
+        .arg("-version")
+        .output()
+        .map_err(|e| Error::Java(format!("could not run {}: {}", java, e)))?;
+
+    // java -version prints to stderr.
+    let text = String::from_utf8_lossy(&output.stderr);
+
+    parse_java_version(&text)
+        .ok_or_else(|| Error::Java(format!("could not parse the output of {} -version", java)))
+}
+
+fn parse_java_version(output: &str) -> Option<JavaInstallation> {
+    let start = output.find("version \"")? + "version \"".len();
+    let end = start + output[start..].find('"')?;
+    let version = &output[start..end];
+
+    // Java 8 and older report themselves as 1.x.
+    let mut parts = version.split(['.', '_', '-', '+']);
+    let first = parts.next()?.parse::<u32>().ok()?;
+    let major = if first == 1 {
+        parts.next()?.parse::<u32>().ok()?
+    } else {
+        first
+    };
+
+    Some(JavaInstallation {
+        version: version.to_owned(),
+        major,
+        vendor: JavaVendor::detect(output),
+    })
+}
+
+/// Runs `java` with `flags` and returns what it said about the first one it
+/// rejected, like `Unrecognized VM option 'UseFooGC'`. `None` when it starts
+/// fine with all of them, or can't be run at all.
+pub fn rejected_flag(java: &str, flags: &[String]) -> Option<String> {
+    let output = Command::new(java)
+        .args(flags)
+        .arg("-version")
+        .output()
+        .ok()?;
+
+    if output.status.success() {
+        return None;
+    }
+
+    let text = String::from_utf8_lossy(&output.stderr);
+    let reason = text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or("it failed to start");
+
+    Some(reason.trim_start_matches("Error: ").to_owned())
+}
+
+/// Curated garbage collector flag sets, so picking a collector doesn't require
+/// knowing the `-XX` flags that go with it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum GcPreset {
+    #[default]
+    G1,
+    #[serde(rename = "ZGC")]
+    Zgc,
+    Shenandoah,
+}
+
+impl GcPreset {
+    /// Fails when the collector isn't available in the given Java version.
+    /// On OpenJ9, which ignores HotSpot's G1 flags, G1 is always fine.
+    pub fn check_available(self, java: &JavaInstallation) -> Result<()> {
+        let unavailable = |reason: &str| {
+            Err(Error::Java(format!(
+                "{} isn't available in {} Java {} ({}), pick another gc",
+                self, java.vendor, java.version, reason
+            )))
+        };
+
+        let minimum = match (self, java.vendor) {
+            (GcPreset::G1, _) => return Ok(()),
+            (_, JavaVendor::OpenJ9) => return unavailable("OpenJ9 only has its own collectors"),
+            (GcPreset::Shenandoah, JavaVendor::Oracle) => {
+                return unavailable(
+                    "Oracle's JDK is built without it, Temurin and most other builds have it",
+                )
+            }
+            (GcPreset::Zgc, _) => 11,
+            (GcPreset::Shenandoah, _) => 12,
+        };
+
+        if java.major < minimum {
+            return Err(Error::Java(format!(
+                "{} needs Java {} or newer, found Java {}",
+                self, minimum, java.version
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// JVM flags for the collector. `java` picks up the extra flags older
+    /// Java versions need, when known. OpenJ9 gets none, as it has no
+    /// HotSpot collectors and picks its own.
+    pub fn jvm_flags(self, java: Option<&JavaInstallation>) -> Vec<&'static str> {
+        if java.is_some_and(|java| java.vendor == JavaVendor::OpenJ9) {
+            return Vec::new();
+        }
+
+        let java_major = java.map(|java| java.major);
+        match self {
+            GcPreset::G1 => vec![
+                "-XX:+UnlockExperimentalVMOptions",
+                "-XX:+UseG1GC",
+                "-XX:G1NewSizePercent=20",
+                "-XX:G1ReservePercent=20",
+                "-XX:MaxGCPauseMillis=50",
+                "-XX:G1HeapRegionSize=32M",
+            ],
+            GcPreset::Zgc => match java_major {
+                // ZGC was experimental before Java 15.
+                Some(major) if major < 15 => {
+                    vec!["-XX:+UnlockExperimentalVMOptions", "-XX:+UseZGC"]
+                }
+                Some(21..=22) => vec!["-XX:+UseZGC", "-XX:+ZGenerational"],
+                _ => vec!["-XX:+UseZGC"],
+            },
+            GcPreset::Shenandoah => vec!["-XX:+UseShenandoahGC"],
+        }
+    }
+}
+
+impl fmt::Display for GcPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            GcPreset::G1 => "G1",
+            GcPreset::Zgc => "ZGC",
+            GcPreset::Shenandoah => "Shenandoah",
+        })
+    }
+}
+
+impl FromStr for GcPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "g1" => Ok(GcPreset::G1),
+            "zgc" => Ok(GcPreset::Zgc),
+            "shenandoah" => Ok(GcPreset::Shenandoah),
+            _ => Err(format!("unknown gc {}, expected ZGC, G1 or Shenandoah", s)),
+        }
+    }
+}
@@ -0,0 +1,1000 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::process::Command;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use crate::accounts::{owns_minecraft, Account, Accounts};
+use crate::arguments::{apply_template, meta_arguments, meta_has_feature};
+use crate::config::{
+    apply_environment, config_fields, load_config, parse_config, read_config, AssetLayout,
+    LauncherConfig, CONFIG_FILE_NAME,
+};
+use crate::crashes::redirect_reports;
+use crate::download::{legacy_assets_directory, refresh_natives, ASSET_VIEW_DIRECTORY};
+use crate::error::PathContext;
+use crate::java::{detect_java, rejected_flag, GcPreset, JavaVendor};
+use crate::lock::{installed_asset_index, InstanceLock};
+use crate::meta::{load_instance_meta, parse_libraries, Library};
+use crate::mods::{find_mod_conflicts, installed_mods};
+use crate::options::seed_options;
+use crate::platform::os_problems;
+use crate::skin::install_skin;
+use crate::verify::verify_instance;
+use crate::zip::ZipArchive;
+use crate::{Error, Result, NATIVES_DIRECTORY};
+
+#[derive(Debug, Default)]
+pub struct LaunchOptions {
+    /// Stored account to launch with, overriding the config's default.
+    pub account: Option<String>,
+    /// Garbage collector preset, overriding the config's.
+    pub gc: Option<GcPreset>,
+    /// Extra JVM arguments for this launch, placed after the default and
+    /// config flags so they can override them.
+    pub jvm_args: Vec<String>,
+    /// Save under `saves/` to open straight into, on versions with quick play.
+    pub world: Option<String>,
+    /// Start the game in demo mode.
+    pub demo: bool,
+    /// Skip `verify_on_launch` when the lock matches the cached meta.
+    pub trust_cache: bool,
+    /// Run the game in this directory instead of the config's
+    /// `game_directory`.
+    pub game_directory: Option<PathBuf>,
+    /// Wipe and extract the natives again even if their jars haven't
+    /// changed since they were last extracted.
+    pub clean_natives: bool,
+    /// Check the mods like `check_mods` and refuse to launch if anything is
+    /// wrong with them.
+    pub strict_mods: bool,
+    /// Refuse to launch on an OS too old for the version instead of warning.
+    pub strict: bool,
+    /// Run Java through this command instead of the config's
+    /// `wrapper_command`.
+    pub wrapper: Vec<String>,
+    /// Build the command without writing argfiles, the log4j config, the
+    /// checksum cache or a migrated config, passing what argfiles would hold
+    /// inline, so printing it leaves the instance as it was. `verify_on_launch`
+    /// is skipped.
+    pub dry_run: bool,
+}
+
+impl LaunchOptions {
+    /// Lays the options that override config fields over `config`. Returns
+    /// the fields they set.
+    fn apply(&self, config: &mut LauncherConfig) -> Vec<&'static str> {
+        let mut set = Vec::new();
+
+        if let Some(account) = &self.account {
+            config.account = Some(account.clone());
+            set.push("account");
+        }
+        if let Some(gc) = self.gc {
+            config.gc = gc;
+            set.push("gc");
+        }
+        if !self.jvm_args.is_empty() {
+            config.jvm_args.extend(self.jvm_args.iter().cloned());
+            set.push("jvm_args");
+        }
+        if let Some(game_directory) = &self.game_directory {
+            // Relative to where the launcher runs, not to the instance.
+            let game_directory =
+                std::path::absolute(game_directory).unwrap_or_else(|_| game_directory.clone());
+            config.game_directory = Some(game_directory.to_string_lossy().into_owned());
+            set.push("game_directory");
+        }
+        if !self.wrapper.is_empty() {
+            config.wrapper_command = self.wrapper.clone();
+            set.push("wrapper_command");
+        }
+
+        set
+    }
+}
+
+/// Where a field of the config a launch runs with came from, from the
+/// lowest precedence up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigSource {
+    Default,
+    File,
+    Environment,
+    CommandLine,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ConfigSource::Default => "default",
+            ConfigSource::File => "file",
+            ConfigSource::Environment => "environment",
+            ConfigSource::CommandLine => "command line",
+        })
+    }
+}
+
+/// The config a launch with `options` runs with: the instance's, then the
+/// [`CONFIG_ENV_PREFIX`](crate::config::CONFIG_ENV_PREFIX) environment
+/// variables, then `options`. A dry run reads the config without migrating
+/// it.
+pub fn launch_config(instance_directory: &Path, options: &LaunchOptions) -> Result<LauncherConfig> {
+    let mut config = match options.dry_run {
+        true => read_config(instance_directory)?,
+        false => load_config(instance_directory)?,
+    };
+    apply_environment(&mut config)?;
+    options.apply(&mut config);
+
+    Ok(config)
+}
+
+/// [`launch_config`] and where each of its fields came from, reading the
+/// config file without rewriting it.
+pub fn effective_config(
+    instance_directory: &Path,
+    options: &LaunchOptions,
+) -> Result<(LauncherConfig, Vec<(&'static str, ConfigSource)>)> {
+    let (mut config, on_disk) = parse_config(&instance_directory.join(CONFIG_FILE_NAME))?;
+    let from_environment = apply_environment(&mut config)?;
+    let from_options = options.apply(&mut config);
+
+    let sources = config_fields()
+        .iter()
+        .map(|&field| {
+            let source = if from_options.contains(&field) {
+                ConfigSource::CommandLine
+            } else if from_environment.contains(&field) {
+                ConfigSource::Environment
+            } else if on_disk.contains_key(field) {
+                ConfigSource::File
+            } else {
+                ConfigSource::Default
+            };
+            (field, source)
+        })
+        .collect();
+
+    Ok((config, sources))
+}
+
+/// Who the game is launched as.
+#[derive(Debug)]
+pub struct Session {
+    pub username: String,
+    pub uuid: String,
+    pub access_token: Option<String>,
+    pub xuid: Option<String>,
+    pub client_id: Option<String>,
+}
+
+impl From<Account> for Session {
+    fn from(account: Account) -> Self {
+        Self {
+            username: account.name,
+            uuid: account.uuid,
+            access_token: account.access_token,
+            xuid: account.xuid,
+            client_id: account.client_id,
+        }
+    }
+}
+
+/// Picks the account named on the command line, then the config's default
+/// account, and falls back to an offline session for the config username.
+pub fn select_session(config: &LauncherConfig, options: &LaunchOptions) -> Result<Session> {
+    let account_name = options.account.as_ref().or(config.account.as_ref());
+
+    let account = match account_name {
+        Some(name) => Accounts::load(&Accounts::default_path())?
+            .get(name)?
+            .clone(),
+        None => Account::offline(&config.username),
+    };
+
+    Ok(account.into())
+}
+
+/// Refreshes the launching account's session when its access token has
+/// expired or is about to, and saves the new one. Fails with
+/// [`Error::SessionExpired`] when that doesn't work, since the game would
+/// only kick the player for an invalid session.
+pub async fn refresh_session(
+    http_client: &reqwest::Client,
+    instance_directory: &Path,
+    options: &LaunchOptions,
+) -> Result<()> {
+    let config = launch_config(instance_directory, options)?;
+    let Some(name) = options.account.as_ref().or(config.account.as_ref()) else {
+        return Ok(());
+    };
+
+    let path = Accounts::default_path();
+    let mut accounts = Accounts::load(&path)?;
+    let account = accounts.get_mut(name)?;
+
+    if !account.needs_refresh() {
+        return Ok(());
+    }
+
+    match account.refresh(http_client).await {
+        Ok(()) => {}
+        Err(e @ Error::SessionExpired { .. }) => return Err(e),
+        Err(e) => {
+            return Err(Error::SessionExpired {
+                account: name.clone(),
+                reason: e.to_string(),
+            })
+        }
+    }
+
+    accounts.save(&path)?;
+    eprintln!("refreshed the session of {}", name);
+
+    Ok(())
+}
+
+/// Checks that the launching account owns the game, switching the launch to
+/// demo mode when it doesn't, or failing with `refuse_unowned`. Offline
+/// accounts have nothing to check, and a failed check doesn't stop a launch.
+pub async fn check_ownership(
+    http_client: &reqwest::Client,
+    instance_directory: &Path,
+    options: &mut LaunchOptions,
+) -> Result<()> {
+    let config = launch_config(instance_directory, options)?;
+    let session = select_session(&config, options)?;
+
+    let Some(access_token) = &session.access_token else {
+        return Ok(());
+    };
+
+    match owns_minecraft(http_client, access_token).await {
+        Ok(true) => {}
+        Ok(false) if config.refuse_unowned => return Err(Error::NotOwned(session.username)),
+        Ok(false) => {
+            eprintln!("this account doesn't own Minecraft; launching demo.");
+            options.demo = true;
+        }
+        Err(e) => eprintln!(
+            "warning: could not check whether {} owns Minecraft: {}",
+            session.username, e
+        ),
+    }
+
+    Ok(())
+}
+
+fn _list_files(paths: &mut Vec<PathBuf>, path: &Path) -> Result<()> {
+    let dir_paths = fs::read_dir(path).with_path(path)?;
+    for entry in dir_paths {
+        let entry_path = entry?.path();
+        if entry_path.is_dir() {
+            _list_files(paths, &entry_path)?;
+        } else {
+            paths.push(entry_path)
+        }
+    }
+    Ok(())
+}
+
+pub fn list_files(path: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    _list_files(&mut paths, path)?;
+    Ok(paths)
+}
+
+/// Log lines that mean the game got far enough to count as started.
+const STARTUP_MARKERS: &[&str] = &["Setting user:", "Backend library:"];
+
+/// Echoes the game's output and waits for it to log one of the startup
+/// markers, killing it when that takes longer than `timeout`. Output keeps
+/// being echoed afterwards by the returned thread, which finishes once the
+/// game exits.
+pub fn wait_for_startup(
+    child: &mut process::Child,
+    timeout: Duration,
+) -> Result<thread::JoinHandle<()>> {
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| Error::Startup(String::from("the game's output isn't captured")))?;
+    let (started, startup) = mpsc::channel();
+
+    let echo = thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(|line| line.ok()) {
+            // The game's own output, so it stays on stdout.
+            println!("{}", line);
+
+            if STARTUP_MARKERS.iter().any(|marker| line.contains(marker)) {
+                let _ = started.send(());
+            }
+        }
+    });
+
+    match startup.recv_timeout(timeout) {
+        Ok(()) => Ok(echo),
+        Err(mpsc::RecvTimeoutError::Disconnected) => {
+            let status = child.wait()?;
+            Err(Error::Startup(format!(
+                "the game exited ({}) before starting",
+                status
+            )))
+        }
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+            child.kill()?;
+            child.wait()?;
+            Err(Error::Startup(format!(
+                "the game didn't start within {}s and was killed",
+                timeout.as_secs()
+            )))
+        }
+    }
+}
+
+const CLASSPATH_SEPARATOR: &str = if cfg!(windows) { ";" } else { ":" };
+
+/// Where the JVM arguments go when they're passed as an `@argfile`.
+pub const ARGFILE_NAME: &str = "jvm.args";
+
+/// Where the main class and the game's arguments, access token included, go
+/// with `token_argfile`.
+pub const GAME_ARGFILE_NAME: &str = "game.args";
+
+/// `key=value` lines in the instance, each passed as `-Dkey=value`.
+pub const JVM_PROPERTIES_FILE_NAME: &str = "jvm.properties";
+
+/// Longest command line passed inline, a little under Windows' 32767
+/// characters and Linux's 128 KiB for any one argument.
+const MAX_COMMAND_LINE: usize = if cfg!(windows) { 32_000 } else { 128_000 };
+
+/// The instance's classpath in launch order: every library that applies to
+/// this platform, once per coordinate with the first occurrence winning, then
+/// the client jar. Natives that ship as a classifier are extracted rather
+/// than put on the classpath, so they aren't included.
+pub fn build_classpath(instance_directory: &Path) -> Result<Vec<PathBuf>> {
+    let meta = load_instance_meta(instance_directory)?;
+    let libraries_directory = std::path::absolute(instance_directory.join("libraries"))?;
+
+    let mut seen = HashSet::new();
+    let mut classpath = parse_libraries(&meta)?
+        .iter()
+        .filter(|library| library.applies() && seen.insert(library.key()))
+        .filter_map(|library| library.downloads.artifact.as_ref())
+        .map(|artifact| libraries_directory.join(&artifact.path))
+        .collect::<Vec<_>>();
+
+    classpath.push(std::path::absolute(instance_directory.join("client.jar"))?);
+
+    Ok(classpath)
+}
+
+/// Assembles the JVM invocation for the instance at `instance_directory`
+/// without running it.
+pub fn build_launch_command(instance_directory: &Path, options: &LaunchOptions) -> Result<Command> {
+    let libraries_path = instance_directory.join("libraries");
+    let assets_path = instance_directory.join("assets");
+
+    let config = launch_config(instance_directory, options)?;
+    let meta = load_instance_meta(instance_directory)?;
+    let game_directory = config.game_directory(instance_directory);
+
+    let lock = InstanceLock::load(instance_directory)?;
+
+    if let Some(lock) = &lock {
+        lock.check_meta(&meta)?;
+    }
+
+    let trusted =
+        options.trust_cache && lock.is_some_and(|lock| lock.meta_unchanged(instance_directory));
+
+    if config.verify_on_launch && !trusted && !options.dry_run {
+        let report = verify_instance(instance_directory, true)?;
+
+        if !report.is_ok() {
+            return Err(Error::CorruptInstance(
+                report.missing.len() + report.corrupt.len(),
+            ));
+        }
+    }
+
+    if config.check_mods || options.strict_mods {
+        let conflicts = find_mod_conflicts(&installed_mods(&game_directory));
+
+        for conflict in &conflicts {
+            eprintln!("warning: {}", conflict);
+        }
+
+        if options.strict_mods && !conflicts.is_empty() {
+            return Err(Error::ModConflicts(conflicts.len()));
+        }
+    }
+
+    let os_problems = os_problems(&meta);
+
+    for problem in &os_problems {
+        eprintln!("warning: {}", problem);
+    }
+
+    if options.strict {
+        if let Some(problem) = os_problems.into_iter().next() {
+            return Err(Error::UnsupportedOs(problem));
+        }
+    }
+
+    let version = meta["id"]
+        .as_str()
+        .ok_or_else(|| Error::InvalidMeta(String::from("missing /id")))?;
+    let version_type = meta["type"].as_str().unwrap_or("release");
+    let main_class = match &config.main_class {
+        Some(main_class) => main_class.as_str(),
+        None => meta["mainClass"]
+            .as_str()
+            .ok_or_else(|| Error::InvalidMeta(String::from("missing /mainClass")))?,
+    };
+    let session = select_session(&config, options)?;
+
+    let gc = config.gc;
+    // javaw doesn't print its version, so always ask the console binary
+    // unless a specific Java is configured.
+    let console_java = config.java_path.as_deref().unwrap_or("java");
+    let java = match detect_java(console_java) {
+        Ok(java) => {
+            gc.check_available(&java)?;
+            if java.vendor == JavaVendor::OpenJ9 {
+                eprintln!(
+                    "OpenJ9 picks its own garbage collector, leaving out the {} flags",
+                    gc
+                );
+            }
+            Some(java)
+        }
+        Err(e) => {
+            eprintln!("warning: {}, not checking that {} is available", e, gc);
+            None
+        }
+    };
+    let java_major = java.as_ref().map(|java| java.major);
+
+    let java_libraries = build_classpath(instance_directory)?
+        .iter()
+        .map(|path| path.to_str().unwrap().to_owned())
+        .collect::<Vec<_>>()
+        .join(CLASSPATH_SEPARATOR);
+
+    let path_str = |path: &Path| path.to_str().unwrap().to_owned();
+
+    // Old versions read their assets by name from the legacy layout. The
+    // oldest have no index at all and don't ask for one.
+    let asset_index_id = installed_asset_index(instance_directory, &meta)?.unwrap_or_default();
+    let asset_index_path = assets_path
+        .join("indexes")
+        .join(format!("{}.json", asset_index_id));
+    let game_assets = fs::read_to_string(asset_index_path)
+        .ok()
+        .and_then(|index| serde_json::from_str(&index).ok())
+        .and_then(|index| {
+            legacy_assets_directory(instance_directory, &assets_path, &asset_index_id, &index)
+        })
+        .unwrap_or_else(|| assets_path.clone());
+    let assets_root = match config.asset_layout {
+        AssetLayout::Nested => assets_path.clone(),
+        AssetLayout::Flat => assets_path.join(ASSET_VIEW_DIRECTORY),
+    };
+    let access_token = session
+        .access_token
+        .clone()
+        .unwrap_or_else(|| String::from("0"));
+
+    let mut variables = HashMap::from([
+        ("auth_player_name", session.username.clone()),
+        ("auth_uuid", session.uuid.clone()),
+        ("auth_access_token", access_token.clone()),
+        ("auth_session", access_token),
+        (
+            "user_type",
+            String::from(if session.access_token.is_some() {
+                "msa"
+            } else {
+                "legacy"
+            }),
+        ),
+        ("user_properties", String::from("{}")),
+        ("version_name", version.to_owned()),
+        ("version_type", version_type.to_owned()),
+        ("game_directory", path_str(&game_directory)),
+        ("assets_root", path_str(&assets_root)),
+        ("game_assets", path_str(&game_assets)),
+        ("assets_index_name", asset_index_id.clone()),
+        (
+            "natives_directory",
+            path_str(&instance_directory.join(NATIVES_DIRECTORY)),
+        ),
+        ("library_directory", path_str(&libraries_path)),
+        ("launcher_name", config.launcher_brand().to_owned()),
+        ("launcher_version", config.launcher_version().to_owned()),
+        ("classpath", java_libraries),
+        ("classpath_separator", String::from(CLASSPATH_SEPARATOR)),
+    ]);
+
+    if let Some(xuid) = &session.xuid {
+        variables.insert("auth_xuid", xuid.clone());
+    }
+
+    if let Some(client_id) = &session.client_id {
+        variables.insert("clientid", client_id.clone());
+    }
+
+    let mut features = Vec::new();
+
+    if options.demo {
+        features.push("is_demo_user");
+    }
+
+    let resolution = config.window_width.zip(config.window_height);
+
+    if let Some((width, height)) = resolution {
+        features.push("has_custom_resolution");
+        variables.insert("resolution_width", width.to_string());
+        variables.insert("resolution_height", height.to_string());
+    }
+
+    if let Some(world) = &options.world {
+        if !game_directory.join("saves").join(world).is_dir() {
+            return Err(Error::UnknownWorld(world.clone()));
+        }
+
+        if meta_has_feature(&meta, "is_quick_play_singleplayer") {
+            features.push("is_quick_play_singleplayer");
+            variables.insert("quick_play_singleplayer", world.clone());
+        } else {
+            eprintln!(
+                "warning: {} can't open a world directly, starting at the title screen",
+                version
+            );
+        }
+    }
+
+    let mut arguments = meta_arguments(&meta, &features);
+
+    // Metas from before `arguments` have no rules for these to turn on.
+    if meta.get("arguments").is_none() {
+        if options.demo {
+            arguments.game.push(String::from("--demo"));
+        }
+
+        if let Some((width, height)) = resolution {
+            arguments.game.extend([
+                String::from("--width"),
+                width.to_string(),
+                String::from("--height"),
+                height.to_string(),
+            ]);
+        }
+    }
+
+    let mut jvm_arguments = apply_template(&arguments.jvm, &variables);
+    let module_path = config
+        .module_path
+        .iter()
+        .map(|path| path_str(&instance_directory.join(path)))
+        .collect::<Vec<_>>();
+    add_module_flags(&mut jvm_arguments, &module_path, &config.add_modules);
+
+    if config.log4j_mitigation {
+        jvm_arguments.extend(log4j_mitigation(
+            instance_directory,
+            &parse_libraries(&meta)?,
+            options.dry_run,
+        )?);
+    }
+
+    jvm_arguments.push(format!("-Xmx{}M", config.max_memory_mb));
+    jvm_arguments.extend(gc.jvm_flags(java.as_ref()).into_iter().map(String::from));
+
+    // Vendors differ in the -XX flags they take, and the JVM refuses to
+    // start on one it doesn't know, so try the configured ones first.
+    let extra_flags = config
+        .jvm_args
+        .iter()
+        .filter(|argument| argument.starts_with("-XX:"))
+        .cloned()
+        .collect::<Vec<_>>();
+    if let Some(java) = java.as_ref().filter(|_| !extra_flags.is_empty()) {
+        if let Some(reason) = rejected_flag(console_java, &extra_flags) {
+            eprintln!(
+                "warning: {} Java {} doesn't accept the jvm_args ({}), the game will likely \
+                 fail to start",
+                java.vendor, java.version, reason
+            );
+        }
+    }
+
+    // Before the jvm_args, so a -D there still wins.
+    jvm_arguments.extend(jvm_properties(instance_directory)?);
+    jvm_arguments.extend(config.jvm_args.iter().cloned());
+
+    let game_arguments = apply_template(&arguments.game, &variables);
+    let length = jvm_arguments
+        .iter()
+        .chain(&game_arguments)
+        .map(|argument| argument.len() + 1)
+        .sum::<usize>();
+
+    // Java 8 doesn't read argfiles, so a long one is left to fail there.
+    if !options.dry_run
+        && (config.use_argfile
+            || (length > MAX_COMMAND_LINE && java_major.is_some_and(|major| major >= 9)))
+    {
+        let argfile_path = std::path::absolute(instance_directory.join(ARGFILE_NAME))?;
+        write_argfile(&argfile_path, &jvm_arguments)?;
+        jvm_arguments = vec![format!("@{}", path_str(&argfile_path))];
+    }
+
+    let mut application_arguments = std::iter::once(main_class.to_owned())
+        .chain(game_arguments)
+        .collect::<Vec<_>>();
+    let game_argfile_path = std::path::absolute(instance_directory.join(GAME_ARGFILE_NAME))?;
+
+    if options.dry_run {
+        // The token stays on the command line, where printing hides it.
+    } else if config.token_argfile && session.access_token.is_some() {
+        if java_major.is_some_and(|major| major >= 9) {
+            write_argfile(&game_argfile_path, &application_arguments)?;
+            application_arguments = vec![format!("@{}", path_str(&game_argfile_path))];
+        } else {
+            eprintln!(
+                "warning: token_argfile needs Java 9 or newer, passing the access token on the \
+                 command line"
+            );
+        }
+    } else if game_argfile_path.exists() {
+        // Don't leave an old token lying around once it's no longer used.
+        fs::remove_file(&game_argfile_path).with_path(&game_argfile_path)?;
+    }
+
+    let mut command = match config.wrapper_command.split_first() {
+        Some((program, arguments)) => {
+            let mut command = Command::new(program);
+            command.args(arguments).arg(config.java_command());
+            command
+        }
+        None => Command::new(config.java_command()),
+    };
+    command
+        .stdin(process::Stdio::piped())
+        .stdout(process::Stdio::piped())
+        .args(jvm_arguments)
+        .args(application_arguments);
+
+    Ok(command)
+}
+
+/// The game's usual logging, with lookups turned off in every message.
+const HARDENED_LOG4J_CONFIG: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Configuration status="WARN">
+    <Appenders>
+        <Console name="SysOut" target="SYSTEM_OUT">
+            <PatternLayout pattern="[%d{HH:mm:ss}] [%t/%level]: %msg{nolookups}%n" />
+        </Console>
+        <RollingRandomAccessFile name="File" fileName="logs/latest.log" filePattern="logs/%d{yyyy-MM-dd}-%i.log.gz">
+            <PatternLayout pattern="[%d{HH:mm:ss}] [%t/%level]: %msg{nolookups}%n" />
+            <Policies>
+                <TimeBasedTriggeringPolicy />
+                <OnStartupTriggeringPolicy />
+            </Policies>
+        </RollingRandomAccessFile>
+    </Appenders>
+    <Loggers>
+        <Root level="info">
+            <AppenderRef ref="SysOut" />
+            <AppenderRef ref="File" />
+        </Root>
+    </Loggers>
+</Configuration>
+"#;
+
+/// Where [`HARDENED_LOG4J_CONFIG`] is written in the instance.
+pub const LOG4J_CONFIG_NAME: &str = "log4j2-hardened.xml";
+
+/// The flag that turns off message lookups when the libraries include a
+/// log4j from before 2.16, which let a chat message load code over JNDI.
+/// 2.7 to 2.9 don't know the flag, so they get a logging config without
+/// lookups instead, which a `dry_run` only names. Anything older only gets a
+/// warning.
+fn log4j_mitigation(
+    instance_directory: &Path,
+    libraries: &[Library],
+    dry_run: bool,
+) -> Result<Option<String>> {
+    let version = libraries.iter().find_map(|library| {
+        library
+            .name
+            .strip_prefix("org.apache.logging.log4j:log4j-core:")
+    });
+    let Some(version) = version else {
+        return Ok(None);
+    };
+
+    // Like 2.8.1, or 2.0-beta9 for the oldest versions.
+    let mut parts = version
+        .split(['.', '-'])
+        .map(|part| part.parse::<u32>().ok());
+    let (Some(Some(2)), Some(Some(minor))) = (parts.next(), parts.next()) else {
+        return Ok(None);
+    };
+
+    Ok(match minor {
+        16.. => None,
+        10.. => Some(String::from("-Dlog4j2.formatMsgNoLookups=true")),
+        7.. => {
+            let config_path = std::path::absolute(instance_directory.join(LOG4J_CONFIG_NAME))?;
+            if !dry_run {
+                fs::write(&config_path, HARDENED_LOG4J_CONFIG).with_path(&config_path)?;
+            }
+            Some(format!(
+                "-Dlog4j.configurationFile={}",
+                config_path.to_str().unwrap()
+            ))
+        }
+        _ => {
+            eprintln!(
+                "warning: log4j {} is vulnerable to Log4Shell and too old to turn its lookups off, only join servers you trust",
+                version
+            );
+            None
+        }
+    })
+}
+
+/// The `-D` arguments for the instance's [`JVM_PROPERTIES_FILE_NAME`], none
+/// when it doesn't have one. Blank lines and ones starting with `#` are
+/// skipped, and lines that aren't `key=value` are warned about.
+fn jvm_properties(instance_directory: &Path) -> Result<Vec<String>> {
+    let path = instance_directory.join(JVM_PROPERTIES_FILE_NAME);
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_path(&path),
+    };
+
+    let mut properties = Vec::new();
+
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match line.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() && !key.trim().contains(' ') => {
+                properties.push(format!("-D{}={}", key.trim(), value.trim()));
+            }
+            _ => eprintln!(
+                "warning: ignoring line {} of {}, it isn't key=value: {}",
+                number + 1,
+                path.display(),
+                line
+            ),
+        }
+    }
+
+    Ok(properties)
+}
+
+/// Writes `arguments` as a Java `@argfile`, one quoted argument per line.
+/// On Unix only the user can read it, as it may hold the access token.
+fn write_argfile(path: &Path, arguments: &[String]) -> Result<()> {
+    let contents = arguments
+        .iter()
+        .map(|argument| {
+            format!(
+                "\"{}\"\n",
+                argument.replace('\\', "\\\\").replace('"', "\\\"")
+            )
+        })
+        .collect::<String>();
+
+    let mut file = fs::OpenOptions::new();
+    file.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut file, 0o600);
+
+    file.open(path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .with_path(path)
+}
+
+/// Fails with [`Error::CorruptClient`] when client.jar isn't a readable zip,
+/// or for vanilla metas lacks the main class, instead of the JVM failing with
+/// a ClassNotFoundException. Loaders and a configured `main_class` start
+/// from a library, so only the jar itself is checked for them.
+fn check_client_jar(
+    instance_directory: &Path,
+    meta: &serde_json::Value,
+    config: &LauncherConfig,
+) -> Result<()> {
+    let archive = ZipArchive::open(&instance_directory.join("client.jar"))
+        .map_err(|e| Error::CorruptClient(e.to_string()))?;
+
+    let vanilla_main_class = meta["mainClass"]
+        .as_str()
+        .filter(|_| meta.get("inheritsFrom").is_none() && config.main_class.is_none());
+
+    if let Some(main_class) = vanilla_main_class {
+        let class_file = format!("{}.class", main_class.replace('.', "/"));
+
+        if archive.find(&class_file).is_none() {
+            return Err(Error::CorruptClient(format!("it has no {}", class_file)));
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds `module_path` and `modules` to the JVM arguments. Only the last
+/// `--module-path` counts, so when the meta already has one, like NeoForge's
+/// `-p`, the paths go on the end of it instead.
+fn add_module_flags(jvm_arguments: &mut Vec<String>, module_path: &[String], modules: &[String]) {
+    if !module_path.is_empty() {
+        let module_path = module_path.join(CLASSPATH_SEPARATOR);
+        let existing = jvm_arguments
+            .iter()
+            .rposition(|argument| argument == "-p" || argument == "--module-path")
+            .and_then(|flag| jvm_arguments.get_mut(flag + 1));
+
+        match existing {
+            Some(existing) => {
+                existing.push_str(CLASSPATH_SEPARATOR);
+                existing.push_str(&module_path);
+            }
+            None => jvm_arguments.extend([String::from("--module-path"), module_path]),
+        }
+    }
+
+    if !modules.is_empty() {
+        jvm_arguments.extend([String::from("--add-modules"), modules.join(",")]);
+    }
+}
+
+/// Spawns the game for the instance at `instance_directory` and returns the
+/// running JVM.
+pub fn launch_minecraft(
+    instance_directory: &Path,
+    options: &LaunchOptions,
+) -> Result<process::Child> {
+    let config = launch_config(instance_directory, options)?;
+    let game_directory = config.game_directory(instance_directory);
+    fs::create_dir_all(&game_directory).with_path(&game_directory)?;
+
+    // For instances created before gui_scale was set, and game directories
+    // the game hasn't run in yet.
+    seed_options(&game_directory, &config)?;
+
+    if let Some(skin_path) = &config.skin_path {
+        let session = select_session(&config, options)?;
+        install_skin(
+            &game_directory,
+            &session.username,
+            &instance_directory.join(skin_path),
+        )?;
+    }
+
+    if let Err(e) = redirect_reports(&game_directory, &config) {
+        eprintln!("warning: could not redirect crash reports and logs: {}", e);
+    }
+
+    let meta = load_instance_meta(instance_directory)?;
+    check_client_jar(instance_directory, &meta, &config)?;
+
+    let libraries = parse_libraries(&meta)?;
+    let extracted = refresh_natives(
+        &instance_directory.join("libraries"),
+        &instance_directory.join(NATIVES_DIRECTORY),
+        &libraries,
+        options.clean_natives,
+    )?;
+    if extracted {
+        eprintln!("extracted the natives again");
+    }
+
+    build_launch_command(instance_directory, options)?
+        .spawn()
+        .map_err(|source| Error::Launch { source })
+}
+
+fn quote_argument(argument: &str) -> String {
+    let plain = !argument.is_empty()
+        && argument
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=,@+%".contains(c));
+
+    if plain {
+        argument.to_owned()
+    } else if cfg!(windows) {
+        format!("\"{}\"", argument.replace('"', "\\\""))
+    } else {
+        format!("'{}'", argument.replace('\'', "'\\''"))
+    }
+}
+
+/// `command` as a line that can be pasted into a shell. Any of `secrets`
+/// are replaced by a placeholder so the line is safe to share.
+pub fn format_command(command: &Command, secrets: &[&str]) -> String {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|argument| {
+            let argument = argument.to_string_lossy();
+
+            if secrets.contains(&argument.as_ref()) {
+                String::from("<redacted>")
+            } else {
+                quote_argument(&argument)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::META_FILE_NAME;
+
+    fn library(name: &str, path: &str) -> serde_json::Value {
+        serde_json::json!({
+            "name": name,
+            "downloads": { "artifact": { "path": path, "url": "https://example.com" } }
+        })
+    }
+
+    #[test]
+    fn classpath_keeps_the_first_of_each_library_and_ends_with_the_client() {
+        let instance = std::env::temp_dir().join(format!("blazinglyassmc-cp-{}", process::id()));
+        fs::create_dir_all(&instance).unwrap();
+
+        // A merged meta lists the child's libraries before the parent's.
+        let mut excluded = library("com.example:other-os:1.0", "other-os.jar");
+        excluded["rules"] = serde_json::json!([{ "action": "allow", "os": { "name": "none" } }]);
+        let meta = serde_json::json!({
+            "downloads": {},
+            "libraries": [
+                library("org.ow2.asm:asm:9.6", "asm-9.6.jar"),
+                library("net.fabricmc:fabric-loader:0.15.7", "fabric-loader.jar"),
+                library("org.ow2.asm:asm:9.3", "asm-9.3.jar"),
+                excluded,
+                {
+                    "name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.4",
+                    "natives": { "linux": "natives-linux", "osx": "natives-osx", "windows": "natives-windows" },
+                    "downloads": { "classifiers": {
+                        "natives-linux": { "path": "natives-linux.jar", "url": "https://example.com" },
+                        "natives-osx": { "path": "natives-osx.jar", "url": "https://example.com" },
+                        "natives-windows": { "path": "natives-windows.jar", "url": "https://example.com" }
+                    } }
+                },
+                library("com.mojang:brigadier:1.2.9", "brigadier.jar")
+            ]
+        });
+        fs::write(instance.join(META_FILE_NAME), meta.to_string()).unwrap();
+
+        let classpath = build_classpath(&instance);
+        fs::remove_dir_all(&instance).unwrap();
+
+        let libraries = std::path::absolute(instance.join("libraries")).unwrap();
+        assert_eq!(
+            classpath.unwrap(),
+            [
+                libraries.join("asm-9.6.jar"),
+                libraries.join("fabric-loader.jar"),
+                libraries.join("brigadier.jar"),
+                std::path::absolute(instance.join("client.jar")).unwrap(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,818 @@
+mod accounts;
+mod arguments;
+mod compare;
+mod config;
+mod crashes;
+mod doctor;
+mod download;
+mod error;
+mod filter;
+mod fsutil;
+mod java;
+mod launch;
+mod lock;
+mod manifest;
+mod md5;
+mod meta;
+mod mods;
+mod nbt;
+mod notify;
+mod options;
+mod paths;
+mod platform;
+mod portable;
+mod progress;
+mod refresh;
+mod resume;
+mod rules;
+mod servers;
+mod sha1;
+mod shortcut;
+mod skin;
+mod status;
+mod store;
+mod template;
+mod throttle;
+mod usage;
+mod verify;
+mod zip;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub use accounts::{owns_minecraft, Account, Accounts};
+pub use compare::{compare_instances, Difference};
+pub use config::{
+    load_config, read_config, save_config, AssetLayout, IpPreference, LauncherConfig,
+    CONFIG_FILE_NAME,
+};
+pub use crashes::{find_crash_reports, CrashReport};
+pub use doctor::{is_valid_username, run_doctor, Check, CheckStatus};
+pub use download::{http_client, http_client_for, Concurrency, Downloader};
+pub use error::{Error, PathContext, Result};
+pub use filter::AssetFilter;
+pub use java::{detect_java, GcPreset, JavaInstallation, JavaVendor};
+pub use launch::{
+    build_classpath, build_launch_command, check_ownership, effective_config, format_command,
+    launch_config, launch_minecraft, list_files, refresh_session, select_session, wait_for_startup,
+    ConfigSource, LaunchOptions, Session,
+};
+pub use lock::InstanceLock;
+pub use manifest::{
+    fetch_version_manifest, load_version_manifest, resolve_fabric_meta_url, resolve_meta_url,
+    ManifestVersion, VersionManifest, VERSION_MANIFEST_URL,
+};
+pub use meta::{
+    check_natives_arch, maven_path, override_lwjgl, parse_libraries, Artifact, Library,
+    LibraryDownloads, MINECRAFT_1_20_4_META_URL,
+};
+pub use mods::{find_mod_conflicts, installed_mods, ModConflict, ModMetadata};
+pub use notify::{GameEvent, GameEventKind, GameHooks};
+pub use paths::data_directory;
+pub use portable::{export_instance, import_instance};
+pub use refresh::{
+    find_instance_directories, refresh_launcher, LauncherRefresh, LAUNCHER_FILE_NAME,
+};
+pub use servers::{add_server, list_servers, Server};
+pub use shortcut::create_shortcuts;
+pub use status::{serve_status, set_activity, set_exited, set_running, Activity};
+pub use store::{seed_store, store_directory, SeedSummary};
+pub use template::{load_template, Template, TemplateFile};
+pub use usage::{disk_usage, CategoryUsage, DiskUsage, USAGE_CATEGORIES};
+pub use verify::{
+    count_installed, verify_instance, verify_scope, FileCount, InstallCounts, VerifyReport,
+    VerifyScope,
+};
+
+use download::{
+    diff_asset_indexes, download_assets, download_jar, download_libraries, fetch_asset_index,
+    legacy_assets_directory, materialize_asset_view, materialize_legacy_assets, missing_assets,
+    prune_libraries, refresh_natives, AssetIndexDownload, DownloadRun, JarDownload,
+};
+use progress::CombinedProgress;
+use resume::{CreateState, Phase};
+
+/// Where natives jars are unpacked inside an instance.
+pub const NATIVES_DIRECTORY: &str = "natives";
+
+/// An installed instance directory, ready to be launched.
+#[derive(Debug)]
+pub struct Instance {
+    pub directory: PathBuf,
+    pub version: String,
+    pub config: LauncherConfig,
+    /// The install's downloads, for later ones into the instance to share
+    /// the retries and the error report with.
+    pub downloads: DownloadRun,
+}
+
+fn meta_str<'a>(meta: &'a serde_json::Value, pointer: &str) -> Result<&'a str> {
+    meta.pointer(pointer)
+        .and_then(|value| value.as_str())
+        .ok_or_else(|| Error::InvalidMeta(format!("missing {}", pointer)))
+}
+
+/// The `client` or `server` entry of the meta's downloads, saved as
+/// `<side>.jar`.
+fn jar_download<'a>(meta: &'a serde_json::Value, side: &str) -> Result<JarDownload<'a>> {
+    Ok(JarDownload {
+        file_name: match side {
+            "server" => "server.jar",
+            _ => "client.jar",
+        },
+        url: meta_str(meta, &format!("/downloads/{}/url", side))?,
+        sha1: meta_str(meta, &format!("/downloads/{}/sha1", side))?,
+        size: meta["downloads"][side]["size"]
+            .as_u64()
+            .ok_or_else(|| Error::InvalidMeta(format!("missing /downloads/{}/size", side)))?,
+    })
+}
+
+/// Knobs for a single install that don't belong in the instance config.
+#[derive(Clone, Debug, Default)]
+pub struct InstallOptions {
+    /// Only download the assets this selects. Everything by default.
+    pub assets_filter: Option<AssetFilter>,
+    /// Don't check that every asset made it to disk after downloading.
+    pub skip_asset_check: bool,
+    /// Skip the asset objects entirely. Only the index is fetched.
+    pub no_assets: bool,
+    /// Hash assets already on disk instead of trusting any with the right
+    /// size.
+    pub verify_hashes: bool,
+    /// The asset index of the version being updated from, to report how
+    /// many assets are actually new.
+    pub previous_asset_index: Option<serde_json::Value>,
+    /// Skip the install when the instance's lock says it already has this
+    /// version.
+    pub trust_cache: bool,
+    /// Record finished phases in `.create-state` and skip them when the
+    /// install is run again after failing.
+    pub resumable: bool,
+    /// Ignore whatever `.create-state` an earlier run left.
+    pub restart: bool,
+    /// Pin the instance to this asset index, saved as the config's
+    /// `asset_index_url` so later repairs and updates keep it.
+    pub asset_index_url: Option<String>,
+    /// Saved as the config's `asset_index_id` along with `asset_index_url`.
+    pub asset_index_id: Option<String>,
+    /// Resolve versions in this manifest, saved as the config's
+    /// `manifest_url`.
+    pub manifest_url: Option<String>,
+    /// Swap in this LWJGL version, saved as the config's `lwjgl_version`.
+    pub lwjgl_version: Option<String>,
+    /// Keep a minimal meta, saved as the config's `strip_meta`.
+    pub strip_meta: bool,
+    /// Asset objects to download at once instead of the config's
+    /// `assets_concurrency`.
+    pub concurrency: Option<Concurrency>,
+    /// What downloads the asset objects.
+    pub downloader: Downloader,
+    /// Seconds each download phase may take instead of the config's
+    /// `phase_timeout_secs`.
+    pub phase_timeout_secs: Option<u64>,
+    /// Show the downloads as one total, like the config's
+    /// `combined_progress`.
+    pub combined_progress: bool,
+    /// With a combined total, keep each phase's own line as well.
+    pub verbose: bool,
+    /// Connect over this IP family first for this install instead of the
+    /// config's `ip_preference`.
+    pub ip_preference: Option<IpPreference>,
+}
+
+/// Runs the download phase `phase`, failing with [`Error::PhaseTimeout`] if
+/// it's still going after `seconds` (0 for no limit).
+async fn with_phase_timeout<T>(
+    phase: Phase,
+    seconds: u64,
+    download: impl std::future::Future<Output = Result<T>>,
+) -> Result<T> {
+    if seconds == 0 {
+        return download.await;
+    }
+
+    tokio::time::timeout(Duration::from_secs(seconds), download)
+        .await
+        .unwrap_or_else(|_| {
+            Err(Error::PhaseTimeout {
+                phase: phase.to_string(),
+                seconds,
+            })
+        })
+}
+
+/// The asset index to install: the config's pinned one if it has one, the
+/// meta's otherwise. `None` when neither has one.
+fn asset_index_download<'a>(
+    meta: &'a serde_json::Value,
+    config: &'a LauncherConfig,
+) -> Result<Option<AssetIndexDownload<'a>>> {
+    let Some(id) = config
+        .asset_index_id
+        .as_deref()
+        .or(meta::asset_index_id(meta))
+    else {
+        return Ok(None);
+    };
+
+    Ok(Some(AssetIndexDownload {
+        id,
+        url: match &config.asset_index_url {
+            Some(url) => url,
+            None => meta_str(meta, "/assetIndex/url")?,
+        },
+    }))
+}
+
+/// Downloads the asset objects in `asset_index`, checks they all arrived and
+/// lays them out for old versions that need it.
+async fn install_assets(
+    http_client: reqwest::Client,
+    target_dir: &Path,
+    asset_index_id: &str,
+    asset_index: &serde_json::Value,
+    options: &InstallOptions,
+    run: &DownloadRun,
+    config: &LauncherConfig,
+) -> Result<()> {
+    let assets_directory = target_dir.join("assets");
+
+    if let Some(previous) = &options.previous_asset_index {
+        let (new, unchanged) = diff_asset_indexes(previous, asset_index);
+        eprintln!("downloading {} new assets, {} unchanged", new, unchanged);
+    }
+
+    download_assets(
+        http_client,
+        &assets_directory,
+        asset_index,
+        options,
+        run,
+        config,
+    )
+    .await?;
+
+    if !options.skip_asset_check {
+        let missing = missing_assets(
+            &assets_directory,
+            asset_index,
+            options.assets_filter.as_ref(),
+            config.asset_layout,
+        );
+
+        for key in &missing {
+            eprintln!("missing asset {}", key);
+        }
+
+        if !missing.is_empty() {
+            return Err(Error::IncompleteAssets(missing.len()));
+        }
+    }
+
+    if let Some(legacy_directory) =
+        legacy_assets_directory(target_dir, &assets_directory, asset_index_id, asset_index)
+    {
+        materialize_legacy_assets(
+            &legacy_directory,
+            &assets_directory,
+            asset_index,
+            config.asset_layout,
+            config.copy_legacy_assets,
+        )?;
+    }
+
+    if config.asset_layout == AssetLayout::Flat {
+        materialize_asset_view(
+            &assets_directory,
+            asset_index_id,
+            asset_index,
+            config.copy_legacy_assets,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Installs the version described by the meta at `meta_url` into
+/// `target_dir`. `config` seeds the instance config if it has none yet.
+pub async fn install_version(
+    meta_url: &str,
+    target_dir: &Path,
+    config: &LauncherConfig,
+) -> Result<Instance> {
+    install_version_with(meta_url, target_dir, config, &InstallOptions::default()).await
+}
+
+/// Fails with [`Error::NotWritable`] unless files can be written in the
+/// instance directory, creating it if needed, before anything is downloaded.
+pub fn check_instance_directory(target_dir: &Path) -> Result<()> {
+    fsutil::check_writable(target_dir).map_err(|source| Error::NotWritable {
+        path: target_dir.to_owned(),
+        source,
+    })
+}
+
+/// [`install_version`] with non-default [`InstallOptions`].
+pub async fn install_version_with(
+    meta_url: &str,
+    target_dir: &Path,
+    config: &LauncherConfig,
+    options: &InstallOptions,
+) -> Result<Instance> {
+    check_instance_directory(target_dir)?;
+
+    if options.trust_cache {
+        match InstanceLock::load(target_dir) {
+            Ok(Some(lock)) if lock.matches(target_dir, meta_url) => {
+                eprintln!("{} is already installed, trusting the cache", lock.version);
+
+                let config = load_config(target_dir)?;
+                return Ok(Instance {
+                    directory: target_dir.to_owned(),
+                    version: lock.loader.unwrap_or(lock.version),
+                    downloads: DownloadRun::new(config.retry_budget),
+                    config,
+                });
+            }
+            _ => eprintln!("no matching {}, installing normally", lock::LOCK_FILE_NAME),
+        }
+    }
+
+    // Before the meta, which may inherit from a version in the config's
+    // manifest.
+    let mut config = config::create_config(target_dir, config)?;
+    if options.manifest_url.is_some() {
+        config.manifest_url = options.manifest_url.clone();
+        save_config(target_dir, &config)?;
+    }
+    if options.asset_index_url.is_some() {
+        config.asset_index_url = options.asset_index_url.clone();
+        config.asset_index_id = options.asset_index_id.clone();
+        save_config(target_dir, &config)?;
+    }
+    if options.lwjgl_version.is_some() {
+        config.lwjgl_version = options.lwjgl_version.clone();
+        save_config(target_dir, &config)?;
+    }
+    if options.strip_meta {
+        config.strip_meta = true;
+        save_config(target_dir, &config)?;
+    }
+    // Only for this install, so after the config is saved.
+    if let Some(ip_preference) = options.ip_preference {
+        config.ip_preference = ip_preference;
+    }
+
+    let http_client = http_client()?;
+    let meta_path = target_dir.join(meta::META_FILE_NAME);
+    let mut minecraft_meta =
+        meta::get_minecraft_meta(&http_client, meta_url, config.manifest_url(), &meta_path).await?;
+
+    let assets_directory = target_dir.join("assets");
+    let libraries_directory = target_dir.join("libraries");
+
+    // Spliced into the cached meta so launch and verify see the same
+    // libraries. Splicing again replaces an earlier override.
+    if let Some(lwjgl_version) = &config.lwjgl_version {
+        eprintln!(
+            "warning: using LWJGL {} instead of the version's own, this is unsupported and \
+             the game may crash; unset lwjgl_version and update the instance to undo it",
+            lwjgl_version
+        );
+        minecraft_meta = meta::override_lwjgl(minecraft_meta, lwjgl_version)?;
+        fs::write(&meta_path, serde_json::to_string_pretty(&minecraft_meta)?)
+            .with_path(&meta_path)?;
+    }
+
+    if config.strip_meta {
+        minecraft_meta = meta::strip_meta(minecraft_meta);
+        fs::write(&meta_path, serde_json::to_string_pretty(&minecraft_meta)?)
+            .with_path(&meta_path)?;
+    }
+
+    let version = meta_str(&minecraft_meta, "/id")?;
+    let client_download = jar_download(&minecraft_meta, "client")?;
+    let libraries = meta::parse_libraries(&minecraft_meta)?;
+    meta::check_natives_arch(&libraries)?;
+    let asset_index_download = asset_index_download(&minecraft_meta, &config)?;
+    let asset_index_id = asset_index_download.as_ref().map(|download| download.id);
+    // The meta came from Mojang's API, everything else from the hosts the
+    // config may pin.
+    let http_client = http_client_for(&config)?;
+    let run = DownloadRun::new(config.retry_budget);
+    let phase_timeout = options
+        .phase_timeout_secs
+        .unwrap_or(config.phase_timeout_secs);
+
+    if options.restart {
+        CreateState::clear(target_dir)?;
+    }
+
+    let mut state = if options.resumable {
+        CreateState::load(target_dir, meta_url)
+    } else {
+        CreateState::untracked()
+    };
+
+    if !state.completed().is_empty() {
+        let done = state
+            .completed()
+            .iter()
+            .map(Phase::to_string)
+            .collect::<Vec<_>>();
+        eprintln!(
+            "resuming an earlier create, already done: {}",
+            done.join(", ")
+        );
+    }
+
+    let combined_progress = (options.combined_progress || config.combined_progress).then(|| {
+        // Phases that won't download anything are left out, since they never
+        // start a progress line to replace their estimate.
+        let mut estimates = BTreeMap::new();
+        let client_path = target_dir.join(client_download.file_name);
+        if !state.is_done(Phase::Client)
+            && fs::metadata(&client_path).map_or(true, |m| m.len() != client_download.size)
+        {
+            estimates.insert(client_download.file_name.to_owned(), client_download.size);
+        }
+        if !state.is_done(Phase::Libraries) {
+            let size = libraries
+                .iter()
+                .filter(|library| library.applies())
+                .flat_map(Library::artifacts)
+                .map(|artifact| artifact.size)
+                .sum();
+            estimates.insert(String::from("libraries"), size);
+        }
+        if asset_index_id.is_some() && !options.no_assets && !state.is_done(Phase::Assets) {
+            let size = minecraft_meta["assetIndex"]["totalSize"]
+                .as_u64()
+                .unwrap_or(0);
+            estimates.insert(String::from("assets"), size);
+        }
+
+        CombinedProgress::start(estimates, options.verbose)
+    });
+
+    // The asset index is only needed once the client and libraries are done,
+    // so fetch it alongside them instead of as another round trip afterwards.
+    let (asset_index, downloads) = futures::join!(
+        async {
+            match asset_index_download {
+                Some(download) => fetch_asset_index(&http_client, &assets_directory, download)
+                    .await
+                    .map(Some),
+                None => Ok(None),
+            }
+        },
+        async {
+            if !state.is_done(Phase::Client) {
+                download_jar(http_client.clone(), target_dir, client_download, &run).await?;
+                state.complete(target_dir, Phase::Client)?;
+            }
+
+            if !state.is_done(Phase::Libraries) {
+                with_phase_timeout(
+                    Phase::Libraries,
+                    phase_timeout,
+                    download_libraries(
+                        http_client.clone(),
+                        &libraries_directory,
+                        &libraries,
+                        &run,
+                        &config,
+                    ),
+                )
+                .await?;
+                state.complete(target_dir, Phase::Libraries)?;
+            }
+
+            Ok::<(), Error>(())
+        }
+    );
+    run.report_failure(target_dir, downloads)?;
+
+    if !state.is_done(Phase::Natives) {
+        refresh_natives(
+            &libraries_directory,
+            &target_dir.join(NATIVES_DIRECTORY),
+            &libraries,
+            true,
+        )?;
+        state.complete(target_dir, Phase::Natives)?;
+    }
+    let asset_index = asset_index?;
+
+    match asset_index_id.zip(asset_index.as_ref()) {
+        None => eprintln!(
+            "{} has no asset index, it loads the resources bundled in its client jar; \
+             skipping asset downloads",
+            version
+        ),
+        Some(_) if options.no_assets => eprintln!(
+            "skipped asset downloads, the instance will have no textures, sounds or languages \
+             until it is created again without --no-assets"
+        ),
+        Some(_) if state.is_done(Phase::Assets) => {}
+        Some((asset_index_id, asset_index)) => {
+            let installed = with_phase_timeout(
+                Phase::Assets,
+                phase_timeout,
+                install_assets(
+                    http_client.clone(),
+                    target_dir,
+                    asset_index_id,
+                    asset_index,
+                    options,
+                    &run,
+                    &config,
+                ),
+            )
+            .await;
+            run.report_failure(target_dir, installed)?;
+            state.complete(target_dir, Phase::Assets)?;
+        }
+    }
+
+    if let Some(combined_progress) = combined_progress {
+        combined_progress.finish().await;
+    }
+
+    options::seed_options(target_dir, &config)?;
+
+    InstanceLock::from_meta(target_dir, meta_url, &minecraft_meta, asset_index_id)?
+        .save(target_dir)?;
+    CreateState::clear(target_dir)?;
+    run.save_report(target_dir)?;
+
+    // A last check that nothing went missing without an error to show for
+    // it. Assets left out on purpose aren't held against the install.
+    let counts = verify::count_installed(
+        target_dir,
+        options.assets_filter.as_ref(),
+        !options.no_assets,
+    )?;
+    eprintln!("{}", counts);
+    let assets_complete = options.skip_asset_check || counts.assets.is_none_or(|c| c.is_complete());
+    if !counts.libraries.is_complete() || !counts.client.is_complete() || !assets_complete {
+        return Err(Error::IncompleteInstall(counts.to_string()));
+    }
+
+    Ok(Instance {
+        directory: target_dir.to_owned(),
+        version: version.to_owned(),
+        config,
+        downloads: run,
+    })
+}
+
+/// Checks the parts of the instance `scope` selects against their hashes and
+/// downloads whatever is missing or corrupt again, leaving everything else
+/// alone. Returns what was wrong beforehand.
+pub async fn repair_instance(
+    instance_directory: &Path,
+    scope: VerifyScope,
+) -> Result<VerifyReport> {
+    let config = load_config(instance_directory)?;
+    let minecraft_meta = meta::load_instance_meta(instance_directory)?;
+    let http_client = http_client_for(&config)?;
+
+    let assets_directory = instance_directory.join("assets");
+    let libraries_directory = instance_directory.join("libraries");
+
+    // The index is what the assets are checked against, so it can't be
+    // trusted either.
+    let asset_index_id = lock::installed_asset_index(instance_directory, &minecraft_meta)?;
+    let asset_index = match (
+        &asset_index_id,
+        asset_index_download(&minecraft_meta, &config)?,
+    ) {
+        (Some(id), Some(download)) if scope != VerifyScope::Libraries => {
+            let index = AssetIndexDownload { id, ..download };
+            Some(fetch_asset_index(&http_client, &assets_directory, index).await?)
+        }
+        _ => None,
+    };
+
+    let report = verify_scope(instance_directory, false, scope)?;
+    let run = DownloadRun::new(config.retry_budget);
+
+    for path in &report.corrupt {
+        eprintln!("removing corrupt {}", path.display());
+        fs::remove_file(path).with_path(path)?;
+    }
+
+    if scope == VerifyScope::All {
+        download_jar(
+            http_client.clone(),
+            instance_directory,
+            jar_download(&minecraft_meta, "client")?,
+            &run,
+        )
+        .await?;
+    }
+
+    if scope != VerifyScope::Assets {
+        let libraries = meta::parse_libraries(&minecraft_meta)?;
+        with_phase_timeout(
+            Phase::Libraries,
+            config.phase_timeout_secs,
+            download_libraries(
+                http_client.clone(),
+                &libraries_directory,
+                &libraries,
+                &run,
+                &config,
+            ),
+        )
+        .await?;
+        refresh_natives(
+            &libraries_directory,
+            &instance_directory.join(NATIVES_DIRECTORY),
+            &libraries,
+            true,
+        )?;
+    }
+
+    if let Some((asset_index_id, asset_index)) = asset_index_id.zip(asset_index) {
+        with_phase_timeout(
+            Phase::Assets,
+            config.phase_timeout_secs,
+            install_assets(
+                http_client,
+                instance_directory,
+                &asset_index_id,
+                &asset_index,
+                &InstallOptions::default(),
+                &run,
+                &config,
+            ),
+        )
+        .await?;
+    }
+
+    Ok(report)
+}
+
+/// What a fresh server.properties starts with. The server fills in the rest
+/// of the defaults on its first run.
+const SERVER_PROPERTIES: &str = "\
+motd=A Minecraft Server
+server-port=25565
+online-mode=true
+max-players=20
+difficulty=easy
+gamemode=survival
+";
+
+/// Downloads the dedicated server for the meta at `meta_url` into
+/// `target_dir`, with a starter server.properties and eula.txt. The EULA is
+/// only marked as accepted with `accept_eula`; an eula.txt that's already
+/// there is otherwise left alone. Returns the version id.
+pub async fn install_server(
+    meta_url: &str,
+    target_dir: &Path,
+    accept_eula: bool,
+) -> Result<String> {
+    fs::create_dir_all(target_dir).with_path(target_dir)?;
+
+    let http_client = http_client()?;
+    let minecraft_meta = meta::fetch_meta(&http_client, meta_url, VERSION_MANIFEST_URL).await?;
+    let version = meta_str(&minecraft_meta, "/id")?.to_owned();
+
+    download_jar(
+        http_client,
+        target_dir,
+        jar_download(&minecraft_meta, "server")?,
+        &DownloadRun::new(LauncherConfig::default().retry_budget),
+    )
+    .await?;
+
+    let properties_path = target_dir.join("server.properties");
+    if !properties_path.exists() {
+        fs::write(&properties_path, SERVER_PROPERTIES).with_path(&properties_path)?;
+    }
+
+    let eula_path = target_dir.join("eula.txt");
+    if accept_eula || !eula_path.exists() {
+        fs::write(
+            &eula_path,
+            format!("# See https://aka.ms/MinecraftEULA\neula={}\n", accept_eula),
+        )
+        .with_path(&eula_path)?;
+    }
+
+    Ok(version)
+}
+
+/// Moves the instance at `instance_directory` to the version described by the
+/// meta at `meta_url`. Everything the player owns (saves, mods, config,
+/// resource packs, options) is left alone; the cached meta, client jar and
+/// libraries are replaced, and assets already on disk are reused.
+pub async fn update_instance(
+    instance_directory: &Path,
+    meta_url: &str,
+    options: &InstallOptions,
+) -> Result<Instance> {
+    let config = load_config(instance_directory)?;
+    let previous = InstanceLock::load(instance_directory)?;
+    // Read before the install replaces the meta and the lock.
+    let previous_index_path = lock::installed_asset_index(
+        instance_directory,
+        &meta::load_instance_meta(instance_directory)?,
+    )?
+    .map(|id| instance_directory.join(format!("assets/indexes/{}.json", id)));
+
+    let http_client = http_client()?;
+    let manifest_url = options
+        .manifest_url
+        .as_deref()
+        .unwrap_or(config.manifest_url());
+    let minecraft_meta = meta::fetch_meta(&http_client, meta_url, manifest_url).await?;
+    let (version, _) = lock::version_and_loader(&minecraft_meta)?;
+
+    for installed in installed_mods(&config.game_directory(instance_directory)) {
+        if !installed.supports(&version) {
+            eprintln!(
+                "warning: {} ({} {}) does not declare support for Minecraft {}",
+                installed.file.display(),
+                installed.id,
+                installed.version,
+                version
+            );
+        }
+    }
+
+    // The meta and the client jar are per version, so the old ones can't be
+    // "resumed". They and the lock pinning them are set aside rather than
+    // removed until the new version is installed, and put back if it isn't.
+    let mut set_aside = Vec::new();
+    for name in [meta::META_FILE_NAME, "client.jar", lock::LOCK_FILE_NAME] {
+        let path = instance_directory.join(name);
+        let aside = instance_directory.join(format!("{}.previous", name));
+        if path.exists() {
+            fs::rename(&path, &aside).with_path(&path)?;
+            set_aside.push((path, aside));
+        }
+    }
+
+    // install_version picks up the new meta from the cache.
+    let meta_path = instance_directory.join(meta::META_FILE_NAME);
+    let written = serde_json::to_string_pretty(&minecraft_meta)
+        .map_err(Error::from)
+        .and_then(|meta| fsutil::write_atomic(&meta_path, meta).with_path(&meta_path));
+
+    let previous_asset_index = previous_index_path
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|index| serde_json::from_str(&index).ok());
+    let options = InstallOptions {
+        previous_asset_index,
+        ..options.clone()
+    };
+
+    let installed = match written {
+        Ok(()) => install_version_with(meta_url, instance_directory, &config, &options).await,
+        Err(e) => Err(e),
+    };
+    let instance = match installed {
+        Ok(instance) => {
+            for (_, aside) in &set_aside {
+                fs::remove_file(aside).with_path(aside)?;
+            }
+            instance
+        }
+        Err(e) => {
+            let _ = fs::remove_file(instance_directory.join("client.jar.part"));
+            for (path, aside) in &set_aside {
+                // Windows doesn't rename over an existing file.
+                let _ = fs::remove_file(path);
+                if let Err(restore) = fs::rename(aside, path) {
+                    eprintln!(
+                        "warning: could not put {} back: {}",
+                        path.display(),
+                        restore
+                    );
+                }
+            }
+            return Err(e);
+        }
+    };
+
+    // The installed meta, which may have had LWJGL swapped in.
+    let libraries = meta::parse_libraries(&meta::load_instance_meta(instance_directory)?)?;
+    let removed = prune_libraries(&instance_directory.join("libraries"), &libraries)?;
+
+    match previous {
+        Some(previous) => eprintln!(
+            "updated from {} to {}, removed {} old libraries",
+            previous.version, version, removed
+        ),
+        None => eprintln!("updated to {}, removed {} old libraries", version, removed),
+    }
+
+    Ok(instance)
+}
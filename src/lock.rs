@@ -0,0 +1,149 @@
+use std::fs;
+use std::path::Path;
+
+use crate::error::{ParseContext, PathContext};
+use crate::meta::{asset_index_id, META_FILE_NAME};
+use crate::sha1::sha1_file;
+use crate::{Error, Result};
+
+pub const LOCK_FILE_NAME: &str = "instance.lock.json";
+
+/// What an instance was installed as, so later runs can tell when the files
+/// they are about to use no longer match it.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct InstanceLock {
+    /// The Minecraft version, i.e. the base version when a loader is used.
+    pub version: String,
+    /// The loader's version id when the meta inherits from a vanilla one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loader: Option<String>,
+    pub meta_url: String,
+    pub meta_sha1: String,
+    /// `None` for versions without an asset index.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset_index: Option<String>,
+    pub libraries: Vec<String>,
+}
+
+/// The Minecraft version and loader a meta describes.
+pub fn version_and_loader(meta: &serde_json::Value) -> Result<(String, Option<String>)> {
+    let id = meta["id"]
+        .as_str()
+        .ok_or_else(|| Error::InvalidMeta(String::from("missing /id")))?;
+
+    Ok(match meta["inheritsFrom"].as_str() {
+        Some(parent) => (parent.to_owned(), Some(id.to_owned())),
+        None => (id.to_owned(), None),
+    })
+}
+
+impl InstanceLock {
+    pub fn from_meta(
+        instance_directory: &Path,
+        meta_url: &str,
+        meta: &serde_json::Value,
+        asset_index: Option<&str>,
+    ) -> Result<Self> {
+        let (version, loader) = version_and_loader(meta)?;
+
+        let libraries = meta["libraries"]
+            .as_array()
+            .map(|libraries| {
+                libraries
+                    .iter()
+                    .filter_map(|library| library["name"].as_str())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            version,
+            loader,
+            meta_url: meta_url.to_owned(),
+            meta_sha1: sha1_file(&instance_directory.join(META_FILE_NAME))?,
+            asset_index: asset_index.map(String::from),
+            libraries,
+        })
+    }
+
+    /// Returns `None` for instances created before lock files existed.
+    pub fn load(instance_directory: &Path) -> Result<Option<Self>> {
+        let path = instance_directory.join(LOCK_FILE_NAME);
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let lock = fs::read_to_string(&path).with_path(&path)?;
+
+        Ok(Some(serde_json::from_str(&lock).parsing(path.display())?))
+    }
+
+    pub fn save(&self, instance_directory: &Path) -> Result<()> {
+        let path = instance_directory.join(LOCK_FILE_NAME);
+        fs::write(&path, serde_json::to_string_pretty(self)?).with_path(&path)?;
+
+        Ok(())
+    }
+
+    /// Whether the instance is still what was installed from `meta_url`,
+    /// judged only by the lock and the cached meta rather than every file.
+    pub fn matches(&self, instance_directory: &Path, meta_url: &str) -> bool {
+        self.meta_url == meta_url && self.meta_unchanged(instance_directory)
+    }
+
+    /// Whether the cached meta is still the one the lock was written for.
+    /// Launching has no url of its own to compare, so this is all it checks.
+    pub fn meta_unchanged(&self, instance_directory: &Path) -> bool {
+        sha1_file(&instance_directory.join(META_FILE_NAME))
+            .is_ok_and(|sha1| sha1.eq_ignore_ascii_case(&self.meta_sha1))
+    }
+
+    /// Fails when `meta` describes a different version or loader than the one
+    /// the instance was installed with.
+    pub fn check_meta(&self, meta: &serde_json::Value) -> Result<()> {
+        let (version, loader) = version_and_loader(meta)?;
+
+        if version != self.version || loader != self.loader {
+            return Err(Error::VersionMismatch {
+                locked: describe(&self.version, self.loader.as_deref()),
+                found: describe(&version, loader.as_deref()),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// What the asset index of the instance at `instance_directory` is saved as:
+/// the id its lock recorded, or for instances without one the meta's id.
+/// Instances from before the real id was used saved it as `12`. `None` for
+/// versions without an asset index.
+pub fn installed_asset_index(
+    instance_directory: &Path,
+    meta: &serde_json::Value,
+) -> Result<Option<String>> {
+    if let Some(lock) = InstanceLock::load(instance_directory)? {
+        return Ok(lock.asset_index);
+    }
+
+    let Some(id) = asset_index_id(meta) else {
+        return Ok(None);
+    };
+    let indexes_path = instance_directory.join("assets/indexes");
+
+    if !indexes_path.join(format!("{}.json", id)).exists() && indexes_path.join("12.json").exists()
+    {
+        return Ok(Some(String::from("12")));
+    }
+
+    Ok(Some(id.to_owned()))
+}
+
+fn describe(version: &str, loader: Option<&str>) -> String {
+    match loader {
+        Some(loader) => format!("{} ({})", version, loader),
+        None => version.to_owned(),
+    }
+}
@@ -1,294 +1,581 @@
-use reqwest;
+mod cli;
+mod confirm;
+mod menu;
+
 use std::env;
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::io::{self, IsTerminal};
 use std::process;
-use std::process::Command;
-use std::sync::Arc;
-use tokio::sync::Semaphore;
-
-const MINECRAFT_1_20_4_META_URL: &str = "https://piston-meta.mojang.com/v1/packages/efcc510e525cef0e859b5435f82b6e3193214efc/1.20.4.json";
+use std::thread;
+use std::time::{Duration, Instant};
+
+use blazinglyassmc::{
+    add_server, build_classpath, build_launch_command, check_instance_directory, check_ownership,
+    compare_instances, create_shortcuts, disk_usage, effective_config, export_instance,
+    find_crash_reports, find_instance_directories, format_command, http_client, import_instance,
+    install_server, install_version_with, launch_config, launch_minecraft, list_servers,
+    load_config, load_template, load_version_manifest, refresh_launcher, refresh_session,
+    repair_instance, resolve_meta_url, run_doctor, seed_store, select_session, serve_status,
+    set_activity, set_exited, set_running, store_directory, update_instance, verify_instance,
+    wait_for_startup, Account, Accounts, Activity, CheckStatus, Error, GameEvent, GameHooks,
+    InstallOptions, LauncherRefresh, PathContext, Result, Server, Template, CONFIG_FILE_NAME,
+    LAUNCHER_FILE_NAME, USAGE_CATEGORIES, VERSION_MANIFEST_URL,
+};
+use cli::{
+    AccountsCommand, Cli, Command, ConfigArgs, CrashesArgs, CreateArgs, DiffArgs, DoctorArgs,
+    DuArgs, ExportArgs, ImportArgs, LaunchArgs, MetaSource, RefreshLaunchersArgs, RepairArgs,
+    SeedStoreArgs, ServerArgs, ServerCommand, ShortcutArgs, UpdateArgs, VerifyArgs, VersionsArgs,
+};
+use confirm::confirm;
+
+/// How long `--serve-status` keeps serving once there's nothing left to do.
+const STATUS_LINGER: Duration = Duration::from_secs(10);
+
+async fn create_profile(args: CreateArgs) -> Result<()> {
+    // Before looking the version up, so a wrong --dir fails straight away.
+    check_instance_directory(&args.directory)?;
+
+    if let Some(address) = args.serve_status {
+        serve_status(address)?;
+    }
+    set_activity(Activity::Downloading);
 
-struct AssetIndexDownload<'a> {
-    id: &'a str,
-    url: &'a str,
-}
+    let template = match &args.template {
+        Some(path) => load_template(path)?,
+        None => Template::default(),
+    };
 
-#[derive(serde::Serialize, serde::Deserialize)]
-struct LauncherConfig {
-    username: String,
-}
+    let meta_url = match args.meta {
+        MetaSource::Url(url) => url,
+        MetaSource::Version(version) => {
+            // The one the instance will be saved with.
+            let manifest_url = args
+                .options
+                .manifest_url
+                .clone()
+                .or_else(|| load_config(&args.directory).ok()?.manifest_url)
+                .or_else(|| template.config.manifest_url.clone());
+            let manifest_url = manifest_url.as_deref().unwrap_or(VERSION_MANIFEST_URL);
+
+            resolve_meta_url(
+                &http_client()?,
+                manifest_url,
+                &version,
+                args.refresh_manifest,
+            )
+            .await?
+        }
+    };
 
-impl Default for LauncherConfig {
-    fn default() -> Self {
-        Self {
-            username: String::from("Username"),
+    let instance =
+        install_version_with(&meta_url, &args.directory, &template.config, &args.options).await?;
+    let applied = template
+        .apply(&http_client()?, &instance.directory, &instance.downloads)
+        .await;
+    instance
+        .downloads
+        .report_failure(&instance.directory, applied)?;
+
+    if args.download_only {
+        let report = verify_instance(&instance.directory, false)?;
+
+        if !report.is_ok() {
+            return Err(Error::CorruptInstance(
+                report.missing.len() + report.corrupt.len(),
+            ));
         }
+
+        eprintln!(
+            "instance ready at {} ({} files verified)",
+            instance.directory.display(),
+            report.checked
+        );
+    } else {
+        let current_exe = env::current_exe()?;
+        let launcher = instance.directory.join(LAUNCHER_FILE_NAME);
+        fs::copy(current_exe, &launcher).with_path(&launcher)?;
     }
-}
 
-fn _list_files(paths: &mut Vec<PathBuf>, path: &Path) {
-    let dir_paths = fs::read_dir(path).unwrap();
-    for entry in dir_paths {
-        let entry_path = entry.unwrap().path();
-        if entry_path.is_dir() {
-            _list_files(paths, &entry_path);
-        } else {
-            paths.push(entry_path)
-        }
+    set_activity(Activity::Ready);
+    if args.serve_status.is_some() {
+        tokio::time::sleep(STATUS_LINGER).await;
     }
+
+    Ok(())
 }
 
-fn list_files(path: &Path) -> Vec<PathBuf> {
-    let mut paths = Vec::new();
-    _list_files(&mut paths, path);
-    paths
+async fn update(args: UpdateArgs, yes: bool) -> Result<()> {
+    confirm(
+        &format!(
+            "replace the client and libraries of {} with {}",
+            args.directory.display(),
+            args.version
+        ),
+        yes,
+    )?;
+
+    let manifest_url = match args.manifest_url.clone() {
+        Some(manifest_url) => manifest_url,
+        None => load_config(&args.directory)?.manifest_url().to_owned(),
+    };
+    let meta_url = resolve_meta_url(
+        &http_client()?,
+        &manifest_url,
+        &args.version,
+        args.refresh_manifest,
+    )
+    .await?;
+    let options = InstallOptions {
+        manifest_url: args.manifest_url,
+        ..InstallOptions::default()
+    };
+    update_instance(&args.directory, &meta_url, &options).await?;
+
+    Ok(())
 }
 
-async fn download_assets(
-    http_client: reqwest::Client,
-    assets_directory: &Path,
-    asset_index_download: AssetIndexDownload<'_>,
-) {
-    let indexes_path = assets_directory.join("indexes");
-    fs::create_dir_all(&indexes_path).unwrap();
-
-    let asset_index_json: serde_json::Value = http_client
-        .get(asset_index_download.url)
-        .send()
-        .await
-        .unwrap()
-        .json()
-        .await
-        .unwrap();
+async fn versions(args: VersionsArgs) -> Result<()> {
+    let manifest_url = args.manifest_url.as_deref().unwrap_or(VERSION_MANIFEST_URL);
+    let manifest =
+        load_version_manifest(&http_client()?, manifest_url, args.refresh_manifest).await?;
+    let versions = manifest.list(args.since.as_deref(), args.kind.as_deref());
+
+    for version in &versions {
+        // Just the date of the release time.
+        let date = version
+            .release_time
+            .get(..10)
+            .unwrap_or(&version.release_time);
+        println!("{:<24} {:<10} {}", version.id, version.kind, date);
+    }
+
+    if versions.is_empty() {
+        eprintln!("no versions match");
+    }
 
-    let index_path = indexes_path.join(format!("{}.json", asset_index_download.id));
-    fs::write(index_path, asset_index_json.clone().to_string()).unwrap();
+    Ok(())
+}
 
-    let objects_path = assets_directory.join("objects");
-    fs::create_dir_all(&objects_path).unwrap();
+fn verify(args: VerifyArgs) -> Result<()> {
+    let report = verify_instance(&args.directory, args.use_cache)?;
 
-    let asset_objects = asset_index_json["objects"].as_object().unwrap();
+    for path in &report.missing {
+        println!("missing {}", path.display());
+    }
 
-    let semaphore = Arc::new(Semaphore::new(5));
+    for path in &report.corrupt {
+        println!("corrupt {}", path.display());
+    }
 
-    let mut handles = Vec::new();
+    eprintln!(
+        "checked {} files, {} missing, {} corrupt",
+        report.checked,
+        report.missing.len(),
+        report.corrupt.len()
+    );
 
-    for (_k, v) in asset_objects.iter() {
-        let hash = v["hash"].as_str().unwrap().to_owned();
-        let hash_prefix = hash[0..2].to_owned();
+    if !report.is_ok() {
+        process::exit(1);
+    }
 
-        let asset_parent = objects_path.join(&hash_prefix);
-        let asset_path = asset_parent.join(&hash);
-        fs::create_dir_all(asset_parent).unwrap();
+    Ok(())
+}
 
-        if !asset_path.exists() {
-            let semaphore = semaphore.clone();
-            let http_client = http_client.clone();
+async fn repair(args: RepairArgs) -> Result<()> {
+    let report = repair_instance(&args.directory, args.scope).await?;
 
-            handles.push(tokio::spawn(async move {
-                let permit = semaphore.acquire().await.unwrap();
+    eprintln!(
+        "checked {} files, downloaded {} missing and {} corrupt ones again",
+        report.checked,
+        report.missing.len(),
+        report.corrupt.len()
+    );
 
-                let data = http_client
-                    .get(format!(
-                        "https://resources.download.minecraft.net/{}/{}",
-                        hash_prefix, hash
-                    ))
-                    .send()
-                    .await
-                    .unwrap()
-                    .bytes()
-                    .await
-                    .unwrap();
+    Ok(())
+}
 
-                fs::write(asset_path, data).unwrap();
+async fn launch(mut args: LaunchArgs) -> Result<()> {
+    let instance_directory = match args.directory {
+        Some(directory) => directory,
+        None => env::current_exe()?.parent().unwrap().to_owned(),
+    };
 
-                drop(permit);
-                println!("downloaded asset {}", hash);
-            }));
+    if args.print_classpath {
+        for path in build_classpath(&instance_directory)? {
+            println!("{}", path.display());
         }
+
+        return Ok(());
     }
 
-    futures::future::join_all(handles).await;
-}
+    // Printing the command asks nothing of the network and writes nothing,
+    // so the stored session is shown as it is.
+    if args.print_command {
+        args.options.dry_run = true;
+        let session = select_session(
+            &launch_config(&instance_directory, &args.options)?,
+            &args.options,
+        )?;
+        let secrets = session
+            .access_token
+            .as_deref()
+            .into_iter()
+            .collect::<Vec<_>>();
+        let command = build_launch_command(&instance_directory, &args.options)?;
+
+        println!("{}", format_command(&command, &secrets));
+
+        return Ok(());
+    }
+
+    if let Some(address) = args.serve_status {
+        serve_status(address)?;
+    }
+    set_activity(Activity::Launching);
 
-async fn download_libraries(
-    http_client: reqwest::Client,
-    libraries_directory: &Path,
-    library_entries: &[serde_json::Value],
-) {
-    for library_entry in library_entries.iter() {
-        let path = library_entry["downloads"]["artifact"]["path"]
-            .as_str()
-            .unwrap();
+    refresh_session(&http_client()?, &instance_directory, &args.options).await?;
 
-        let rules = library_entry.get("rules").map_or(None, |a| a.get(0));
+    if !args.options.demo {
+        check_ownership(&http_client()?, &instance_directory, &mut args.options).await?;
+    }
 
-        if rules.is_some() && rules.unwrap()["os"]["name"] != "windows" {
-            continue;
+    let hooks = GameHooks::new(
+        &launch_config(&instance_directory, &args.options)?,
+        args.notify_url,
+    )?;
+
+    let launched = Instant::now();
+    let mut child = launch_minecraft(&instance_directory, &args.options)?;
+    let pid = child.id();
+    set_running(pid);
+    hooks
+        .notify(&GameEvent::launched(&instance_directory, pid))
+        .await;
+
+    // Without a timeout, a status to serve or an exit hook the launcher exits
+    // straight after spawning and the game outlives it.
+    let attached = args.serve_status.is_some() || hooks.watch_exit();
+    let echo = match (args.startup_timeout, attached) {
+        (Some(seconds), _) => match wait_for_startup(&mut child, Duration::from_secs(seconds)) {
+            Ok(echo) => echo,
+            Err(e) => {
+                // wait_for_startup has waited for or killed the game by now.
+                let code = child.try_wait()?.and_then(|status| status.code());
+                let event = GameEvent::exited(&instance_directory, pid, code, launched.elapsed());
+                hooks.notify(&event).await;
+                return Err(e);
+            }
+        },
+        (None, true) => {
+            let mut stdout = child.stdout.take().expect("the game's output is captured");
+            thread::spawn(move || {
+                let _ = io::copy(&mut stdout, &mut io::stdout());
+            })
         }
+        (None, false) => return Ok(()),
+    };
 
-        let lib_path = libraries_directory.join(path);
+    let status = child.wait()?;
+    let _ = echo.join();
+    set_exited(status.code());
+    let event = GameEvent::exited(&instance_directory, pid, status.code(), launched.elapsed());
+    hooks.notify(&event).await;
 
-        if lib_path.exists() {
-            continue;
-        }
+    if args.serve_status.is_some() {
+        tokio::time::sleep(STATUS_LINGER).await;
+    }
 
-        let url = library_entry["downloads"]["artifact"]["url"]
-            .as_str()
-            .unwrap();
+    Ok(())
+}
 
-        fs::create_dir_all(lib_path.parent().unwrap()).unwrap();
+async fn server(args: ServerArgs) -> Result<()> {
+    match args.command {
+        ServerCommand::List => {
+            for server in list_servers(&args.directory)? {
+                println!("{} {}", server.name, server.address);
+            }
+        }
+        ServerCommand::Add { name, address } => {
+            add_server(&args.directory, &Server { name, address })?;
+            eprintln!("added server to {}", args.directory.display());
+        }
+        ServerCommand::Download {
+            version,
+            refresh_manifest,
+            accept_eula,
+        } => {
+            let meta_url = resolve_meta_url(
+                &http_client()?,
+                VERSION_MANIFEST_URL,
+                &version,
+                refresh_manifest,
+            )
+            .await?;
+            let version = install_server(&meta_url, &args.directory, accept_eula).await?;
+            eprintln!(
+                "downloaded the {} server to {}, start it there with java -jar server.jar nogui",
+                version,
+                args.directory.display()
+            );
+
+            if !accept_eula {
+                eprintln!(
+                    "it won't start until you accept the EULA (https://aka.ms/MinecraftEULA) \
+                     with --accept-eula or eula=true in eula.txt"
+                );
+            }
+        }
+    }
 
-        println!("downloading {}", path);
+    Ok(())
+}
 
-        let data = http_client
-            .get(url)
-            .send()
-            .await
-            .unwrap()
-            .bytes()
-            .await
-            .unwrap();
+fn export(args: ExportArgs) -> Result<()> {
+    let count = export_instance(&args.directory, &args.archive)?;
+    eprintln!("exported {} files to {}", count, args.archive.display());
 
-        fs::write(lib_path, data).unwrap();
-    }
+    Ok(())
 }
 
-async fn download_client(
-    http_client: reqwest::Client,
-    instance_directory: &Path,
-    client_jar_url: &str,
-) {
-    let client_jar = instance_directory.join("client.jar");
+async fn import(args: ImportArgs, yes: bool) -> Result<()> {
+    if args.directory.join(CONFIG_FILE_NAME).exists() {
+        confirm(
+            &format!(
+                "replace the config, options and mods of {} with those in {}",
+                args.directory.display(),
+                args.archive.display()
+            ),
+            yes,
+        )?;
+    }
 
-    if client_jar.exists() {
-        return;
+    // Commands from someone else's archive are only kept when the user says
+    // so for these fields, not on a blanket --yes.
+    let instance = import_instance(&args.archive, &args.directory, |commands| {
+        confirm(
+            &format!(
+                "keep {} from {}, which the game is launched with",
+                commands.join(", "),
+                args.archive.display()
+            ),
+            false,
+        )
+        .is_ok()
+    })
+    .await?;
+
+    let current_exe = env::current_exe()?;
+    let launcher = instance.directory.join(LAUNCHER_FILE_NAME);
+    fs::copy(current_exe, &launcher).with_path(&launcher)?;
+    eprintln!(
+        "imported {} into {}",
+        instance.version,
+        instance.directory.display()
+    );
+
+    Ok(())
+}
+
+fn config(args: ConfigArgs) -> Result<()> {
+    let (config, sources) = effective_config(&args.directory, &args.options)?;
+
+    if args.json {
+        let sources = sources
+            .iter()
+            .map(|(field, source)| (field.to_string(), source.to_string().into()))
+            .collect::<serde_json::Map<_, _>>();
+        let shown = serde_json::json!({ "config": config, "sources": sources });
+        println!("{}", serde_json::to_string_pretty(&shown)?);
+        return Ok(());
     }
 
-    let data = http_client
-        .get(client_jar_url)
-        .send()
-        .await
-        .unwrap()
-        .bytes()
-        .await
-        .unwrap();
+    // Each field's source goes after it, or after the header of its table.
+    for line in toml::to_string(&config)?.lines() {
+        let key = line
+            .trim_start_matches('[')
+            .split([']', ' '])
+            .next()
+            .unwrap_or_default();
+        let starts_field = line.starts_with('[') || line.starts_with(&format!("{} = ", key));
+
+        match sources.iter().find(|(field, _)| *field == key) {
+            Some((_, source)) if starts_field => println!("{}  # {}", line, source),
+            _ => println!("{}", line),
+        }
+    }
 
-    fs::write(client_jar, data).unwrap()
+    Ok(())
 }
 
-async fn get_minecraft_meta(current_directory: &Path) -> serde_json::Value {
-    let minecraft_meta_path = current_directory.join("1.20.4.json");
+fn du(args: DuArgs) -> Result<()> {
+    let usage = disk_usage(&args.directory)?;
+    let mib = |bytes: u64| bytes as f64 / 1024.0 / 1024.0;
+
+    for name in USAGE_CATEGORIES {
+        let category = usage.categories[name];
+        println!(
+            "{:<10} {:>10.1} MiB  ({:.1} MiB shared)",
+            name,
+            mib(category.total()),
+            mib(category.shared)
+        );
+    }
 
-    let meta: serde_json::Value = if minecraft_meta_path.exists() {
-        fs::read_to_string(&minecraft_meta_path)
-            .unwrap()
-            .parse::<serde_json::Value>()
-            .unwrap()
-    } else {
-        let json: serde_json::Value = reqwest::get(MINECRAFT_1_20_4_META_URL)
-            .await
-            .unwrap()
-            .json()
-            .await
-            .unwrap();
+    let total = usage.total();
+    println!(
+        "{:<10} {:>10.1} MiB  ({:.1} MiB shared)",
+        "total",
+        mib(total.total()),
+        mib(total.shared)
+    );
 
-        fs::write(&minecraft_meta_path, json.to_string()).unwrap();
+    Ok(())
+}
 
-        json
-    };
+fn diff(args: DiffArgs) -> Result<()> {
+    let differences = compare_instances(&args.a, &args.b)?;
+
+    if differences.is_empty() {
+        eprintln!("no differences");
+        return Ok(());
+    }
+
+    println!("{:<48} {:<24} {}", "", args.a.display(), args.b.display());
+    for difference in &differences {
+        let what = match difference.name.as_str() {
+            "" => difference.category.to_owned(),
+            name => format!("{} {}", difference.category, name),
+        };
+        println!(
+            "{:<48} {:<24} {}",
+            what,
+            difference.a.as_deref().unwrap_or("-"),
+            difference.b.as_deref().unwrap_or("-")
+        );
+    }
 
-    meta
+    Ok(())
 }
 
-async fn create_config(instance_directory: &Path) {
-    let config_path = instance_directory.join("LauncherConfig.toml");
+fn refresh_launchers(args: RefreshLaunchersArgs) -> Result<()> {
+    let current_exe = env::current_exe()?;
+    let instances = find_instance_directories(&args.directories);
+    let mut updated = 0;
+
+    for instance in &instances {
+        match refresh_launcher(instance, &current_exe)? {
+            LauncherRefresh::Updated => {
+                println!("updated {}", instance.display());
+                updated += 1;
+            }
+            LauncherRefresh::UpToDate => println!("{} is up to date", instance.display()),
+            LauncherRefresh::NoLauncher => {
+                println!("{} has no {}", instance.display(), LAUNCHER_FILE_NAME)
+            }
+            LauncherRefresh::InUse(e) => eprintln!(
+                "warning: skipped {}, its {} may be running: {}",
+                instance.display(),
+                LAUNCHER_FILE_NAME,
+                e
+            ),
+        }
+    }
+
+    eprintln!("updated {} of {} instances", updated, instances.len());
 
-    if !config_path.exists() {
-        let config_str = toml::to_string(&LauncherConfig::default()).unwrap();
-        fs::write(config_path, config_str).unwrap();
+    Ok(())
+}
+
+fn shortcut(args: ShortcutArgs) -> Result<()> {
+    for path in create_shortcuts(&args.directory)? {
+        println!("{}", path.display());
     }
+
+    Ok(())
 }
 
-async fn create_profile() {
-    let current_directory = env::current_dir().unwrap();
-    let minecraft_meta = get_minecraft_meta(&current_directory).await;
+fn seed(args: SeedStoreArgs) -> Result<()> {
+    let summary = seed_store(&args.from)?;
+
+    println!(
+        "added {} files to {}, {} were already there",
+        summary.added,
+        store_directory().display(),
+        summary.present
+    );
+    if summary.corrupt > 0 {
+        println!("skipped {} corrupt files", summary.corrupt);
+    }
 
-    let instance_directory = Path::new("instance");
+    Ok(())
+}
 
-    fs::create_dir_all(instance_directory).unwrap();
+fn crashes(args: CrashesArgs) -> Result<()> {
+    let reports = find_crash_reports(&args.directories);
 
-    let assets_directory = instance_directory.join("assets");
-    let libraries_directory = instance_directory.join("libraries");
+    for report in reports.iter().take(args.limit) {
+        println!(
+            "{}  {}  {}",
+            report.time, report.instance, report.description
+        );
+        println!("    {}", report.exception);
+        println!("    {}", report.path.display());
+    }
 
-    let client_jar_url = minecraft_meta["downloads"]["client"]["url"]
-        .as_str()
-        .unwrap();
-    let library_entries = minecraft_meta["libraries"].as_array().unwrap();
-    let assets_url = minecraft_meta["assetIndex"]["url"].as_str().unwrap();
+    if reports.is_empty() {
+        eprintln!("no crash reports found");
+    }
 
-    create_config(&instance_directory).await;
+    Ok(())
+}
 
-    let http_client = reqwest::Client::new();
+fn accounts(command: AccountsCommand) -> Result<()> {
+    let path = Accounts::default_path();
+    let mut accounts = Accounts::load(&path)?;
 
-    download_client(http_client.clone(), &instance_directory, client_jar_url).await;
-    download_libraries(http_client.clone(), &libraries_directory, &library_entries).await;
-    download_assets(
-        http_client.clone(),
-        &assets_directory,
-        AssetIndexDownload {
-            id: "12",
-            url: assets_url,
-        },
-    )
-    .await;
+    match command {
+        AccountsCommand::List => {
+            for account in &accounts.accounts {
+                println!("{} {}", account.name, account.uuid);
+            }
+        }
+        AccountsCommand::Add(name) => {
+            accounts.add(Account::offline(&name));
+            accounts.save(&path)?;
+            eprintln!("added offline account {}", name);
+        }
+        AccountsCommand::Remove(name) => {
+            accounts.remove(&name)?;
+            accounts.save(&path)?;
+            eprintln!("removed account {}", name);
+        }
+    }
 
-    let current_exe = env::current_exe().unwrap();
-    fs::copy(current_exe, instance_directory.join("start.exe")).unwrap();
+    Ok(())
 }
 
-fn launch_minecraft() {
-    let parent_dir = env::current_exe().unwrap().parent().unwrap().to_owned();
-
-    let config_path = parent_dir.join("LauncherConfig.toml");
-    let client_path = parent_dir.join("client.jar");
-    let libraries_path = parent_dir.join("libraries");
-    let assets_path = parent_dir.join("assets");
-
-    let config = fs::read_to_string(&config_path).unwrap();
-    let config: LauncherConfig = toml::from_str(&config).unwrap();
-
-    let mut library_file_listing = list_files(&libraries_path);
-    library_file_listing.push(client_path);
-
-    let java_libraries = library_file_listing
-        .iter()
-        .map(|a| a.canonicalize().unwrap().to_str().unwrap()[4..].to_owned())
-        .collect::<Vec<String>>()
-        .join(";");
-
-    Command::new("javaw")
-        .stdin(process::Stdio::piped())
-        .stdout(process::Stdio::piped())
-        .arg("-XX:HeapDumpPath=MojangTricksIntelDriversForPerformance_javaw.exe_minecraft.exe.heapdump")
-        .arg("-Djava.library.path=".to_string() + libraries_path.to_str().unwrap())
-        .arg("-Djna.tmpdir=".to_string() + libraries_path.to_str().unwrap())
-        .arg("-Dio.netty.native.workdir=".to_string() + libraries_path.to_str().unwrap())
-        .arg("-Dminecraft.launcher.brand=minecraft-launcher")
-        .arg("-Dminecraft.launcher.version=1.20.4")
-        .args(["-cp", &java_libraries])
-        .args(["-Xmx2G", "-XX:+UnlockExperimentalVMOptions", "-XX:+UseG1GC", "-XX:G1NewSizePercent=20", "-XX:G1ReservePercent=20", "-XX:MaxGCPauseMillis=50", "-XX:G1HeapRegionSize=32M"])
-        .arg("net.minecraft.client.main.Main")
-        .args(["--username", &config.username])
-        .args(["--version", "1.20.4"])
-        .args(["--gameDir", parent_dir.to_str().unwrap()])
-        .args(["--assetsDir", assets_path.to_str().unwrap()])
-        .args(["--assetIndex", "12"])
-        .args(["--accessToken"])
-        .args(["--versionType", "release"])
-        .spawn()
-        .unwrap();
+async fn doctor(args: DoctorArgs) -> Result<()> {
+    let checks = run_doctor(&http_client()?, &args.directory).await;
+
+    for check in &checks {
+        let mark = match check.status {
+            CheckStatus::Pass => "ok  ",
+            CheckStatus::Warn => "warn",
+            CheckStatus::Fail => "FAIL",
+        };
+
+        println!("[{}] {}: {}", mark, check.name, check.detail);
+
+        if let Some(hint) = &check.hint {
+            println!("       {}", hint);
+        }
+    }
+
+    if checks.iter().any(|check| check.status == CheckStatus::Fail) {
+        process::exit(1);
+    }
+
+    Ok(())
 }
 
 #[tokio::main]
@@ -302,9 +589,53 @@ async fn main() {
         .unwrap()
         .to_owned();
 
-    if exe_name == "blazinglyassmc.exe" {
-        create_profile().await
-    } else {
-        launch_minecraft()
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    // Copies of the launcher (start.exe) still launch their instance, and
+    // scripts without a terminal still get the old behavior.
+    if args.is_empty()
+        && exe_name.starts_with("blazinglyassmc")
+        && io::stdin().is_terminal()
+        && io::stdout().is_terminal()
+    {
+        if let Err(e) = menu::run().await {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    let Cli { yes, command } = match cli::parse(&exe_name, args) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            process::exit(2);
+        }
+    };
+
+    let result = match command {
+        Command::Create(args) => create_profile(args).await,
+        Command::Launch(args) => launch(args).await,
+        Command::Accounts(command) => accounts(command),
+        Command::Doctor(args) => doctor(args).await,
+        Command::Update(args) => update(args, yes).await,
+        Command::Verify(args) => verify(args),
+        Command::Repair(args) => repair(args).await,
+        Command::Server(args) => server(args).await,
+        Command::Crashes(args) => crashes(args),
+        Command::Export(args) => export(args),
+        Command::Import(args) => import(args, yes).await,
+        Command::Config(args) => config(args),
+        Command::Versions(args) => versions(args).await,
+        Command::Du(args) => du(args),
+        Command::Diff(args) => diff(args),
+        Command::SeedStore(args) => seed(args),
+        Command::RefreshLaunchers(args) => refresh_launchers(args),
+        Command::Shortcut(args) => shortcut(args),
+    };
+
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        process::exit(1);
     }
 }
@@ -0,0 +1,180 @@
+use std::fs;
+use std::path::PathBuf;
+
+use crate::error::PathContext;
+use crate::fsutil::write_atomic;
+use crate::meta::fetch_json;
+use crate::paths::data_directory;
+use crate::sha1::Sha1;
+use crate::{Error, Result};
+
+pub const VERSION_MANIFEST_URL: &str =
+    "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
+
+/// Name of the manifest cached in the data directory.
+pub const MANIFEST_CACHE_FILE_NAME: &str = "version_manifest_v2.json";
+
+const MANIFEST_REQUIRED_KEYS: &[&str] = &["latest", "versions"];
+
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct LatestVersions {
+    pub release: String,
+    pub snapshot: String,
+}
+
+/// One entry of Mojang's version manifest.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct ManifestVersion {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub url: String,
+    #[serde(rename = "releaseTime")]
+    pub release_time: String,
+    pub sha1: String,
+}
+
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct VersionManifest {
+    pub latest: LatestVersions,
+    pub versions: Vec<ManifestVersion>,
+}
+
+impl VersionManifest {
+    /// Finds a version by id. `latest` and `latest-snapshot` name whatever the
+    /// manifest currently points them at.
+    pub fn find(&self, id: &str) -> Result<&ManifestVersion> {
+        let id = match id {
+            "latest" => self.latest.release.as_str(),
+            "latest-snapshot" => self.latest.snapshot.as_str(),
+            id => id,
+        };
+
+        self.versions
+            .iter()
+            .find(|version| version.id == id)
+            .ok_or_else(|| Error::UnknownVersion(id.to_owned()))
+    }
+
+    /// Versions of type `kind` released on or after `since`, a `YYYY-MM-DD`
+    /// date, newest first.
+    pub fn list(&self, since: Option<&str>, kind: Option<&str>) -> Vec<&ManifestVersion> {
+        let mut versions = self
+            .versions
+            .iter()
+            .filter(|version| kind.is_none_or(|kind| version.kind == kind))
+            // releaseTime is ISO 8601 in UTC, so it sorts as a string.
+            .filter(|version| since.is_none_or(|since| version.release_time.as_str() >= since))
+            .collect::<Vec<_>>();
+
+        versions.sort_by(|a, b| b.release_time.cmp(&a.release_time));
+        versions
+    }
+}
+
+pub async fn fetch_version_manifest(
+    http_client: &reqwest::Client,
+    manifest_url: &str,
+) -> Result<VersionManifest> {
+    let json = fetch_json(http_client, manifest_url, MANIFEST_REQUIRED_KEYS).await?;
+
+    Ok(serde_json::from_value(json)?)
+}
+
+/// Where the manifest at `manifest_url` is cached. Manifests other than
+/// Mojang's get a file each, named after a hash of their url, so switching
+/// sources never picks up the wrong one.
+fn manifest_cache_path(manifest_url: &str) -> PathBuf {
+    if manifest_url == VERSION_MANIFEST_URL {
+        return data_directory().join(MANIFEST_CACHE_FILE_NAME);
+    }
+
+    let mut hasher = Sha1::new();
+    hasher.update(manifest_url.as_bytes());
+    data_directory().join(format!(
+        "version_manifest_v2-{}.json",
+        &hasher.finish_hex()[..12]
+    ))
+}
+
+/// The version manifest at `manifest_url` as cached in the data directory,
+/// fetched fresh when there is no cached copy or `refresh` is set.
+pub async fn load_version_manifest(
+    http_client: &reqwest::Client,
+    manifest_url: &str,
+    refresh: bool,
+) -> Result<VersionManifest> {
+    let cache_path = manifest_cache_path(manifest_url);
+
+    if !refresh {
+        let cached = fs::read_to_string(&cache_path)
+            .ok()
+            .and_then(|manifest| serde_json::from_str(&manifest).ok());
+
+        if let Some(manifest) = cached {
+            return Ok(manifest);
+        }
+    }
+
+    let json = fetch_json(http_client, manifest_url, MANIFEST_REQUIRED_KEYS).await?;
+    fs::create_dir_all(data_directory()).with_path(&data_directory())?;
+    write_atomic(&cache_path, json.to_string()).with_path(&cache_path)?;
+
+    Ok(serde_json::from_value(json)?)
+}
+
+/// Resolves a version id like `1.21` to the url of its meta in the manifest
+/// at `manifest_url`. A cached manifest that doesn't know the version yet is
+/// refreshed once.
+pub async fn resolve_meta_url(
+    http_client: &reqwest::Client,
+    manifest_url: &str,
+    version: &str,
+    refresh: bool,
+) -> Result<String> {
+    let manifest = load_version_manifest(http_client, manifest_url, refresh).await?;
+
+    match manifest.find(version) {
+        Ok(found) => Ok(found.url.clone()),
+        Err(Error::UnknownVersion(_)) if !refresh => {
+            let manifest = load_version_manifest(http_client, manifest_url, true).await?;
+            Ok(manifest.find(version)?.url.clone())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+pub const FABRIC_META_URL: &str = "https://meta.fabricmc.net/v2";
+
+/// The meta for the newest stable Fabric loader on `game_version`, which may
+/// be `latest`. It inherits from the vanilla meta like any other loader's.
+/// Fabric only knows Mojang's versions, so they come from Mojang's manifest.
+pub async fn resolve_fabric_meta_url(
+    http_client: &reqwest::Client,
+    game_version: &str,
+    refresh: bool,
+) -> Result<String> {
+    let manifest = load_version_manifest(http_client, VERSION_MANIFEST_URL, refresh).await?;
+    let game_version = &manifest.find(game_version)?.id;
+
+    let loaders = fetch_json(
+        http_client,
+        &format!("{}/versions/loader/{}", FABRIC_META_URL, game_version),
+        &[],
+    )
+    .await?;
+
+    let loader = loaders
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|entry| &entry["loader"])
+        .find(|loader| loader["stable"] == true)
+        .and_then(|loader| loader["version"].as_str())
+        .ok_or_else(|| Error::UnknownVersion(format!("fabric for {}", game_version)))?;
+
+    Ok(format!(
+        "{}/versions/loader/{}/{}/profile/json",
+        FABRIC_META_URL, game_version, loader
+    ))
+}
@@ -0,0 +1,272 @@
+//! The interactive menu shown when the launcher is started without arguments
+//! from a terminal.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::widgets::{Block, List, ListState, Paragraph};
+use ratatui::DefaultTerminal;
+
+use blazinglyassmc::{
+    http_client, is_valid_username, load_config, load_version_manifest, read_config,
+    resolve_fabric_meta_url, save_config, InstallOptions, InstanceLock, LaunchOptions, Result,
+    VERSION_MANIFEST_URL,
+};
+
+use crate::cli::{CreateArgs, LaunchArgs, MetaSource};
+use crate::{create_profile, launch};
+
+/// Loaders an instance can be created with.
+const LOADERS: [&str; 2] = ["vanilla", "fabric"];
+
+/// Takes over the terminal for `screens`, and gives it back however they end.
+fn with_terminal<T>(screens: impl FnOnce(&mut DefaultTerminal) -> io::Result<T>) -> Result<T> {
+    let mut terminal = ratatui::try_init()?;
+    let result = screens(&mut terminal);
+    ratatui::try_restore()?;
+
+    Ok(result?)
+}
+
+/// Lets the user pick one of `items`, `None` when they back out.
+fn select(
+    terminal: &mut DefaultTerminal,
+    title: &str,
+    items: &[String],
+    status: &str,
+) -> io::Result<Option<usize>> {
+    let mut state = ListState::default().with_selected(Some(0));
+
+    loop {
+        terminal.draw(|frame| {
+            let [list_area, help_area] =
+                Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+            let list = List::new(items.iter().map(String::as_str))
+                .block(Block::bordered().title(title))
+                .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+                .highlight_symbol("> ");
+            frame.render_stateful_widget(list, list_area, &mut state);
+
+            let help = match status {
+                "" => String::from("↑/↓ move, enter picks, esc goes back"),
+                status => status.to_owned(),
+            };
+            frame.render_widget(Paragraph::new(help), help_area);
+        })?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => state.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => state.select_next(),
+            KeyCode::PageUp => state.scroll_up_by(10),
+            KeyCode::PageDown => state.scroll_down_by(10),
+            KeyCode::Home => state.select_first(),
+            KeyCode::End => state.select_last(),
+            KeyCode::Enter => return Ok(state.selected().filter(|&index| index < items.len())),
+            KeyCode::Esc | KeyCode::Char('q') => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+/// Asks for a line of text, `default` when it's left empty. `None` when the
+/// user backs out.
+fn input(terminal: &mut DefaultTerminal, title: &str, default: &str) -> io::Result<Option<String>> {
+    let mut answer = String::new();
+
+    loop {
+        terminal.draw(|frame| {
+            let [input_area, help_area, _] = Layout::vertical([
+                Constraint::Length(3),
+                Constraint::Length(1),
+                Constraint::Min(0),
+            ])
+            .areas(frame.area());
+            let text = match answer.as_str() {
+                "" => Paragraph::new(default).style(Style::new().add_modifier(Modifier::DIM)),
+                answer => Paragraph::new(answer),
+            };
+            frame.render_widget(text.block(Block::bordered().title(title)), input_area);
+            frame.render_widget(Paragraph::new("enter accepts, esc goes back"), help_area);
+        })?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        match key.code {
+            KeyCode::Char(c) => answer.push(c),
+            KeyCode::Backspace => {
+                answer.pop();
+            }
+            KeyCode::Enter if answer.is_empty() => return Ok(Some(default.to_owned())),
+            KeyCode::Enter => return Ok(Some(answer)),
+            KeyCode::Esc => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+/// Instances directly inside the current directory, with what they run.
+/// Their configs are only read, so listing them never rewrites or warns
+/// about one.
+fn find_instances() -> Vec<(PathBuf, String)> {
+    let mut instances = fs::read_dir(".")
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| PathBuf::from(entry.file_name()))
+        .filter(|path| read_config(path).is_ok())
+        .map(|path| {
+            let version = match InstanceLock::load(&path) {
+                Ok(Some(lock)) => lock.loader.unwrap_or(lock.version),
+                _ => String::from("unknown version"),
+            };
+            (path, version)
+        })
+        .collect::<Vec<_>>();
+
+    instances.sort();
+    instances
+}
+
+/// Asks for a version from the manifest, a loader and a directory, then
+/// creates the instance. Returns what to tell the user back in the menu.
+async fn create_instance() -> Result<String> {
+    let http_client = http_client()?;
+    let manifest = load_version_manifest(&http_client, VERSION_MANIFEST_URL, false).await?;
+
+    let mut versions = vec![&manifest.latest.snapshot];
+    versions.extend(
+        manifest
+            .list(None, Some("release"))
+            .into_iter()
+            .map(|version| &version.id),
+    );
+    let mut items = versions.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+    items[0] = format!("{} (latest snapshot)", items[0]);
+
+    let loaders = LOADERS.map(String::from);
+    let choice = with_terminal(|terminal| {
+        let Some(version) = select(terminal, "Minecraft version", &items, "")? else {
+            return Ok(None);
+        };
+        let Some(loader) = select(terminal, "loader", &loaders, "")? else {
+            return Ok(None);
+        };
+        let Some(directory) = input(terminal, "instance directory", "instance")? else {
+            return Ok(None);
+        };
+        Ok(Some((
+            versions[version].clone(),
+            LOADERS[loader],
+            directory,
+        )))
+    })?;
+    let Some((version, loader, directory)) = choice else {
+        return Ok(String::new());
+    };
+
+    let meta_url = match loader {
+        "fabric" => resolve_fabric_meta_url(&http_client, &version, false).await?,
+        _ => manifest.find(&version)?.url.clone(),
+    };
+
+    create_profile(CreateArgs {
+        meta: MetaSource::Url(meta_url),
+        refresh_manifest: false,
+        directory: PathBuf::from(&directory),
+        template: None,
+        options: InstallOptions {
+            resumable: true,
+            ..InstallOptions::default()
+        },
+        download_only: false,
+        serve_status: None,
+    })
+    .await?;
+
+    Ok(format!("created {} with {} {}", directory, loader, version))
+}
+
+/// Asks for a username and gives it to every instance. Returns what to tell
+/// the user back in the menu.
+fn set_username(instances: &[(PathBuf, String)]) -> Result<String> {
+    let Some(username) = with_terminal(|terminal| input(terminal, "username", ""))? else {
+        return Ok(String::new());
+    };
+
+    if !is_valid_username(&username) {
+        return Ok(format!(
+            "{} isn't a valid username, use 3 to 16 letters, digits or _",
+            username
+        ));
+    }
+
+    for (directory, _) in instances {
+        let mut config = load_config(directory)?;
+        config.username = username.clone();
+        save_config(directory, &config)?;
+    }
+
+    Ok(format!(
+        "set the username of {} instances to {}",
+        instances.len(),
+        username
+    ))
+}
+
+/// Shows the instances in the current directory until one is launched or
+/// the menu is left.
+pub async fn run() -> Result<()> {
+    let mut status = String::new();
+
+    loop {
+        let instances = find_instances();
+
+        let mut items = instances
+            .iter()
+            .map(|(directory, version)| format!("launch {} ({})", directory.display(), version))
+            .collect::<Vec<_>>();
+        items.push(String::from("create a new instance"));
+        items.push(String::from("set the username of every instance here"));
+        items.push(String::from("quit"));
+
+        let choice =
+            with_terminal(|terminal| select(terminal, env!("CARGO_PKG_NAME"), &items, &status))?;
+
+        status = match choice {
+            Some(index) if index < instances.len() => {
+                return launch(LaunchArgs {
+                    directory: Some(instances[index].0.clone()),
+                    options: LaunchOptions::default(),
+                    startup_timeout: None,
+                    print_classpath: false,
+                    print_command: false,
+                    serve_status: None,
+                    notify_url: None,
+                })
+                .await
+            }
+            // A failed create can be retried from the menu like any other.
+            Some(index) if index == instances.len() => create_instance()
+                .await
+                .unwrap_or_else(|e| format!("error: {}", e)),
+            Some(index) if index == instances.len() + 1 => set_username(&instances)?,
+            _ => return Ok(()),
+        };
+    }
+}
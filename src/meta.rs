@@ -0,0 +1,855 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use crate::error::{ParseContext, PathContext};
+use crate::manifest::resolve_meta_url;
+use crate::rules::{natives_arch, os_name, rules_allow};
+use crate::{Error, Result};
+
+pub const MINECRAFT_1_20_4_META_URL: &str = "https://piston-meta.mojang.com/v1/packages/efcc510e525cef0e859b5435f82b6e3193214efc/1.20.4.json";
+
+/// Name of the cached copy of the version meta inside an instance.
+pub const META_FILE_NAME: &str = "version.json";
+
+// Real metas and asset indexes are tens of kilobytes; anything this small is an
+// error page that was served with a 200.
+const MIN_JSON_BODY_LEN: usize = 64;
+
+// Not assetIndex, which the oldest versions don't have.
+pub(crate) const META_REQUIRED_KEYS: &[&str] = &["downloads", "libraries"];
+pub(crate) const ASSET_INDEX_REQUIRED_KEYS: &[&str] = &["objects"];
+
+fn check_json_keys(source: &str, json: &serde_json::Value, required_keys: &[&str]) -> Result<()> {
+    let missing = required_keys
+        .iter()
+        .filter(|key| json.get(**key).is_none())
+        .collect::<Vec<_>>();
+
+    if !missing.is_empty() {
+        return Err(Error::InvalidMeta(format!(
+            "{} is missing expected keys {:?}",
+            source, missing
+        )));
+    }
+
+    Ok(())
+}
+
+pub(crate) async fn fetch_json(
+    http_client: &reqwest::Client,
+    url: &str,
+    required_keys: &[&str],
+) -> Result<serde_json::Value> {
+    let invalid = |reason: String| Error::InvalidResponse {
+        url: url.to_owned(),
+        reason,
+    };
+
+    let response = http_client.get(url).send().await?.error_for_status()?;
+    let body = response.bytes().await?;
+
+    if body.len() < MIN_JSON_BODY_LEN {
+        return Err(invalid(format!(
+            "returned a suspiciously small body ({} bytes)",
+            body.len()
+        )));
+    }
+
+    let json: serde_json::Value = serde_json::from_slice(&body).parsing(url)?;
+    check_json_keys(url, &json, required_keys)?;
+
+    Ok(json)
+}
+
+/// How many `inheritsFrom` hops are followed before giving up on a meta.
+const MAX_INHERITANCE_DEPTH: usize = 4;
+
+/// Identifies a library regardless of its version, so `g:a:1.0` and
+/// `g:a:1.1` are the same library but `g:a:1.0:natives-linux` is not.
+pub(crate) fn coordinate_key(name: &str) -> String {
+    let parts = name.split(':').collect::<Vec<_>>();
+
+    match parts.as_slice() {
+        [group, artifact, _, rest @ ..] => format!("{}:{}:{}", group, artifact, rest.join(":")),
+        _ => name.to_owned(),
+    }
+}
+
+/// Where the Maven coordinate `group:artifact:version[:classifier][@extension]`
+/// lives in a repository. The extension defaults to jar.
+pub fn maven_path(coordinate: &str) -> Option<String> {
+    let (coordinate, extension) = coordinate.split_once('@').unwrap_or((coordinate, "jar"));
+    let parts = coordinate.split(':').collect::<Vec<_>>();
+
+    let (group, artifact, version, classifier) = match parts.as_slice() {
+        [group, artifact, version] => (group, artifact, version, None),
+        [group, artifact, version, classifier] => (group, artifact, version, Some(classifier)),
+        _ => return None,
+    };
+
+    if [group, artifact, version]
+        .iter()
+        .any(|part| part.is_empty())
+        || extension.is_empty()
+    {
+        return None;
+    }
+
+    let file_name = match classifier {
+        Some(classifier) => format!("{}-{}-{}.{}", artifact, version, classifier, extension),
+        None => format!("{}-{}.{}", artifact, version, extension),
+    };
+
+    Some(format!(
+        "{}/{}/{}/{}",
+        group.replace('.', "/"),
+        artifact,
+        version,
+        file_name
+    ))
+}
+
+fn library_key(library: &serde_json::Value) -> Option<String> {
+    library["name"].as_str().map(coordinate_key)
+}
+
+fn merge_values(parent: &mut serde_json::Value, child: serde_json::Value) {
+    match (parent, child) {
+        (serde_json::Value::Object(parent), serde_json::Value::Object(child)) => {
+            for (key, value) in child {
+                match parent.get_mut(&key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => {
+                        parent.insert(key, value);
+                    }
+                }
+            }
+        }
+        (parent, child) => *parent = child,
+    }
+}
+
+/// Merges a loader meta over the meta it `inheritsFrom`. The child's
+/// libraries come first and replace the parent's versions of the same
+/// library, its arguments are appended to the parent's, and everything else
+/// the child sets wins.
+pub fn merge_inherited(
+    mut parent: serde_json::Value,
+    mut child: serde_json::Value,
+) -> serde_json::Value {
+    let child_libraries = child
+        .get_mut("libraries")
+        .map(serde_json::Value::take)
+        .and_then(|libraries| match libraries {
+            serde_json::Value::Array(libraries) => Some(libraries),
+            _ => None,
+        })
+        .unwrap_or_default();
+    let child_arguments = child
+        .get_mut("arguments")
+        .map(serde_json::Value::take)
+        .unwrap_or_default();
+
+    if let Some(object) = child.as_object_mut() {
+        object.remove("libraries");
+        object.remove("arguments");
+    }
+
+    if !child_libraries.is_empty() {
+        let overridden = child_libraries
+            .iter()
+            .filter_map(library_key)
+            .collect::<Vec<_>>();
+        let parent_libraries = parent["libraries"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|library| library_key(library).is_none_or(|key| !overridden.contains(&key)));
+
+        parent["libraries"] = serde_json::Value::Array(
+            child_libraries
+                .into_iter()
+                .chain(parent_libraries)
+                .collect(),
+        );
+    }
+
+    if let serde_json::Value::Object(child_arguments) = child_arguments {
+        for (kind, arguments) in child_arguments {
+            let serde_json::Value::Array(arguments) = arguments else {
+                continue;
+            };
+
+            match parent["arguments"][&kind].as_array_mut() {
+                Some(existing) => existing.extend(arguments),
+                None => parent["arguments"][&kind] = serde_json::Value::Array(arguments),
+            }
+        }
+    }
+
+    merge_values(&mut parent, child);
+
+    parent
+}
+
+/// Where [`override_lwjgl`] fetches LWJGL from, since Mojang only hosts the
+/// versions its own metas use.
+const MAVEN_CENTRAL_URL: &str = "https://repo1.maven.org/maven2";
+
+/// Maven groups of LWJGL 3 and LWJGL 2.
+const LWJGL_GROUPS: &[&str] = &["org.lwjgl", "org.lwjgl.lwjgl"];
+
+/// Swaps the LWJGL libraries of `meta` for `version` of them from Maven
+/// Central, by merging them over it the way a loader meta is merged over its
+/// parent. The replacements keep their rules and natives but have no hashes,
+/// and nothing checks the version suits the game.
+pub fn override_lwjgl(meta: serde_json::Value, version: &str) -> Result<serde_json::Value> {
+    if version.is_empty() || version.contains([':', '@', '/']) {
+        return Err(Error::InvalidLwjglVersion(version.to_owned()));
+    }
+
+    let mut replacements = Vec::new();
+    for library in meta["libraries"].as_array().into_iter().flatten() {
+        let Some(name) = library["name"].as_str() else {
+            continue;
+        };
+        let mut parts = name.split(':').collect::<Vec<_>>();
+        if parts.len() < 3 || !LWJGL_GROUPS.contains(&parts[0]) {
+            continue;
+        }
+        parts[2] = version;
+        let name = parts.join(":");
+
+        let mut replacement = library.clone();
+        replacement["name"] = serde_json::Value::String(name.clone());
+
+        match library["downloads"]["classifiers"].as_object() {
+            // Old natives entries: build each classifier's download by hand,
+            // since the resolver only fills in the main artifact.
+            Some(classifiers) => {
+                let artifact = |coordinate: &str| {
+                    let path = maven_path(coordinate).unwrap_or_default();
+                    serde_json::json!({
+                        "path": path,
+                        "url": format!("{}/{}", MAVEN_CENTRAL_URL, path),
+                    })
+                };
+                let mut downloads = serde_json::json!({
+                    "classifiers": classifiers
+                        .keys()
+                        .map(|classifier| {
+                            let coordinate = format!("{}:{}", name, classifier);
+                            (classifier.clone(), artifact(&coordinate))
+                        })
+                        .collect::<serde_json::Map<_, _>>(),
+                });
+                if library["downloads"].get("artifact").is_some() {
+                    downloads["artifact"] = artifact(&name);
+                }
+                replacement["downloads"] = downloads;
+            }
+            None => {
+                if let Some(object) = replacement.as_object_mut() {
+                    object.remove("downloads");
+                    object.remove("sha1");
+                    object.remove("size");
+                }
+                replacement["url"] = serde_json::Value::String(MAVEN_CENTRAL_URL.to_owned());
+            }
+        }
+
+        replacements.push(replacement);
+    }
+
+    Ok(merge_inherited(
+        meta,
+        serde_json::json!({ "libraries": replacements }),
+    ))
+}
+
+/// Fetches one version meta, telling a removed package apart from other
+/// failures.
+async fn fetch_version_json(
+    http_client: &reqwest::Client,
+    meta_url: &str,
+) -> Result<serde_json::Value> {
+    fetch_json(http_client, meta_url, &["id"])
+        .await
+        .map_err(|e| match e {
+            Error::Network { source, .. }
+                if source.status() == Some(reqwest::StatusCode::NOT_FOUND) =>
+            {
+                Error::MetaNotFound {
+                    // Package urls end in the version id, e.g. .../1.20.4.json.
+                    version: meta_url
+                        .rsplit('/')
+                        .next()
+                        .and_then(|file| file.strip_suffix(".json"))
+                        .map(String::from),
+                    url: meta_url.to_owned(),
+                }
+            }
+            e => e,
+        })
+}
+
+/// Fetches the meta at `meta_url` and, for loader metas, the chain of metas
+/// it inherits from, merged into one effective meta. Parents are looked up in
+/// the manifest at `manifest_url`.
+pub(crate) async fn fetch_meta(
+    http_client: &reqwest::Client,
+    meta_url: &str,
+    manifest_url: &str,
+) -> Result<serde_json::Value> {
+    let mut chain = vec![fetch_version_json(http_client, meta_url).await?];
+
+    while let Some(parent) = chain.last().unwrap()["inheritsFrom"].as_str() {
+        if chain.len() > MAX_INHERITANCE_DEPTH {
+            return Err(Error::InvalidMeta(format!(
+                "{} inherits through more than {} metas",
+                meta_url, MAX_INHERITANCE_DEPTH
+            )));
+        }
+
+        let parent_url = resolve_meta_url(http_client, manifest_url, parent, false).await?;
+        chain.push(fetch_version_json(http_client, &parent_url).await?);
+    }
+
+    let mut meta = chain.pop().unwrap();
+    while let Some(child) = chain.pop() {
+        meta = merge_inherited(meta, child);
+    }
+
+    check_json_keys(meta_url, &meta, META_REQUIRED_KEYS)?;
+
+    Ok(meta)
+}
+
+/// Loads the meta cached at `cache_path`, fetching it from `meta_url` when
+/// there is no cached copy yet. Loader metas are cached already merged with
+/// their parent.
+pub async fn get_minecraft_meta(
+    http_client: &reqwest::Client,
+    meta_url: &str,
+    manifest_url: &str,
+    cache_path: &Path,
+) -> Result<serde_json::Value> {
+    if cache_path.exists() {
+        let json = fs::read_to_string(cache_path)
+            .with_path(cache_path)?
+            .parse::<serde_json::Value>()
+            .parsing(cache_path.display())?;
+        check_json_keys(&cache_path.display().to_string(), &json, META_REQUIRED_KEYS)?;
+
+        return Ok(json);
+    }
+
+    // Pretty so the cached meta can be read and diffed between versions.
+    let json = match embedded_meta() {
+        Some(json) => json?,
+        None => fetch_meta(http_client, meta_url, manifest_url).await?,
+    };
+    fs::write(cache_path, serde_json::to_string_pretty(&json)?).with_path(cache_path)?;
+
+    Ok(json)
+}
+
+#[cfg(any(feature = "embedded-meta", feature = "embedded-asset-index"))]
+fn parse_embedded(what: &str, json: &str, required_keys: &[&str]) -> Result<serde_json::Value> {
+    let json = serde_json::from_str(json).parsing(what)?;
+    check_json_keys(what, &json, required_keys)?;
+
+    Ok(json)
+}
+
+/// The meta built into the launcher with the `embedded-meta` feature, used
+/// instead of fetching whatever meta is asked for. It comes from the file
+/// `BLAZINGLYASSMC_META` names at build time and can't inherit from another,
+/// so an installed instance's version.json is a good source.
+pub(crate) fn embedded_meta() -> Option<Result<serde_json::Value>> {
+    #[cfg(feature = "embedded-meta")]
+    return Some(parse_embedded(
+        "embedded meta",
+        include_str!(env!("BLAZINGLYASSMC_META")),
+        META_REQUIRED_KEYS,
+    ));
+
+    #[cfg(not(feature = "embedded-meta"))]
+    None
+}
+
+/// The asset index built in with the `embedded-asset-index` feature, from
+/// the file `BLAZINGLYASSMC_ASSET_INDEX` names at build time.
+pub(crate) fn embedded_asset_index() -> Option<Result<serde_json::Value>> {
+    #[cfg(feature = "embedded-asset-index")]
+    return Some(parse_embedded(
+        "embedded asset index",
+        include_str!(env!("BLAZINGLYASSMC_ASSET_INDEX")),
+        ASSET_INDEX_REQUIRED_KEYS,
+    ));
+
+    #[cfg(not(feature = "embedded-asset-index"))]
+    None
+}
+
+/// Reads the meta an instance was installed from.
+pub fn load_instance_meta(instance_directory: &Path) -> Result<serde_json::Value> {
+    let meta_path = instance_directory.join(META_FILE_NAME);
+    let json = fs::read_to_string(&meta_path)
+        .with_path(&meta_path)?
+        .parse::<serde_json::Value>()
+        .parsing(meta_path.display())?;
+    check_json_keys(&meta_path.display().to_string(), &json, META_REQUIRED_KEYS)?;
+
+    Ok(json)
+}
+
+/// The top-level meta fields the launcher reads. Stripped metas leave out
+/// the rest, like `logging` and `releaseTime`.
+const STRIPPED_META_KEYS: &[&str] = &[
+    "id",
+    "type",
+    "inheritsFrom",
+    "mainClass",
+    "arguments",
+    "minecraftArguments",
+    "assetIndex",
+    "javaVersion",
+    "libraries",
+    "downloads",
+];
+
+/// `meta` cut down to [`STRIPPED_META_KEYS`], with only the client of its
+/// downloads, for instances that keep a minimal meta.
+pub fn strip_meta(meta: serde_json::Value) -> serde_json::Value {
+    let serde_json::Value::Object(mut fields) = meta else {
+        return meta;
+    };
+
+    fields.retain(|key, _| STRIPPED_META_KEYS.contains(&key.as_str()));
+    if let Some(serde_json::Value::Object(downloads)) = fields.get_mut("downloads") {
+        downloads.retain(|side, _| side == "client");
+    }
+
+    serde_json::Value::Object(fields)
+}
+
+/// The id of the meta's asset index, like `17`, `1.12` or `legacy`. It's
+/// both the index's file name under `assets/indexes` and `--assetIndex`.
+/// `None` for the oldest versions, which have no asset index and load the
+/// resources bundled in their jar instead.
+pub fn asset_index_id(meta: &serde_json::Value) -> Option<&str> {
+    meta["assetIndex"]["id"].as_str()
+}
+
+/// A downloadable file as the meta describes it.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct Artifact {
+    pub path: String,
+    pub url: String,
+    #[serde(default)]
+    pub sha1: Option<String>,
+    #[serde(default)]
+    pub size: u64,
+}
+
+#[derive(Clone, Debug, Default, serde::Deserialize)]
+pub struct LibraryDownloads {
+    /// The library's jar. Old natives-only entries have just classifiers.
+    #[serde(default)]
+    pub artifact: Option<Artifact>,
+    #[serde(default)]
+    pub classifiers: HashMap<String, Artifact>,
+}
+
+#[derive(Clone, Debug, Default, serde::Deserialize)]
+pub struct ExtractRules {
+    /// Path prefixes inside a natives jar that aren't extracted.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+/// One entry of the meta's `libraries` as it's written, before
+/// [`Library::from`] fills in what it leaves to be worked out.
+#[derive(serde::Deserialize)]
+struct LibraryEntry {
+    name: String,
+    #[serde(default)]
+    downloads: LibraryDownloads,
+    #[serde(default)]
+    natives: HashMap<String, String>,
+    #[serde(default)]
+    rules: Option<serde_json::Value>,
+    #[serde(default)]
+    extract: ExtractRules,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    sha1: Option<String>,
+    #[serde(default)]
+    size: u64,
+}
+
+/// One entry of the meta's `libraries`.
+#[derive(Clone, Debug, serde::Deserialize)]
+#[serde(from = "LibraryEntry")]
+pub struct Library {
+    pub name: String,
+    pub downloads: LibraryDownloads,
+    /// Maps an os name to the classifier holding its natives.
+    pub natives: HashMap<String, String>,
+    pub rules: Option<serde_json::Value>,
+    pub extract: ExtractRules,
+    /// Maven repository to fetch the library from, for loader libraries that
+    /// only have a `name` and no `downloads`.
+    pub url: Option<String>,
+    pub sha1: Option<String>,
+    pub size: u64,
+    /// [`Library::applies`], worked out once when the library is read so the
+    /// downloads and the classpath can't disagree about it.
+    applies: bool,
+}
+
+impl From<LibraryEntry> for Library {
+    fn from(entry: LibraryEntry) -> Self {
+        let mut library = Self {
+            name: entry.name,
+            downloads: entry.downloads,
+            natives: entry.natives,
+            rules: entry.rules,
+            extract: entry.extract,
+            url: entry.url,
+            sha1: entry.sha1,
+            size: entry.size,
+            applies: false,
+        };
+        library.resolve_maven_artifact();
+        library.applies = library.evaluate_rules();
+        library
+    }
+}
+
+impl Library {
+    /// Whether the library is for this OS and, for natives, this CPU.
+    pub fn applies(&self) -> bool {
+        self.applies
+    }
+
+    fn evaluate_rules(&self) -> bool {
+        rules_allow(self.rules.as_ref(), &[])
+            && self
+                .classifier_arch()
+                .is_none_or(|arch| arch == natives_arch())
+    }
+
+    /// The CPU a `natives-<os>[-<arch>]` library is built for. Mojang's
+    /// rules only pick the OS, so this is what tells `natives-macos` (x86_64)
+    /// from `natives-macos-arm64`.
+    pub fn classifier_arch(&self) -> Option<&str> {
+        let classifier = self.name.split('@').next()?.split(':').nth(3)?;
+        let platform = classifier.strip_prefix("natives-")?;
+
+        Some(match platform.split_once('-') {
+            Some((_, arch)) => arch,
+            None => "x86_64",
+        })
+    }
+
+    /// The natives jar for this platform, for libraries that ship their
+    /// natives as a classifier.
+    pub fn native_artifact(&self) -> Option<&Artifact> {
+        let arch = if cfg!(target_pointer_width = "64") {
+            "64"
+        } else {
+            "32"
+        };
+        let classifier = self.natives.get(os_name())?.replace("${arch}", arch);
+
+        self.downloads.classifiers.get(&classifier)
+    }
+
+    /// Fills in `downloads.artifact` from the Maven coordinate for libraries
+    /// that are only given as a `name` and repository `url`.
+    fn resolve_maven_artifact(&mut self) {
+        if self.downloads.artifact.is_some() || !self.downloads.classifiers.is_empty() {
+            return;
+        }
+
+        let (Some(repository), Some(path)) = (&self.url, maven_path(&self.name)) else {
+            return;
+        };
+
+        self.downloads.artifact = Some(Artifact {
+            url: format!("{}/{}", repository.trim_end_matches('/'), path),
+            path,
+            sha1: self.sha1.clone(),
+            size: self.size,
+        });
+    }
+
+    /// The library's coordinate without its version, see [`library_key`].
+    pub fn key(&self) -> String {
+        coordinate_key(&self.name)
+    }
+
+    /// Every file this library needs on disk here: its jar and its natives.
+    pub fn artifacts(&self) -> impl Iterator<Item = &Artifact> {
+        self.downloads.artifact.iter().chain(self.native_artifact())
+    }
+}
+
+/// Fails when `libraries` has natives for this OS but none for this CPU, as
+/// with LWJGL on ARM Linux, rather than launching a game that can't load them.
+pub fn check_natives_arch(libraries: &[Library]) -> Result<()> {
+    let mut found = BTreeMap::<&str, bool>::new();
+
+    for library in libraries
+        .iter()
+        .filter(|library| rules_allow(library.rules.as_ref(), &[]))
+    {
+        if let Some(arch) = library.classifier_arch() {
+            let artifact = match library.name.match_indices(':').nth(1) {
+                Some((end, _)) => &library.name[..end],
+                None => &library.name,
+            };
+            *found.entry(artifact).or_default() |= arch == natives_arch();
+        }
+    }
+
+    match found.into_iter().find(|(_, found)| !found) {
+        Some((library, _)) => Err(Error::UnsupportedArch {
+            library: library.to_owned(),
+            arch: natives_arch().to_owned(),
+        }),
+        None => Ok(()),
+    }
+}
+
+pub fn parse_libraries(meta: &serde_json::Value) -> Result<Vec<Library>> {
+    let libraries = meta
+        .get("libraries")
+        .ok_or_else(|| Error::InvalidMeta(String::from("missing /libraries")))?;
+
+    serde_json::from_value(libraries.clone())
+        .map_err(|e| Error::InvalidMeta(format!("invalid libraries: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn stripped_meta_keeps_only_what_the_launcher_reads() {
+        let meta = json!({
+            "id": "1.20.4",
+            "mainClass": "net.minecraft.client.main.Main",
+            "downloads": {
+                "client": { "url": "https://example.com/client.jar" },
+                "server": { "url": "https://example.com/server.jar" },
+                "client_mappings": { "url": "https://example.com/client.txt" }
+            },
+            "logging": { "client": { "argument": "-Dlog4j.configurationFile=${path}" } },
+            "releaseTime": "2023-12-07T12:56:20+00:00",
+            "libraries": [{ "name": "com.mojang:brigadier:1.2.9" }]
+        });
+
+        let stripped = strip_meta(meta);
+
+        assert_eq!(
+            stripped,
+            json!({
+                "id": "1.20.4",
+                "mainClass": "net.minecraft.client.main.Main",
+                "downloads": { "client": { "url": "https://example.com/client.jar" } },
+                "libraries": [{ "name": "com.mojang:brigadier:1.2.9" }]
+            })
+        );
+    }
+
+    #[test]
+    fn child_meta_merges_over_parent() {
+        let parent = json!({
+            "id": "1.20.4",
+            "mainClass": "net.minecraft.client.main.Main",
+            "downloads": { "client": { "url": "https://example.com/client.jar" } },
+            "assetIndex": { "id": "12" },
+            "arguments": {
+                "game": ["--username", "${auth_player_name}"],
+                "jvm": ["-cp", "${classpath}"]
+            },
+            "libraries": [
+                { "name": "org.ow2.asm:asm:9.3" },
+                { "name": "com.mojang:brigadier:1.2.9" },
+                { "name": "org.lwjgl:lwjgl:3.3.2:natives-linux" }
+            ]
+        });
+        let child = json!({
+            "id": "fabric-loader-0.15.7-1.20.4",
+            "inheritsFrom": "1.20.4",
+            "mainClass": "net.fabricmc.loader.impl.launch.knot.KnotClient",
+            "arguments": { "jvm": ["-DFabricMcEmu= net.minecraft.client.main.Main "] },
+            "libraries": [
+                { "name": "org.ow2.asm:asm:9.6" },
+                { "name": "net.fabricmc:fabric-loader:0.15.7" }
+            ]
+        });
+
+        let merged = merge_inherited(parent, child);
+
+        assert_eq!(merged["id"], "fabric-loader-0.15.7-1.20.4");
+        assert_eq!(merged["inheritsFrom"], "1.20.4");
+        assert_eq!(
+            merged["mainClass"],
+            "net.fabricmc.loader.impl.launch.knot.KnotClient"
+        );
+        assert_eq!(
+            merged["downloads"]["client"]["url"],
+            "https://example.com/client.jar"
+        );
+        assert_eq!(merged["assetIndex"]["id"], "12");
+
+        let libraries = merged["libraries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|library| library["name"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            libraries,
+            [
+                "org.ow2.asm:asm:9.6",
+                "net.fabricmc:fabric-loader:0.15.7",
+                "com.mojang:brigadier:1.2.9",
+                "org.lwjgl:lwjgl:3.3.2:natives-linux"
+            ]
+        );
+
+        assert_eq!(
+            merged["arguments"]["jvm"],
+            json!([
+                "-cp",
+                "${classpath}",
+                "-DFabricMcEmu= net.minecraft.client.main.Main "
+            ])
+        );
+        assert_eq!(
+            merged["arguments"]["game"],
+            json!(["--username", "${auth_player_name}"])
+        );
+    }
+
+    #[test]
+    fn child_without_libraries_keeps_parents() {
+        let parent = json!({ "id": "1.12.2", "libraries": [{ "name": "a:b:1" }] });
+        let child = json!({ "id": "forge", "inheritsFrom": "1.12.2", "minecraftArguments": "--tweakClass x" });
+
+        let merged = merge_inherited(parent, child);
+
+        assert_eq!(merged["libraries"], json!([{ "name": "a:b:1" }]));
+        assert_eq!(merged["minecraftArguments"], "--tweakClass x");
+    }
+
+    #[test]
+    fn maven_coordinate_to_path() {
+        assert_eq!(
+            maven_path("net.fabricmc:tiny-mappings-parser:0.3.0").as_deref(),
+            Some("net/fabricmc/tiny-mappings-parser/0.3.0/tiny-mappings-parser-0.3.0.jar")
+        );
+        assert_eq!(
+            maven_path("org.lwjgl:lwjgl:3.3.2:natives-linux").as_deref(),
+            Some("org/lwjgl/lwjgl/3.3.2/lwjgl-3.3.2-natives-linux.jar")
+        );
+        assert_eq!(
+            maven_path("de.oceanlabs.mcp:mcp_config:1.20.4@zip").as_deref(),
+            Some("de/oceanlabs/mcp/mcp_config/1.20.4/mcp_config-1.20.4.zip")
+        );
+        assert_eq!(
+            maven_path("net.minecraft:client:1.20.4:mappings@txt").as_deref(),
+            Some("net/minecraft/client/1.20.4/client-1.20.4-mappings.txt")
+        );
+        assert_eq!(maven_path("net.fabricmc:fabric-loader"), None);
+        assert_eq!(maven_path("a::1"), None);
+    }
+
+    #[test]
+    fn name_only_libraries_resolve_from_their_repository() {
+        let meta = json!({ "libraries": [
+            {
+                "name": "net.fabricmc:sponge-mixin:0.12.5+mixin.0.8.5",
+                "url": "https://maven.fabricmc.net/",
+                "sha1": "8d31fb97c3e0cd7c8dad3441851c523bcfae6d8e",
+                "size": 1451874
+            },
+            { "name": "org.ow2.asm:asm:9.6" }
+        ] });
+
+        let libraries = parse_libraries(&meta).unwrap();
+        let artifact = libraries[0].downloads.artifact.as_ref().unwrap();
+
+        assert_eq!(
+            artifact.path,
+            "net/fabricmc/sponge-mixin/0.12.5+mixin.0.8.5/sponge-mixin-0.12.5+mixin.0.8.5.jar"
+        );
+        assert_eq!(
+            artifact.url,
+            "https://maven.fabricmc.net/net/fabricmc/sponge-mixin/0.12.5+mixin.0.8.5/sponge-mixin-0.12.5+mixin.0.8.5.jar"
+        );
+        assert_eq!(artifact.size, 1451874);
+        assert!(libraries[1].downloads.artifact.is_none());
+    }
+
+    #[test]
+    fn lwjgl_override_replaces_every_lwjgl_library() {
+        let meta = json!({ "libraries": [
+            { "name": "com.mojang:brigadier:1.0.18", "downloads": { "artifact": {
+                "path": "com/mojang/brigadier/1.0.18/brigadier-1.0.18.jar",
+                "url": "https://libraries.minecraft.net/com/mojang/brigadier/1.0.18/brigadier-1.0.18.jar"
+            } } },
+            { "name": "org.lwjgl:lwjgl:3.3.1", "downloads": { "artifact": {
+                "path": "org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1.jar",
+                "url": "https://libraries.minecraft.net/org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1.jar",
+                "sha1": "ae58664f88e18a9bb2c77b063833ca7aaec484cb"
+            } } },
+            {
+                "name": "org.lwjgl:lwjgl-glfw:3.2.2",
+                "natives": { "linux": "natives-linux" },
+                "downloads": { "classifiers": { "natives-linux": {
+                    "path": "org/lwjgl/lwjgl-glfw/3.2.2/lwjgl-glfw-3.2.2-natives-linux.jar",
+                    "url": "https://libraries.minecraft.net/org/lwjgl/lwjgl-glfw/3.2.2/lwjgl-glfw-3.2.2-natives-linux.jar"
+                } } }
+            }
+        ] });
+
+        let merged = override_lwjgl(meta, "3.3.3").unwrap();
+        let libraries = parse_libraries(&merged).unwrap();
+        let names = libraries
+            .iter()
+            .map(|library| library.name.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            names,
+            [
+                "org.lwjgl:lwjgl:3.3.3",
+                "org.lwjgl:lwjgl-glfw:3.3.3",
+                "com.mojang:brigadier:1.0.18"
+            ]
+        );
+
+        let artifact = libraries[0].downloads.artifact.as_ref().unwrap();
+        assert_eq!(
+            artifact.url,
+            "https://repo1.maven.org/maven2/org/lwjgl/lwjgl/3.3.3/lwjgl-3.3.3.jar"
+        );
+        assert_eq!(artifact.sha1, None);
+        assert_eq!(
+            libraries[1].downloads.classifiers["natives-linux"].path,
+            "org/lwjgl/lwjgl-glfw/3.3.3/lwjgl-glfw-3.3.3-natives-linux.jar"
+        );
+        assert!(libraries[1].downloads.artifact.is_none());
+        assert!(override_lwjgl(merged, "3.3:3").is_err());
+    }
+}
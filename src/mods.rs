@@ -0,0 +1,647 @@
+//! Reading what installed mods say about themselves.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::zip::ZipArchive;
+use crate::Result;
+
+pub const MODS_DIRECTORY: &str = "mods";
+
+/// Ids the loader or the game itself provide, which mods depend on without
+/// there being a jar for them.
+const LOADER_PROVIDED: &[&str] = &[
+    "minecraft",
+    "java",
+    "fabricloader",
+    "fabric-loader",
+    "forge",
+    "neoforge",
+];
+
+/// The Minecraft versions a mod says it works with.
+#[derive(Clone, Debug)]
+pub enum MinecraftRequirement {
+    /// `fabric.mod.json` predicates. Any entry may match, and each entry is a
+    /// space separated list of comparisons that must all hold.
+    Fabric(Vec<String>),
+    /// A `mods.toml` maven version range like `[1.20.4,1.21)`.
+    Forge(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct ModMetadata {
+    pub file: PathBuf,
+    pub id: String,
+    pub version: String,
+    pub minecraft: Option<MinecraftRequirement>,
+    /// Other ids the jar provides, including mods bundled inside it.
+    pub provides: Vec<String>,
+    /// Ids of the mods it requires to load.
+    pub depends: Vec<String>,
+}
+
+impl ModMetadata {
+    /// Whether the mod declares support for `minecraft_version`. Mods that
+    /// don't say, and versions that can't be compared like snapshots, pass.
+    pub fn supports(&self, minecraft_version: &str) -> bool {
+        if version_parts(minecraft_version).is_empty() {
+            return true;
+        }
+
+        match &self.minecraft {
+            None => true,
+            Some(MinecraftRequirement::Fabric(alternatives)) => alternatives.iter().any(|all| {
+                all.split_whitespace()
+                    .all(|predicate| fabric_predicate_matches(predicate, minecraft_version))
+            }),
+            Some(MinecraftRequirement::Forge(range)) => {
+                maven_range_matches(range, minecraft_version)
+            }
+        }
+    }
+}
+
+/// The leading numeric components of a version, so `1.20.4` is `[1, 20, 4]`
+/// and `1.21-pre1` is `[1, 21]`.
+fn version_parts(version: &str) -> Vec<u64> {
+    version
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
+
+fn compare_versions(a: &[u64], b: &[u64]) -> Ordering {
+    for i in 0..a.len().max(b.len()) {
+        let ordering = a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0));
+
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    Ordering::Equal
+}
+
+/// How the game version `version` compares to `target`. Pre-releases and
+/// release candidates like `1.21-pre1` come before the release they lead up
+/// to, as in both Fabric's and maven's ordering.
+fn compare_game_version(version: &str, target: &[u64]) -> Ordering {
+    let pre_release = match version.contains('-') {
+        true => Ordering::Less,
+        false => Ordering::Equal,
+    };
+
+    compare_versions(&version_parts(version), target).then(pre_release)
+}
+
+fn fabric_predicate_matches(predicate: &str, version: &str) -> bool {
+    if predicate == "*" {
+        return true;
+    }
+
+    let (operator, target) = [">=", "<=", ">", "<", "=", "~", "^"]
+        .iter()
+        .find_map(|operator| {
+            predicate
+                .strip_prefix(operator)
+                .map(|rest| (*operator, rest))
+        })
+        .unwrap_or(("=", predicate));
+
+    let wildcard = target
+        .split('.')
+        .any(|part| matches!(part, "x" | "X" | "*"));
+    let target = version_parts(target);
+
+    if target.is_empty() {
+        // Nothing comparable, e.g. a snapshot id. Don't guess.
+        return true;
+    }
+
+    let ordering = compare_game_version(version, &target);
+    let parts = version_parts(version);
+    let shares_prefix = |length: usize| parts.iter().take(length).eq(target.iter().take(length));
+
+    match operator {
+        ">=" => ordering != Ordering::Less,
+        "<=" => ordering != Ordering::Greater,
+        ">" => ordering == Ordering::Greater,
+        "<" => ordering == Ordering::Less,
+        "~" => ordering != Ordering::Less && shares_prefix(2),
+        "^" => ordering != Ordering::Less && shares_prefix(1),
+        _ if wildcard => shares_prefix(target.len()),
+        _ => ordering == Ordering::Equal,
+    }
+}
+
+fn maven_range_matches(range: &str, version: &str) -> bool {
+    let mut ranges = Vec::new();
+    let mut start = None;
+
+    for (i, character) in range.char_indices() {
+        match character {
+            '[' | '(' => start = Some(i),
+            ']' | ')' => {
+                if let Some(start) = start.take() {
+                    ranges.push(&range[start..=i]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // A bare version is only a recommendation in maven ranges.
+    if ranges.is_empty() {
+        return true;
+    }
+
+    ranges.iter().any(|range| {
+        let inner = &range[1..range.len() - 1];
+
+        match inner.split_once(',') {
+            None => compare_game_version(version, &version_parts(inner.trim())) == Ordering::Equal,
+            Some((lower, upper)) => {
+                let (lower, upper) = (lower.trim(), upper.trim());
+
+                let above_lower = lower.is_empty()
+                    || match compare_game_version(version, &version_parts(lower)) {
+                        Ordering::Greater => true,
+                        Ordering::Equal => range.starts_with('['),
+                        Ordering::Less => false,
+                    };
+                let below_upper = upper.is_empty()
+                    || match compare_game_version(version, &version_parts(upper)) {
+                        Ordering::Less => true,
+                        Ordering::Equal => range.ends_with(']'),
+                        Ordering::Greater => false,
+                    };
+
+                above_lower && below_upper
+            }
+        }
+    })
+}
+
+/// Ids a Fabric mod makes available besides its own: its `provides` and the
+/// mods bundled in it with jar-in-jar, like the modules of fabric-api.
+fn fabric_provides(archive: &ZipArchive, json: &serde_json::Value) -> Vec<String> {
+    let mut provides = json["provides"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|id| id.as_str())
+        .map(String::from)
+        .collect::<Vec<_>>();
+
+    for file in json["jars"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|jar| jar["file"].as_str())
+    {
+        let Some(nested) = archive
+            .read_by_name(file)
+            .ok()
+            .flatten()
+            .and_then(|data| ZipArchive::from_bytes(data).ok())
+        else {
+            continue;
+        };
+        let Some(nested_json) = nested
+            .read_by_name("fabric.mod.json")
+            .ok()
+            .flatten()
+            .and_then(|json| serde_json::from_slice::<serde_json::Value>(&json).ok())
+        else {
+            continue;
+        };
+
+        provides.extend(nested_json["id"].as_str().map(String::from));
+        provides.extend(fabric_provides(&nested, &nested_json));
+    }
+
+    provides
+}
+
+fn read_fabric_metadata(path: &Path, archive: &ZipArchive, json: &[u8]) -> Result<ModMetadata> {
+    let json: serde_json::Value = serde_json::from_slice(json)?;
+
+    let minecraft = match &json["depends"]["minecraft"] {
+        serde_json::Value::String(predicate) => {
+            Some(MinecraftRequirement::Fabric(vec![predicate.clone()]))
+        }
+        serde_json::Value::Array(predicates) => Some(MinecraftRequirement::Fabric(
+            predicates
+                .iter()
+                .filter_map(|predicate| predicate.as_str())
+                .map(String::from)
+                .collect(),
+        )),
+        _ => None,
+    };
+
+    Ok(ModMetadata {
+        file: path.to_owned(),
+        id: json["id"].as_str().unwrap_or_default().to_owned(),
+        version: json["version"].as_str().unwrap_or_default().to_owned(),
+        minecraft,
+        provides: fabric_provides(archive, &json),
+        depends: json["depends"]
+            .as_object()
+            .map(|depends| depends.keys().cloned().collect())
+            .unwrap_or_default(),
+    })
+}
+
+fn read_forge_metadata(path: &Path, toml: &[u8]) -> Result<ModMetadata> {
+    let toml: toml::Value = toml::from_str(&String::from_utf8_lossy(toml))?;
+
+    let mod_table = toml
+        .get("mods")
+        .and_then(|mods| mods.as_array())
+        .and_then(|mods| mods.first());
+    let field = |name: &str| {
+        mod_table
+            .and_then(|table| table.get(name))
+            .and_then(|value| value.as_str())
+            .unwrap_or_default()
+            .to_owned()
+    };
+    let id = field("modId");
+
+    let dependencies = toml
+        .get("dependencies")
+        .and_then(|dependencies| dependencies.get(&id))
+        .and_then(|dependencies| dependencies.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let dependency_id = |dependency: &toml::Value| {
+        dependency
+            .get("modId")
+            .and_then(|id| id.as_str())
+            .map(String::from)
+    };
+
+    // Forge marks required dependencies with `mandatory`, NeoForge with
+    // `type = "required"`.
+    let depends = dependencies
+        .iter()
+        .filter(|dependency| {
+            dependency
+                .get("mandatory")
+                .and_then(|mandatory| mandatory.as_bool())
+                == Some(true)
+                || dependency.get("type").and_then(|kind| kind.as_str()) == Some("required")
+        })
+        .filter_map(dependency_id)
+        .collect();
+
+    let minecraft = dependencies
+        .iter()
+        .find(|dependency| dependency_id(dependency).as_deref() == Some("minecraft"))
+        .and_then(|dependency| dependency.get("versionRange"))
+        .and_then(|range| range.as_str())
+        .map(|range| MinecraftRequirement::Forge(range.to_owned()));
+
+    Ok(ModMetadata {
+        file: path.to_owned(),
+        version: field("version"),
+        id,
+        minecraft,
+        provides: Vec::new(),
+        depends,
+    })
+}
+
+/// Reads the metadata of a mod jar. Returns `None` for jars that aren't a
+/// Fabric, Forge or NeoForge mod.
+pub fn read_mod(path: &Path) -> Result<Option<ModMetadata>> {
+    let archive = ZipArchive::open(path)?;
+
+    if let Some(json) = archive.read_by_name("fabric.mod.json")? {
+        return read_fabric_metadata(path, &archive, &json).map(Some);
+    }
+
+    for name in ["META-INF/neoforge.mods.toml", "META-INF/mods.toml"] {
+        if let Some(toml) = archive.read_by_name(name)? {
+            return read_forge_metadata(path, &toml).map(Some);
+        }
+    }
+
+    Ok(None)
+}
+
+/// Metadata of every mod jar in the instance. Jars that can't be read are
+/// reported and skipped.
+pub fn installed_mods(instance_directory: &Path) -> Vec<ModMetadata> {
+    let Ok(entries) = fs::read_dir(instance_directory.join(MODS_DIRECTORY)) else {
+        return Vec::new();
+    };
+
+    let mut paths = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "jar"))
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    paths
+        .iter()
+        .filter_map(|path| match read_mod(path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                eprintln!("warning: could not read {}: {}", path.display(), e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Something about the installed mods that usually crashes the game on
+/// startup.
+#[derive(Clone, Debug)]
+pub enum ModConflict {
+    /// Several jars are the same mod, with the version each has.
+    Duplicate {
+        id: String,
+        copies: Vec<(PathBuf, String)>,
+    },
+    /// A mod requires one that isn't installed.
+    MissingDependency {
+        file: PathBuf,
+        id: String,
+        dependency: String,
+    },
+}
+
+impl fmt::Display for ModConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ModConflict::Duplicate { id, copies } => {
+                let copies = copies
+                    .iter()
+                    .map(|(file, version)| format!("{} ({})", file.display(), version))
+                    .collect::<Vec<_>>();
+                write!(
+                    f,
+                    "{} is installed {} times: {}",
+                    id,
+                    copies.len(),
+                    copies.join(", ")
+                )
+            }
+            ModConflict::MissingDependency {
+                file,
+                id,
+                dependency,
+            } => write!(
+                f,
+                "{} ({}) requires {}, which isn't installed",
+                id,
+                file.display(),
+                dependency
+            ),
+        }
+    }
+}
+
+/// Duplicate mods and missing dependencies among `mods`.
+pub fn find_mod_conflicts(mods: &[ModMetadata]) -> Vec<ModConflict> {
+    // Jars that don't name themselves can't be told apart, so they're never
+    // each other's duplicates.
+    let mut by_id = BTreeMap::<&str, Vec<&ModMetadata>>::new();
+    for installed in mods.iter().filter(|installed| !installed.id.is_empty()) {
+        by_id.entry(&installed.id).or_default().push(installed);
+    }
+
+    let provided = mods
+        .iter()
+        .flat_map(|installed| std::iter::once(&installed.id).chain(&installed.provides))
+        .map(String::as_str)
+        .chain(LOADER_PROVIDED.iter().copied())
+        .collect::<HashSet<_>>();
+
+    let duplicates = by_id
+        .into_iter()
+        .filter(|(_, copies)| copies.len() > 1)
+        .map(|(id, copies)| ModConflict::Duplicate {
+            id: id.to_owned(),
+            copies: copies
+                .iter()
+                .map(|installed| (installed.file.clone(), installed.version.clone()))
+                .collect(),
+        });
+
+    let missing = mods.iter().flat_map(|installed| {
+        installed
+            .depends
+            .iter()
+            .filter(|dependency| !provided.contains(dependency.as_str()))
+            .map(|dependency| ModConflict::MissingDependency {
+                file: installed.file.clone(),
+                id: installed.id.clone(),
+                dependency: dependency.clone(),
+            })
+    });
+
+    duplicates.chain(missing).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zip::ZipWriter;
+
+    fn installed(file: &str, id: &str, provides: &[&str], depends: &[&str]) -> ModMetadata {
+        ModMetadata {
+            file: PathBuf::from(file),
+            id: id.to_owned(),
+            version: String::from("1.0"),
+            minecraft: None,
+            provides: provides.iter().map(|id| id.to_string()).collect(),
+            depends: depends.iter().map(|id| id.to_string()).collect(),
+        }
+    }
+
+    fn conflicts(mods: &[ModMetadata]) -> Vec<String> {
+        find_mod_conflicts(mods)
+            .iter()
+            .map(ModConflict::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn the_same_id_twice_is_a_duplicate() {
+        let mods = [
+            installed("sodium-0.5.jar", "sodium", &[], &[]),
+            installed("lithium.jar", "lithium", &[], &[]),
+            installed("sodium-0.6.jar", "sodium", &[], &[]),
+        ];
+
+        assert_eq!(
+            conflicts(&mods),
+            ["sodium is installed 2 times: sodium-0.5.jar (1.0), sodium-0.6.jar (1.0)"]
+        );
+    }
+
+    #[test]
+    fn jars_without_an_id_are_not_duplicates() {
+        let mods = [
+            installed("a.jar", "", &[], &[]),
+            installed("b.jar", "", &[], &[]),
+        ];
+
+        assert!(conflicts(&mods).is_empty());
+    }
+
+    #[test]
+    fn bundled_jars_are_provided() {
+        let mut nested = ZipWriter::default();
+        nested
+            .add(
+                "fabric.mod.json",
+                br#"{ "id": "fabric-rendering-v1", "provides": ["fabric-renderer"] }"#,
+            )
+            .unwrap();
+        let mut outer = ZipWriter::default();
+        outer
+            .add(
+                "META-INF/jars/fabric-rendering-v1.jar",
+                &nested.finish().unwrap(),
+            )
+            .unwrap();
+        let archive = ZipArchive::from_bytes(outer.finish().unwrap()).unwrap();
+
+        let json = serde_json::json!({
+            "id": "fabric-api",
+            "provides": ["fabric"],
+            "jars": [{ "file": "META-INF/jars/fabric-rendering-v1.jar" }]
+        });
+
+        assert_eq!(
+            fabric_provides(&archive, &json),
+            ["fabric", "fabric-rendering-v1", "fabric-renderer"]
+        );
+    }
+
+    #[test]
+    fn dependencies_can_be_provided_by_bundles_and_the_loader() {
+        let mods = [
+            installed(
+                "fabric-api.jar",
+                "fabric-api",
+                &["fabric-rendering-v1"],
+                &[],
+            ),
+            installed(
+                "sodium.jar",
+                "sodium",
+                &[],
+                &[
+                    "minecraft",
+                    "fabricloader",
+                    "java",
+                    "fabric-rendering-v1",
+                    "indium",
+                ],
+            ),
+        ];
+
+        assert_eq!(
+            conflicts(&mods),
+            ["sodium (sodium.jar) requires indium, which isn't installed"]
+        );
+    }
+
+    fn requiring(minecraft: MinecraftRequirement) -> ModMetadata {
+        ModMetadata {
+            file: PathBuf::from("mod.jar"),
+            id: String::from("mod"),
+            version: String::from("1.0"),
+            minecraft: Some(minecraft),
+            provides: Vec::new(),
+            depends: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn fabric_predicates() {
+        let table: &[(&str, &[&str], &[&str])] = &[
+            (
+                ">=1.20 <1.21",
+                &["1.20", "1.20.4", "1.21-pre1"],
+                &["1.19.4", "1.21", "1.21.1"],
+            ),
+            (
+                "~1.20.1",
+                &["1.20.1", "1.20.6"],
+                &["1.20", "1.20.1-rc1", "1.21"],
+            ),
+            ("^1", &["1.0", "1.20.4"], &["0.9", "2.0"]),
+            ("1.20.x", &["1.20", "1.20.4"], &["1.19.4", "1.21"]),
+            ("*", &["1.0", "1.20.4", "1.21-pre1"], &[]),
+            ("1.20.4", &["1.20.4"], &["1.20.3", "1.20.4-rc1", "1.20.5"]),
+        ];
+
+        for (predicate, matching, other) in table {
+            let mod_metadata = requiring(MinecraftRequirement::Fabric(vec![predicate.to_string()]));
+            for version in *matching {
+                assert!(mod_metadata.supports(version), "{} {}", predicate, version);
+            }
+            for version in *other {
+                assert!(!mod_metadata.supports(version), "{} {}", predicate, version);
+            }
+        }
+    }
+
+    #[test]
+    fn maven_ranges() {
+        let table: &[(&str, &[&str], &[&str])] = &[
+            (
+                "[1.20.4,1.21)",
+                &["1.20.4", "1.20.6", "1.21-pre1"],
+                &["1.20.3", "1.21"],
+            ),
+            ("(,1.20]", &["1.19.4", "1.20"], &["1.20.1"]),
+            ("[1.20.4]", &["1.20.4"], &["1.20.3", "1.20.5"]),
+            (
+                "[1.19,1.20),[1.20.2,)",
+                &["1.19", "1.19.4", "1.20.2", "1.21"],
+                &["1.18.2", "1.20", "1.20.1"],
+            ),
+            // A bare version only recommends one.
+            ("1.20.4", &["1.20.1", "1.21"], &[]),
+        ];
+
+        for (range, matching, other) in table {
+            let mod_metadata = requiring(MinecraftRequirement::Forge(range.to_string()));
+            for version in *matching {
+                assert!(mod_metadata.supports(version), "{} {}", range, version);
+            }
+            for version in *other {
+                assert!(!mod_metadata.supports(version), "{} {}", range, version);
+            }
+        }
+    }
+
+    #[test]
+    fn snapshots_pass_every_requirement() {
+        for requirement in [
+            MinecraftRequirement::Fabric(vec![String::from("1.20.4")]),
+            MinecraftRequirement::Forge(String::from("[1.20.4]")),
+        ] {
+            assert!(requiring(requirement).supports("24w14a"));
+        }
+
+        // A predicate on a snapshot can't be compared either.
+        let snapshot = requiring(MinecraftRequirement::Fabric(vec![String::from(">=24w14a")]));
+        assert!(snapshot.supports("1.20.4"));
+    }
+}
@@ -0,0 +1,259 @@
+//! Uncompressed NBT, the format of servers.dat.
+
+use crate::{Error, Result};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    /// Elements all have the type of the first. An empty list is written as
+    /// a list of `TAG_End`.
+    List(Vec<Tag>),
+    /// Entries keep their order so files round-trip unchanged.
+    Compound(Vec<(String, Tag)>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+    fn id(&self) -> u8 {
+        match self {
+            Tag::Byte(_) => 1,
+            Tag::Short(_) => 2,
+            Tag::Int(_) => 3,
+            Tag::Long(_) => 4,
+            Tag::Float(_) => 5,
+            Tag::Double(_) => 6,
+            Tag::ByteArray(_) => 7,
+            Tag::String(_) => 8,
+            Tag::List(_) => 9,
+            Tag::Compound(_) => 10,
+            Tag::IntArray(_) => 11,
+            Tag::LongArray(_) => 12,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(entries) => entries
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, tag)| tag),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Tag> {
+        match self {
+            Tag::Compound(entries) => entries
+                .iter_mut()
+                .find(|(key, _)| key == name)
+                .map(|(_, tag)| tag),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(string) => Some(string),
+            _ => None,
+        }
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn bytes(&mut self, count: usize) -> Result<&[u8]> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + count)
+            .ok_or_else(|| Error::InvalidNbt(String::from("unexpected end of data")))?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    fn length(&mut self) -> Result<usize> {
+        let length = i32::from_be_bytes(self.array()?);
+        usize::try_from(length)
+            .map_err(|_| Error::InvalidNbt(format!("negative length {}", length)))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let length = u16::from_be_bytes(self.array()?) as usize;
+        Ok(String::from_utf8_lossy(self.bytes(length)?).into_owned())
+    }
+
+    fn payload(&mut self, id: u8, depth: usize) -> Result<Tag> {
+        if depth > 512 {
+            return Err(Error::InvalidNbt(String::from("nested too deeply")));
+        }
+
+        Ok(match id {
+            1 => Tag::Byte(i8::from_be_bytes(self.array()?)),
+            2 => Tag::Short(i16::from_be_bytes(self.array()?)),
+            3 => Tag::Int(i32::from_be_bytes(self.array()?)),
+            4 => Tag::Long(i64::from_be_bytes(self.array()?)),
+            5 => Tag::Float(f32::from_be_bytes(self.array()?)),
+            6 => Tag::Double(f64::from_be_bytes(self.array()?)),
+            7 => {
+                let length = self.length()?;
+                Tag::ByteArray(self.bytes(length)?.iter().map(|&b| b as i8).collect())
+            }
+            8 => Tag::String(self.string()?),
+            9 => {
+                let element_id = self.array::<1>()?[0];
+                let length = self.length()?;
+                let mut elements = Vec::new();
+                for _ in 0..length {
+                    elements.push(self.payload(element_id, depth + 1)?);
+                }
+                Tag::List(elements)
+            }
+            10 => {
+                let mut entries = Vec::new();
+                loop {
+                    let entry_id = self.array::<1>()?[0];
+                    if entry_id == 0 {
+                        break;
+                    }
+                    let name = self.string()?;
+                    entries.push((name, self.payload(entry_id, depth + 1)?));
+                }
+                Tag::Compound(entries)
+            }
+            11 => {
+                let length = self.length()?;
+                let mut values = Vec::new();
+                for _ in 0..length {
+                    values.push(i32::from_be_bytes(self.array()?));
+                }
+                Tag::IntArray(values)
+            }
+            12 => {
+                let length = self.length()?;
+                let mut values = Vec::new();
+                for _ in 0..length {
+                    values.push(i64::from_be_bytes(self.array()?));
+                }
+                Tag::LongArray(values)
+            }
+            id => return Err(Error::InvalidNbt(format!("unknown tag type {}", id))),
+        })
+    }
+}
+
+/// Reads a named root tag, returning its name and value.
+pub fn read(data: &[u8]) -> Result<(String, Tag)> {
+    let mut reader = Reader { data, position: 0 };
+    let id = reader.array::<1>()?[0];
+    let name = reader.string()?;
+
+    Ok((name, reader.payload(id, 0)?))
+}
+
+fn write_string(output: &mut Vec<u8>, string: &str) {
+    output.extend_from_slice(&(string.len() as u16).to_be_bytes());
+    output.extend_from_slice(string.as_bytes());
+}
+
+fn write_payload(output: &mut Vec<u8>, tag: &Tag) {
+    match tag {
+        Tag::Byte(value) => output.extend_from_slice(&value.to_be_bytes()),
+        Tag::Short(value) => output.extend_from_slice(&value.to_be_bytes()),
+        Tag::Int(value) => output.extend_from_slice(&value.to_be_bytes()),
+        Tag::Long(value) => output.extend_from_slice(&value.to_be_bytes()),
+        Tag::Float(value) => output.extend_from_slice(&value.to_be_bytes()),
+        Tag::Double(value) => output.extend_from_slice(&value.to_be_bytes()),
+        Tag::ByteArray(values) => {
+            output.extend_from_slice(&(values.len() as i32).to_be_bytes());
+            output.extend(values.iter().map(|&b| b as u8));
+        }
+        Tag::String(string) => write_string(output, string),
+        Tag::List(elements) => {
+            output.push(elements.first().map_or(0, Tag::id));
+            output.extend_from_slice(&(elements.len() as i32).to_be_bytes());
+            for element in elements {
+                write_payload(output, element);
+            }
+        }
+        Tag::Compound(entries) => {
+            for (name, tag) in entries {
+                output.push(tag.id());
+                write_string(output, name);
+                write_payload(output, tag);
+            }
+            output.push(0);
+        }
+        Tag::IntArray(values) => {
+            output.extend_from_slice(&(values.len() as i32).to_be_bytes());
+            for value in values {
+                output.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+        Tag::LongArray(values) => {
+            output.extend_from_slice(&(values.len() as i32).to_be_bytes());
+            for value in values {
+                output.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+    }
+}
+
+/// Writes `tag` as a named root tag.
+pub fn write(name: &str, tag: &Tag) -> Vec<u8> {
+    let mut output = vec![tag.id()];
+    write_string(&mut output, name);
+    write_payload(&mut output, tag);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_lists() {
+        let root = Tag::Compound(vec![
+            (String::from("empty"), Tag::List(Vec::new())),
+            (
+                String::from("servers"),
+                Tag::List(vec![
+                    Tag::Compound(vec![
+                        (String::from("name"), Tag::String(String::from("Home"))),
+                        (String::from("ip"), Tag::String(String::from("127.0.0.1"))),
+                    ]),
+                    Tag::Compound(vec![
+                        (String::from("name"), Tag::String(String::from("Friends"))),
+                        (
+                            String::from("ip"),
+                            Tag::String(String::from("mc.example.com")),
+                        ),
+                        (String::from("acceptTextures"), Tag::Byte(1)),
+                    ]),
+                ]),
+            ),
+            (
+                String::from("numbers"),
+                Tag::List(vec![Tag::Int(1), Tag::Int(-2), Tag::Int(3)]),
+            ),
+        ]);
+
+        let data = write("", &root);
+
+        assert_eq!(read(&data).unwrap(), (String::new(), root));
+    }
+}
@@ -0,0 +1,167 @@
+//! Telling other tools when the game starts and exits, through the config's
+//! `on_launch` and `on_exit` commands and a notify URL that gets each event
+//! posted to it as JSON.
+
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Duration;
+
+use crate::config::LauncherConfig;
+use crate::download::http_client;
+use crate::Result;
+
+/// How long the notify URL gets to answer before the event is given up on.
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GameEventKind {
+    Launch,
+    Exit,
+}
+
+impl GameEventKind {
+    fn name(self) -> &'static str {
+        match self {
+            GameEventKind::Launch => "launch",
+            GameEventKind::Exit => "exit",
+        }
+    }
+}
+
+/// What happened to the game, as posted to the notify URL.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct GameEvent {
+    pub event: GameEventKind,
+    pub instance: PathBuf,
+    pub pid: u32,
+    /// The game's exit code, `None` when a signal killed it or it hasn't
+    /// exited yet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// Seconds from the launch to the exit.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_secs: Option<u64>,
+}
+
+impl GameEvent {
+    pub fn launched(instance_directory: &Path, pid: u32) -> Self {
+        Self {
+            event: GameEventKind::Launch,
+            instance: instance_directory.to_owned(),
+            pid,
+            exit_code: None,
+            duration_secs: None,
+        }
+    }
+
+    pub fn exited(
+        instance_directory: &Path,
+        pid: u32,
+        exit_code: Option<i32>,
+        duration: Duration,
+    ) -> Self {
+        Self {
+            event: GameEventKind::Exit,
+            instance: instance_directory.to_owned(),
+            pid,
+            exit_code,
+            duration_secs: Some(duration.as_secs()),
+        }
+    }
+
+    /// The event as the environment of the hook commands.
+    fn environment(&self) -> Vec<(&'static str, String)> {
+        let mut environment = vec![
+            ("BLAZINGLYASSMC_EVENT", self.event.name().to_owned()),
+            (
+                "BLAZINGLYASSMC_INSTANCE",
+                self.instance.display().to_string(),
+            ),
+            ("BLAZINGLYASSMC_PID", self.pid.to_string()),
+        ];
+        if let Some(exit_code) = self.exit_code {
+            environment.push(("BLAZINGLYASSMC_EXIT_CODE", exit_code.to_string()));
+        }
+        if let Some(duration_secs) = self.duration_secs {
+            environment.push(("BLAZINGLYASSMC_DURATION_SECS", duration_secs.to_string()));
+        }
+        environment
+    }
+}
+
+/// Where a launch's [`GameEvent`]s go.
+#[derive(Debug)]
+pub struct GameHooks {
+    on_launch: Vec<String>,
+    on_exit: Vec<String>,
+    notify_url: Option<String>,
+    client: Option<reqwest::Client>,
+}
+
+impl GameHooks {
+    /// The config's hooks, with `notify_url` replacing its `notify_url`.
+    pub fn new(config: &LauncherConfig, notify_url: Option<String>) -> Result<Self> {
+        let notify_url = notify_url.or_else(|| config.notify_url.clone());
+        let client = notify_url.as_ref().map(|_| http_client()).transpose()?;
+
+        Ok(Self {
+            on_launch: config.on_launch.clone(),
+            on_exit: config.on_exit.clone(),
+            notify_url,
+            client,
+        })
+    }
+
+    /// Whether anything wants the exit event, which means staying attached
+    /// to the game until it exits.
+    pub fn watch_exit(&self) -> bool {
+        !self.on_exit.is_empty() || self.notify_url.is_some()
+    }
+
+    /// Runs the event's hook command and posts it to the notify URL. Neither
+    /// failing stops the game, so they're only warned about. The launch hook
+    /// runs alongside the game, the exit hook is waited for.
+    pub async fn notify(&self, event: &GameEvent) {
+        let hook = match event.event {
+            GameEventKind::Launch => &self.on_launch,
+            GameEventKind::Exit => &self.on_exit,
+        };
+
+        if let Some((program, arguments)) = hook.split_first() {
+            let mut command = process::Command::new(program);
+            command.args(arguments).envs(event.environment());
+
+            let result = match event.event {
+                GameEventKind::Launch => command.spawn().map(|_| ()),
+                GameEventKind::Exit => command.status().map(|_| ()),
+            };
+            if let Err(e) = result {
+                eprintln!(
+                    "warning: could not run the {} hook {}: {}",
+                    event.event.name(),
+                    program,
+                    e
+                );
+            }
+        }
+
+        if let (Some(url), Some(client)) = (&self.notify_url, &self.client) {
+            let response = client
+                .post(url)
+                .json(event)
+                .timeout(NOTIFY_TIMEOUT)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            if let Err(e) = response {
+                eprintln!(
+                    "warning: could not post the {} event to {}: {}",
+                    event.event.name(),
+                    url,
+                    e
+                );
+            }
+        }
+    }
+}
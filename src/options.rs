@@ -0,0 +1,57 @@
+//! The game's options.txt, a `key:value` line per option.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::config::LauncherConfig;
+use crate::error::PathContext;
+use crate::Result;
+
+pub const OPTIONS_FILE_NAME: &str = "options.txt";
+
+/// Sets `options` in the instance's options.txt, keeping the lines of any
+/// other options already there.
+pub fn merge_options(instance_directory: &Path, options: &BTreeMap<String, String>) -> Result<()> {
+    if options.is_empty() {
+        return Ok(());
+    }
+
+    let options_path = instance_directory.join(OPTIONS_FILE_NAME);
+    let existing = fs::read_to_string(&options_path).unwrap_or_default();
+
+    let mut lines = existing
+        .lines()
+        .filter(|line| {
+            line.split_once(':')
+                .is_none_or(|(key, _)| !options.contains_key(key))
+        })
+        .map(String::from)
+        .collect::<Vec<_>>();
+    lines.extend(
+        options
+            .iter()
+            .map(|(key, value)| format!("{}:{}", key, value)),
+    );
+
+    fs::write(&options_path, lines.join("\n") + "\n").with_path(&options_path)?;
+
+    Ok(())
+}
+
+/// Writes the config's defaults for the game's own options into a new
+/// options.txt. Once the game has written one it's the player's, so an
+/// existing file is never touched.
+pub fn seed_options(instance_directory: &Path, config: &LauncherConfig) -> Result<()> {
+    if instance_directory.join(OPTIONS_FILE_NAME).exists() {
+        return Ok(());
+    }
+
+    let mut options = BTreeMap::new();
+
+    if let Some(gui_scale) = config.gui_scale {
+        options.insert(String::from("guiScale"), gui_scale.to_string());
+    }
+
+    merge_options(instance_directory, &options)
+}
@@ -0,0 +1,23 @@
+use std::env;
+use std::path::PathBuf;
+
+/// Directory for launcher-wide state shared by every instance, such as the
+/// account store. `BLAZINGLYASSMC_HOME` overrides the platform default.
+pub fn data_directory() -> PathBuf {
+    if let Some(home) = env::var_os("BLAZINGLYASSMC_HOME") {
+        return PathBuf::from(home);
+    }
+
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+    };
+
+    base.unwrap_or_else(|| PathBuf::from("."))
+        .join(env!("CARGO_PKG_NAME"))
+}
@@ -0,0 +1,74 @@
+//! Spotting operating systems too old for the version being launched, from
+//! the Java it needs.
+
+use std::env;
+use std::process::Command;
+
+/// The Java major version a meta asks for, 8 for metas from before
+/// `javaVersion` existed.
+fn required_java(meta: &serde_json::Value) -> u64 {
+    meta["javaVersion"]["majorVersion"].as_u64().unwrap_or(8)
+}
+
+/// Whether the OS is 32-bit. A 32-bit launcher on 64-bit Windows runs under
+/// WOW64, which says so in `PROCESSOR_ARCHITEW6432`.
+fn is_32_bit_os() -> bool {
+    cfg!(target_pointer_width = "32") && env::var_os("PROCESSOR_ARCHITEW6432").is_none()
+}
+
+/// The OS version as `(major, minor)`, when it's cheap to find out: from
+/// `ver` on Windows and `sw_vers` on macOS.
+fn os_version() -> Option<(u32, u32)> {
+    let output = if cfg!(windows) {
+        Command::new("cmd").args(["/c", "ver"]).output().ok()?
+    } else if cfg!(target_os = "macos") {
+        Command::new("sw_vers")
+            .arg("-productVersion")
+            .output()
+            .ok()?
+    } else {
+        return None;
+    };
+
+    // "Microsoft Windows [Version 10.0.19045.4291]" or "14.4.1".
+    let output = String::from_utf8_lossy(&output.stdout);
+    let version = output
+        .split(|c: char| !c.is_ascii_digit() && c != '.')
+        .find(|part| part.contains('.'))?;
+    let mut parts = version.split('.').map(|part| part.parse().ok());
+
+    Some((parts.next()??, parts.next()??))
+}
+
+/// Reasons the game likely won't run here, judged by the Java the meta
+/// needs: Java 21 (1.20.5 and later) has no 32-bit builds and wants Windows
+/// 10, and Java 17 (1.18 and later) wants macOS 10.14 Mojave. OSes this
+/// can't tell about get no problems.
+pub fn os_problems(meta: &serde_json::Value) -> Vec<String> {
+    let java = required_java(meta);
+    let mut problems = Vec::new();
+
+    if java >= 21 && is_32_bit_os() {
+        problems.push(format!(
+            "this version needs Java {}, which doesn't run on a 32-bit OS",
+            java
+        ));
+    }
+
+    let minimum = match java {
+        21.. if cfg!(windows) => Some(((10, 0), "Windows 10")),
+        17.. if cfg!(target_os = "macos") => Some(((10, 14), "macOS 10.14")),
+        _ => None,
+    };
+
+    if let Some((minimum, name)) = minimum {
+        if os_version().is_some_and(|version| version < minimum) {
+            problems.push(format!(
+                "this version needs Java {}, which needs {} or newer",
+                java, name
+            ));
+        }
+    }
+
+    problems
+}
@@ -0,0 +1,143 @@
+//! Sharing an instance as a single zip: what the player set up, plus the
+//! lock to reinstall everything else from.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{load_config, save_config, CONFIG_FILE_NAME};
+use crate::error::PathContext;
+use crate::fsutil::{stays_inside, write_atomic};
+use crate::lock::{InstanceLock, LOCK_FILE_NAME};
+use crate::meta::META_FILE_NAME;
+use crate::mods::MODS_DIRECTORY;
+use crate::options::OPTIONS_FILE_NAME;
+use crate::template::RESOURCE_PACKS_DIRECTORY;
+use crate::zip::{ZipArchive, ZipWriter};
+use crate::{install_version_with, Error, InstallOptions, Instance, Result};
+
+/// Files exported from the top of the instance.
+const EXPORTED_FILES: [&str; 3] = [CONFIG_FILE_NAME, OPTIONS_FILE_NAME, LOCK_FILE_NAME];
+
+/// Directories exported whole. `config` is where mods keep their settings.
+const EXPORTED_DIRECTORIES: [&str; 3] = [MODS_DIRECTORY, RESOURCE_PACKS_DIRECTORY, "config"];
+
+fn add_directory(zip: &mut ZipWriter, root: &Path, directory: &Path) -> Result<usize> {
+    let mut added = 0;
+
+    for entry in fs::read_dir(directory).with_path(directory)? {
+        let path = entry.with_path(directory)?.path();
+
+        if path.is_dir() {
+            added += add_directory(zip, root, &path)?;
+        } else {
+            let name = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            zip.add(&name, &fs::read(&path).with_path(&path)?)?;
+            added += 1;
+        }
+    }
+
+    Ok(added)
+}
+
+/// Writes the instance's config, options, lock, mods, resource packs
+/// and mod configs to `archive_path`. Assets, libraries and saves stay
+/// behind. Returns how many files went in.
+pub fn export_instance(instance_directory: &Path, archive_path: &Path) -> Result<usize> {
+    if InstanceLock::load(instance_directory)?.is_none() {
+        return Err(Error::MissingLock(instance_directory.to_owned()));
+    }
+
+    let mut zip = ZipWriter::default();
+    let mut added = 0;
+
+    for file in EXPORTED_FILES {
+        let path = instance_directory.join(file);
+
+        if path.is_file() {
+            zip.add(file, &fs::read(&path).with_path(&path)?)?;
+            added += 1;
+        }
+    }
+
+    for directory in EXPORTED_DIRECTORIES {
+        let path = instance_directory.join(directory);
+
+        if path.is_dir() {
+            added += add_directory(&mut zip, instance_directory, &path)?;
+        }
+    }
+
+    write_atomic(archive_path, zip.finish()?)?;
+
+    Ok(added)
+}
+
+/// Unpacks an exported instance into `target_dir` and installs the version
+/// its lock pins, fetching the meta again from the lock's URL rather than
+/// trusting the exported one.
+///
+/// The config's [`command_fields`](crate::LauncherConfig::command_fields)
+/// are handed to `keep_commands`, and cleared unless it returns true.
+pub async fn import_instance(
+    archive_path: &Path,
+    target_dir: &Path,
+    keep_commands: impl FnOnce(&[&str]) -> bool,
+) -> Result<Instance> {
+    let zip = ZipArchive::open(archive_path)?;
+
+    for entry in zip.entries().iter().filter(|entry| !entry.is_dir()) {
+        let relative = PathBuf::from(&entry.name);
+
+        if !stays_inside(&entry.name) {
+            eprintln!("warning: skipping {} from outside the instance", entry.name);
+            continue;
+        }
+        // Archives from before the meta was left out still carry it.
+        if entry.name == META_FILE_NAME {
+            continue;
+        }
+
+        let path = target_dir.join(relative);
+        let parent = path.parent().unwrap_or(target_dir);
+        fs::create_dir_all(parent).with_path(parent)?;
+        fs::write(&path, zip.read(entry)?).with_path(&path)?;
+    }
+
+    let lock = InstanceLock::load(target_dir)?
+        .ok_or_else(|| Error::MissingLock(archive_path.to_owned()))?;
+    let mut config = load_config(target_dir)?;
+
+    let commands = config.command_fields();
+    if !commands.is_empty() && !keep_commands(&commands) {
+        eprintln!(
+            "warning: cleared {} from the imported config",
+            commands.join(", ")
+        );
+        config.clear_commands();
+        save_config(target_dir, &config)?;
+    }
+
+    let instance = install_version_with(
+        &lock.meta_url,
+        target_dir,
+        &config,
+        &InstallOptions::default(),
+    )
+    .await?;
+
+    if InstanceLock::load(target_dir)?
+        .is_some_and(|installed| installed.meta_sha1 != lock.meta_sha1)
+    {
+        eprintln!(
+            "warning: {} was exported with a different meta than {} serves now",
+            archive_path.display(),
+            lock.meta_url
+        );
+    }
+
+    Ok(instance)
+}
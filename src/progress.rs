@@ -0,0 +1,278 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::status;
+
+const REDRAW_INTERVAL: Duration = Duration::from_millis(250);
+// Interval used when the output isn't a terminal and lines can't be redrawn.
+const LOG_INTERVAL: Duration = Duration::from_secs(5);
+// The speed shown is averaged over this window, so the ETA follows the
+// current rate rather than the whole run's.
+const RATE_WINDOW: Duration = Duration::from_secs(5);
+
+/// Byte accounting for one download phase, shared between the tasks doing
+/// the downloading and the task drawing the progress line.
+pub struct Progress {
+    label: String,
+    // Only the combined total changes, as its phases start.
+    total: AtomicU64,
+    done: AtomicU64,
+    finished: AtomicBool,
+    /// The combined total this phase counts towards, if there is one.
+    overall: Option<Arc<Progress>>,
+    /// Drawn only as part of the combined total.
+    hidden: bool,
+}
+
+struct Combined {
+    progress: Arc<Progress>,
+    /// The phases it covers that haven't started yet, with the size the
+    /// meta gives for each.
+    estimates: BTreeMap<String, u64>,
+    verbose: bool,
+}
+
+// One create runs at a time, so one combined total covers the process.
+static COMBINED: Mutex<Option<Combined>> = Mutex::new(None);
+
+impl Progress {
+    pub fn new(label: &str, total: u64) -> Arc<Self> {
+        let (overall, hidden) = match join_combined(label, total) {
+            Some((overall, verbose)) => (Some(overall), !verbose),
+            None => (None, false),
+        };
+
+        Arc::new(Self {
+            label: label.to_owned(),
+            total: AtomicU64::new(total),
+            done: AtomicU64::new(0),
+            finished: AtomicBool::new(false),
+            overall,
+            hidden,
+        })
+    }
+
+    pub fn add(&self, bytes: u64) {
+        self.done.fetch_add(bytes, Ordering::Relaxed);
+        if let Some(overall) = &self.overall {
+            overall.add(bytes);
+        }
+    }
+
+    /// Takes back bytes from a transfer that failed and will be retried.
+    pub fn sub(&self, bytes: u64) {
+        self.done.fetch_sub(bytes, Ordering::Relaxed);
+        if let Some(overall) = &self.overall {
+            overall.sub(bytes);
+        }
+    }
+
+    pub fn done(&self) -> u64 {
+        self.done.load(Ordering::Relaxed)
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+}
+
+fn mib(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+fn percent(progress: &Progress) -> u64 {
+    (progress.done() * 100 / progress.total().max(1)).min(100)
+}
+
+fn render(progress: &Progress, rate: f64) -> String {
+    let done = progress.done();
+    let total = progress.total();
+
+    let eta = if rate > 0.0 {
+        let seconds = (total.saturating_sub(done) as f64 / rate) as u64;
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    } else {
+        String::from("--:--")
+    };
+
+    let mut line = format!(
+        "{} {}% {:.1}/{:.1} MiB {:.1} MiB/s ETA {}",
+        progress.label,
+        percent(progress),
+        mib(done),
+        mib(total),
+        mib(rate as u64),
+        eta
+    );
+    if let Some(overall) = &progress.overall {
+        line.push_str(&format!(" ({}% of the install)", percent(overall)));
+    }
+    line
+}
+
+/// Makes the phase `label` count towards the combined total, when there is
+/// one covering it, with `total` replacing the meta's size for the phase.
+/// Returns the combined total and whether phases are drawn as well.
+fn join_combined(label: &str, total: u64) -> Option<(Arc<Progress>, bool)> {
+    let mut combined = COMBINED.lock().ok()?;
+    let combined = combined.as_mut()?;
+    let estimate = combined.estimates.remove(label)?;
+
+    // What's already on disk drops out of the total rather than counting as
+    // done, the same as in the phase's own line.
+    combined.progress.total.fetch_add(total, Ordering::Relaxed);
+    combined
+        .progress
+        .total
+        .fetch_sub(estimate, Ordering::Relaxed);
+
+    Some((combined.progress.clone(), combined.verbose))
+}
+
+/// One progress total over several download phases, drawn instead of a line
+/// per phase, until it's finished or dropped.
+pub struct CombinedProgress {
+    display: Option<ProgressDisplay>,
+}
+
+impl CombinedProgress {
+    /// Starts a total over the phases in `estimates`, the labels their
+    /// [`Progress`] will have with the size the meta gives for each. With
+    /// `verbose` the phases keep their own lines, each followed by the total,
+    /// and the total isn't drawn by itself.
+    pub fn start(estimates: BTreeMap<String, u64>, verbose: bool) -> Self {
+        let progress = Progress::new("total", estimates.values().sum());
+
+        if let Ok(mut combined) = COMBINED.lock() {
+            *combined = Some(Combined {
+                progress: progress.clone(),
+                estimates,
+                verbose,
+            });
+        }
+
+        Self {
+            display: (!verbose).then(|| ProgressDisplay::start(progress)),
+        }
+    }
+
+    /// Draws the final total and stops redrawing.
+    pub async fn finish(mut self) {
+        if let Some(display) = self.display.take() {
+            display.finish().await;
+        }
+    }
+}
+
+impl Drop for CombinedProgress {
+    fn drop(&mut self) {
+        if let Ok(mut combined) = COMBINED.lock() {
+            *combined = None;
+        }
+    }
+}
+
+pub struct ProgressDisplay {
+    progress: Arc<Progress>,
+    /// `None` for a phase drawn only as part of the combined total.
+    handle: Option<tokio::task::JoinHandle<()>>,
+    interactive: bool,
+}
+
+impl ProgressDisplay {
+    /// Starts drawing `progress` until [`ProgressDisplay::finish`] is called.
+    pub fn start(progress: Arc<Progress>) -> Self {
+        let interactive = io::stderr().is_terminal();
+        let shown = progress.clone();
+        status::set_progress(progress.clone());
+
+        if progress.hidden {
+            return Self {
+                progress,
+                handle: None,
+                interactive,
+            };
+        }
+
+        let handle = tokio::spawn(async move {
+            let mut samples = VecDeque::from([(Instant::now(), shown.done())]);
+            let mut last_log = Instant::now();
+
+            loop {
+                tokio::time::sleep(REDRAW_INTERVAL).await;
+
+                let now = Instant::now();
+                samples.push_back((now, shown.done()));
+                while samples.len() > 2 && now - samples[0].0 > RATE_WINDOW {
+                    samples.pop_front();
+                }
+
+                let (start, start_done) = samples[0];
+                let elapsed = (now - start).as_secs_f64();
+                let rate = if elapsed > 0.0 {
+                    shown.done().saturating_sub(start_done) as f64 / elapsed
+                } else {
+                    0.0
+                };
+
+                let finished = shown.finished.load(Ordering::Relaxed);
+
+                // Nothing needed downloading, so there's nothing to report.
+                if finished && shown.total() == 0 {
+                    break;
+                }
+
+                if interactive {
+                    eprint!("\r\x1b[2K{}", render(&shown, rate));
+                    if finished {
+                        eprintln!();
+                    }
+                    io::stderr().flush().ok();
+                } else if finished || now - last_log >= LOG_INTERVAL {
+                    eprintln!("{}", render(&shown, rate));
+                    last_log = now;
+                }
+
+                if finished {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            progress,
+            handle: Some(handle),
+            interactive,
+        }
+    }
+
+    /// Draws the final state and stops redrawing.
+    pub async fn finish(mut self) {
+        self.progress.finished.store(true, Ordering::Relaxed);
+        if let Some(handle) = &mut self.handle {
+            handle.await.ok();
+        }
+    }
+}
+
+impl Drop for ProgressDisplay {
+    // A download abandoned before it finished, like one that timed out,
+    // stops redrawing where it was and ends its line for the error after it.
+    fn drop(&mut self) {
+        if !self.progress.finished.swap(true, Ordering::Relaxed) {
+            if let Some(handle) = &self.handle {
+                handle.abort();
+                if self.interactive {
+                    eprintln!();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! Bringing the copies of the launcher in existing instances up to date
+//! after the launcher itself was updated.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::config::CONFIG_FILE_NAME;
+use crate::error::PathContext;
+use crate::fsutil::temp_path;
+use crate::sha1::sha1_file;
+use crate::Result;
+
+/// The copy of the launcher that create puts in an instance, which launches
+/// the instance it's in.
+pub const LAUNCHER_FILE_NAME: &str = "start.exe";
+
+/// What [`refresh_launcher`] did with an instance's launcher.
+#[derive(Debug)]
+pub enum LauncherRefresh {
+    Updated,
+    UpToDate,
+    /// The instance has no launcher, like one made with `--download-only`.
+    NoLauncher,
+    /// The launcher couldn't be replaced, most likely because it's running.
+    InUse(io::Error),
+}
+
+/// The instances among `roots` and their immediate subdirectories, so a
+/// root can be an instance or a folder of them.
+pub fn find_instance_directories(roots: &[PathBuf]) -> Vec<PathBuf> {
+    let is_instance = |path: &Path| path.join(CONFIG_FILE_NAME).is_file();
+    let mut instances = Vec::new();
+
+    for root in roots {
+        if is_instance(root) {
+            instances.push(root.clone());
+            continue;
+        }
+
+        let mut children = fs::read_dir(root)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| is_instance(path))
+            .collect::<Vec<_>>();
+        children.sort();
+        instances.extend(children);
+    }
+
+    instances
+}
+
+/// Replaces the launcher in `instance_directory` with a copy of `launcher`,
+/// unless it's already the same. The copy is renamed into place, so a
+/// launcher that's running either keeps its old file (on Unix) or refuses to
+/// be replaced (on Windows) rather than being left half-written.
+pub fn refresh_launcher(instance_directory: &Path, launcher: &Path) -> Result<LauncherRefresh> {
+    let path = instance_directory.join(LAUNCHER_FILE_NAME);
+
+    if !path.exists() {
+        return Ok(LauncherRefresh::NoLauncher);
+    }
+
+    if sha1_file(&path).with_path(&path)? == sha1_file(launcher).with_path(launcher)? {
+        return Ok(LauncherRefresh::UpToDate);
+    }
+
+    let temp = temp_path(&path);
+    fs::copy(launcher, &temp).with_path(&temp)?;
+
+    match fs::rename(&temp, &path) {
+        Ok(()) => Ok(LauncherRefresh::Updated),
+        Err(e) => {
+            let _ = fs::remove_file(&temp);
+            Ok(LauncherRefresh::InUse(e))
+        }
+    }
+}
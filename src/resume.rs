@@ -0,0 +1,102 @@
+//! Picking a failed create back up where it stopped.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::error::PathContext;
+use crate::fsutil::write_atomic;
+use crate::Result;
+
+pub const CREATE_STATE_FILE_NAME: &str = ".create-state";
+
+/// A step of an install that can be skipped once it has finished.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Client,
+    Libraries,
+    Natives,
+    Assets,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Phase::Client => "client",
+            Phase::Libraries => "libraries",
+            Phase::Natives => "natives",
+            Phase::Assets => "assets",
+        })
+    }
+}
+
+/// Which phases of creating an instance from `meta_url` have finished.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct CreateState {
+    meta_url: String,
+    completed: Vec<Phase>,
+    /// Untracked states never skip or write anything.
+    #[serde(skip)]
+    tracked: bool,
+}
+
+impl CreateState {
+    /// The state left by an earlier run for the same meta, or a fresh one.
+    pub fn load(instance_directory: &Path, meta_url: &str) -> Self {
+        fs::read_to_string(instance_directory.join(CREATE_STATE_FILE_NAME))
+            .ok()
+            .and_then(|state| serde_json::from_str::<Self>(&state).ok())
+            .filter(|state| state.meta_url == meta_url)
+            .map(|state| Self {
+                tracked: true,
+                ..state
+            })
+            .unwrap_or_else(|| Self {
+                meta_url: meta_url.to_owned(),
+                completed: Vec::new(),
+                tracked: true,
+            })
+    }
+
+    /// For installs that always run every phase, like updates.
+    pub fn untracked() -> Self {
+        Self::default()
+    }
+
+    pub fn is_done(&self, phase: Phase) -> bool {
+        self.completed.contains(&phase)
+    }
+
+    pub fn completed(&self) -> &[Phase] {
+        &self.completed
+    }
+
+    pub fn complete(&mut self, instance_directory: &Path, phase: Phase) -> Result<()> {
+        if !self.tracked {
+            return Ok(());
+        }
+
+        if !self.is_done(phase) {
+            self.completed.push(phase);
+        }
+
+        write_atomic(
+            &instance_directory.join(CREATE_STATE_FILE_NAME),
+            serde_json::to_string(self)?,
+        )?;
+
+        Ok(())
+    }
+
+    /// Forgets the state once the install has finished, or to start over.
+    pub fn clear(instance_directory: &Path) -> Result<()> {
+        let path = instance_directory.join(CREATE_STATE_FILE_NAME);
+
+        if path.exists() {
+            fs::remove_file(&path).with_path(&path)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use std::env;
+
+/// The current OS as Mojang's rules name it.
+pub fn os_name() -> &'static str {
+    match env::consts::OS {
+        "macos" => "osx",
+        other => other,
+    }
+}
+
+/// The current CPU architecture as Mojang's rules name it.
+pub fn os_arch() -> &'static str {
+    match env::consts::ARCH {
+        "aarch64" => "arm64",
+        other => other,
+    }
+}
+
+/// The current CPU architecture as natives classifiers name it, e.g. the
+/// `arm64` of `natives-linux-arm64`. Plain `natives-linux` is x86_64.
+pub fn natives_arch() -> &'static str {
+    match env::consts::ARCH {
+        "arm" => "arm32",
+        _ => os_arch(),
+    }
+}
+
+fn rule_matches(rule: &serde_json::Value, features: &[&str]) -> bool {
+    if let Some(os) = rule.get("os") {
+        if os["name"].as_str().is_some_and(|name| name != os_name()) {
+            return false;
+        }
+
+        if os["arch"].as_str().is_some_and(|arch| arch != os_arch()) {
+            return false;
+        }
+
+        // os.version is a regex over the OS version string, which isn't
+        // evaluated; those rules only ever target old Windows/macOS quirks.
+    }
+
+    if let Some(required) = rule.get("features").and_then(|f| f.as_object()) {
+        for (name, expected) in required {
+            if features.contains(&name.as_str()) != expected.as_bool().unwrap_or(false) {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+/// Evaluates a meta `rules` array the way the official launcher does: with no
+/// rules everything applies, otherwise the last matching rule decides and
+/// nothing applies when no rule matches. `features` lists the enabled launch
+/// features such as `is_demo_user`.
+pub fn rules_allow(rules: Option<&serde_json::Value>, features: &[&str]) -> bool {
+    let Some(rules) = rules.and_then(|rules| rules.as_array()) else {
+        return true;
+    };
+
+    let mut allowed = false;
+
+    for rule in rules {
+        if rule_matches(rule, features) {
+            allowed = rule["action"] == "allow";
+        }
+    }
+
+    allowed
+}
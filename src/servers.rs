@@ -0,0 +1,118 @@
+use std::fs;
+use std::path::Path;
+
+use crate::error::{ParseContext, PathContext};
+use crate::fsutil::write_atomic;
+use crate::nbt::{self, Tag};
+use crate::{Error, Result};
+
+pub const SERVERS_FILE_NAME: &str = "servers.dat";
+
+/// An entry of the multiplayer server list.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Server {
+    pub name: String,
+    /// `host` or `host:port`, exactly as the game shows it.
+    pub address: String,
+}
+
+/// Parses `host` or `host:port`.
+pub fn validate_address(address: &str) -> Result<()> {
+    let invalid = || Error::InvalidAddress(address.to_owned());
+
+    let (host, port) = match address.rsplit_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (address, None),
+    };
+
+    if host.is_empty() || host.contains(char::is_whitespace) {
+        return Err(invalid());
+    }
+
+    if let Some(port) = port {
+        port.parse::<u16>().map_err(|_| invalid())?;
+    }
+
+    Ok(())
+}
+
+/// The instance's servers.dat, or an empty server list when it has none yet.
+fn load_root(instance_directory: &Path) -> Result<Tag> {
+    let path = instance_directory.join(SERVERS_FILE_NAME);
+
+    if !path.exists() {
+        return Ok(Tag::Compound(vec![(
+            String::from("servers"),
+            Tag::List(Vec::new()),
+        )]));
+    }
+
+    Ok(nbt::read(&fs::read(&path).with_path(&path)?)
+        .parsing(path.display())?
+        .1)
+}
+
+pub fn list_servers(instance_directory: &Path) -> Result<Vec<Server>> {
+    let root = load_root(instance_directory)?;
+
+    let Some(Tag::List(servers)) = root.get("servers") else {
+        return Ok(Vec::new());
+    };
+
+    Ok(servers
+        .iter()
+        .map(|server| Server {
+            name: server
+                .get("name")
+                .and_then(Tag::as_str)
+                .unwrap_or_default()
+                .to_owned(),
+            address: server
+                .get("ip")
+                .and_then(Tag::as_str)
+                .unwrap_or_default()
+                .to_owned(),
+        })
+        .collect())
+}
+
+/// Adds `server` to the end of the instance's multiplayer list, or replaces
+/// the entry with the same name where it is. Other entries and their icons
+/// are left as they are.
+pub fn add_server(instance_directory: &Path, server: &Server) -> Result<()> {
+    validate_address(&server.address)?;
+
+    let mut root = load_root(instance_directory)?;
+
+    if root.get("servers").is_none() {
+        if let Tag::Compound(entries) = &mut root {
+            entries.push((String::from("servers"), Tag::List(Vec::new())));
+        }
+    }
+
+    let Some(Tag::List(servers)) = root.get_mut("servers") else {
+        return Err(Error::InvalidNbt(String::from(
+            "servers.dat has no server list",
+        )));
+    };
+
+    let entry = Tag::Compound(vec![
+        (String::from("ip"), Tag::String(server.address.clone())),
+        (String::from("name"), Tag::String(server.name.clone())),
+    ]);
+
+    match servers
+        .iter_mut()
+        .find(|existing| existing.get("name").and_then(Tag::as_str) == Some(server.name.as_str()))
+    {
+        Some(existing) => *existing = entry,
+        None => servers.push(entry),
+    }
+
+    write_atomic(
+        &instance_directory.join(SERVERS_FILE_NAME),
+        nbt::write("", &root),
+    )?;
+
+    Ok(())
+}
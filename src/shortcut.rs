@@ -0,0 +1,276 @@
+//! Desktop and menu shortcuts that run an instance's launcher, for people who
+//! don't use a terminal.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::config::{load_config, CONFIG_FILE_NAME};
+use crate::download::asset_object_path;
+use crate::error::PathContext;
+use crate::lock::installed_asset_index;
+use crate::meta::load_instance_meta;
+use crate::refresh::LAUNCHER_FILE_NAME;
+use crate::{Error, Result};
+
+/// Where the instance keeps a copy of the game's icon for its shortcuts, with
+/// an extension so desktops recognize it.
+const ICON_FILE_NAME: &str = "icon.png";
+
+/// The game's own window icons in the asset index, newest layout first.
+const ICON_ASSETS: &[&str] = &[
+    "minecraft/icons/icon_128x128.png",
+    "icons/icon_128x128.png",
+    "minecraft/icons/icon_32x32.png",
+    "icons/icon_32x32.png",
+];
+
+/// Where the instance's asset index keeps the game's icon, if it has one.
+fn game_icon(instance_directory: &Path) -> Option<PathBuf> {
+    let meta = load_instance_meta(instance_directory).ok()?;
+    let asset_index_id = installed_asset_index(instance_directory, &meta).ok()??;
+    let index_path = instance_directory.join(format!("assets/indexes/{}.json", asset_index_id));
+    let index: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(index_path).ok()?).ok()?;
+    let layout = load_config(instance_directory).ok()?.asset_layout;
+
+    ICON_ASSETS
+        .iter()
+        .find_map(|key| index["objects"][key]["hash"].as_str())
+        .map(|hash| asset_object_path(&instance_directory.join("assets/objects"), hash, layout))
+        .filter(|path| path.is_file())
+}
+
+/// Escapes `value` as a desktop entry string, so a newline in a directory
+/// name can't start a key of its own. Control characters the format has no
+/// escape for are refused.
+fn desktop_string(value: &str) -> Result<String> {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            c if c.is_control() => {
+                return Err(Error::Shortcut(format!(
+                    "{:?} has a control character a desktop entry can't hold",
+                    value
+                )))
+            }
+            c => escaped.push(c),
+        }
+    }
+
+    Ok(escaped)
+}
+
+/// Quotes `value` for the `Exec` key of a desktop entry.
+fn desktop_exec_quote(value: &str) -> Result<String> {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+
+    // A lone % would start a field code, and the whole value is unescaped
+    // once more as a string.
+    desktop_string(&quoted.replace('%', "%%"))
+}
+
+fn desktop_entry(name: &str, instance_directory: &Path, launcher: &Path) -> Result<String> {
+    let mut entry = format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=Minecraft ({name})\n\
+         Comment=Launch the {name} instance\n\
+         Exec={exec}\n\
+         Path={path}\n\
+         Terminal=false\n\
+         Categories=Game;\n",
+        name = desktop_string(name)?,
+        exec = desktop_exec_quote(&launcher.to_string_lossy())?,
+        path = desktop_string(&instance_directory.to_string_lossy())?,
+    );
+    let icon = instance_directory.join(ICON_FILE_NAME);
+    if game_icon(instance_directory).is_some_and(|object| fs::copy(object, &icon).is_ok()) {
+        entry.push_str(&format!(
+            "Icon={}\n",
+            desktop_string(&icon.to_string_lossy())?
+        ));
+    }
+
+    Ok(entry)
+}
+
+/// Writes a `.desktop` file for the instance to the applications menu and,
+/// when there is one, the desktop.
+fn create_desktop_entries(
+    name: &str,
+    instance_directory: &Path,
+    launcher: &Path,
+) -> Result<Vec<PathBuf>> {
+    let home = env::var_os("HOME").map(PathBuf::from);
+    let applications = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home.as_ref().map(|home| home.join(".local/share")))
+        .map(|data| data.join("applications"))
+        .ok_or_else(|| Error::Shortcut(String::from("HOME isn't set")))?;
+    let desktop = env::var_os("XDG_DESKTOP_DIR")
+        .map(PathBuf::from)
+        .or_else(|| home.map(|home| home.join("Desktop")))
+        .filter(|desktop| desktop.is_dir());
+
+    let file_name = format!(
+        "{}-{}.desktop",
+        env!("CARGO_PKG_NAME"),
+        name.replace(|c: char| !c.is_ascii_alphanumeric() && c != '-', "_")
+    );
+    let entry = desktop_entry(name, instance_directory, launcher)?;
+
+    fs::create_dir_all(&applications).with_path(&applications)?;
+    let mut created = Vec::new();
+
+    for directory in std::iter::once(applications).chain(desktop) {
+        let path = directory.join(&file_name);
+        fs::write(&path, &entry).with_path(&path)?;
+
+        // Desktops only run entries on the desktop that are executable.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).with_path(&path)?;
+        }
+
+        created.push(path);
+    }
+
+    Ok(created)
+}
+
+/// Quotes `value` as a PowerShell string literal.
+fn powershell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Has the Windows shell write a `.lnk` for the instance to the desktop and
+/// the Start menu. Shortcuts take `.ico` icons only, so they show the
+/// launcher's own.
+fn create_shell_links(
+    name: &str,
+    instance_directory: &Path,
+    launcher: &Path,
+) -> Result<Vec<PathBuf>> {
+    let script = format!(
+        "$shell = New-Object -ComObject WScript.Shell\n\
+         foreach ($folder in 'Desktop', 'Programs') {{\n\
+             $path = Join-Path ([Environment]::GetFolderPath($folder)) {file}\n\
+             $link = $shell.CreateShortcut($path)\n\
+             $link.TargetPath = {target}\n\
+             $link.WorkingDirectory = {directory}\n\
+             $link.Description = {description}\n\
+             $link.Save()\n\
+             $path\n\
+         }}",
+        file = powershell_quote(&format!("Minecraft ({}).lnk", name)),
+        target = powershell_quote(&launcher.to_string_lossy()),
+        directory = powershell_quote(&instance_directory.to_string_lossy()),
+        description = powershell_quote(&format!("Launch the {} instance", name)),
+    );
+
+    let output = process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .output()
+        .map_err(|e| Error::Shortcut(format!("could not run powershell: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(Error::Shortcut(
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| PathBuf::from(line.trim()))
+        .collect())
+}
+
+/// Creates shortcuts named after the instance that run its launcher: `.lnk`
+/// files on the desktop and in the Start menu on Windows, `.desktop` files in
+/// the applications menu and on the desktop elsewhere, with the game's icon
+/// (copied to [`ICON_FILE_NAME`]) when its assets are installed. Returns the
+/// shortcuts created.
+pub fn create_shortcuts(instance_directory: &Path) -> Result<Vec<PathBuf>> {
+    if cfg!(target_os = "macos") {
+        return Err(Error::Shortcut(String::from(
+            "macOS isn't supported, open start.exe from Finder instead",
+        )));
+    }
+
+    let instance_directory =
+        std::path::absolute(instance_directory).with_path(instance_directory)?;
+    if !instance_directory.join(CONFIG_FILE_NAME).is_file() {
+        return Err(Error::Shortcut(format!(
+            "{} isn't an instance",
+            instance_directory.display()
+        )));
+    }
+
+    let launcher = instance_directory.join(LAUNCHER_FILE_NAME);
+    if !launcher.is_file() {
+        return Err(Error::Shortcut(format!(
+            "{} has no {}, create it again without --download-only",
+            instance_directory.display(),
+            LAUNCHER_FILE_NAME
+        )));
+    }
+
+    let name = instance_directory
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| String::from("instance"));
+
+    if cfg!(windows) {
+        create_shell_links(&name, &instance_directory, &launcher)
+    } else {
+        create_desktop_entries(&name, &instance_directory, &launcher)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn desktop_entries_escape_what_would_break_them() {
+        let directory = Path::new("/games/evil\nExec=rm -rf ~");
+        let entry = desktop_entry(
+            "evil\nExec=rm -rf ~",
+            directory,
+            &directory.join("100% $HOME \\ start"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            entry
+                .lines()
+                .filter(|line| line.starts_with("Exec="))
+                .count(),
+            1
+        );
+        assert!(entry.contains("Name=Minecraft (evil\\nExec=rm -rf ~)\n"));
+        assert!(entry.contains("Path=/games/evil\\nExec=rm -rf ~\n"));
+        assert!(
+            entry.contains("Exec=\"/games/evil\\nExec=rm -rf ~/100%% \\\\$HOME \\\\\\\\ start\"\n")
+        );
+    }
+
+    #[test]
+    fn desktop_entries_refuse_other_control_characters() {
+        assert!(desktop_string("bell\u{7}").is_err());
+    }
+}
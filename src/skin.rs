@@ -0,0 +1,55 @@
+//! Local skins for offline play, put where skin loader mods look for them.
+
+use std::fs;
+use std::path::Path;
+
+use crate::error::PathContext;
+use crate::{Error, Result};
+
+/// Where OfflineSkins and CustomSkinLoader read a player's skin from, as
+/// `<username>.png` under the game directory.
+pub const SKIN_DIRECTORIES: &[&str] = &["cachedImages/skins", "CustomSkinLoader/LocalSkin/skins"];
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Checks that `data` is a PNG the game accepts as a skin, i.e. 64x64 or the
+/// old 64x32.
+pub fn check_skin(path: &Path, data: &[u8]) -> Result<()> {
+    let invalid = |reason: String| Error::InvalidSkin {
+        path: path.to_owned(),
+        reason,
+    };
+
+    // The IHDR chunk always comes first: length, type, width, height.
+    if data.len() < 24 || !data.starts_with(PNG_SIGNATURE) || &data[12..16] != b"IHDR" {
+        return Err(invalid(String::from("not a PNG")));
+    }
+
+    let width = u32::from_be_bytes(data[16..20].try_into().unwrap());
+    let height = u32::from_be_bytes(data[20..24].try_into().unwrap());
+
+    if width != 64 || !(height == 64 || height == 32) {
+        return Err(invalid(format!(
+            "{}x{}, skins are 64x64 or 64x32",
+            width, height
+        )));
+    }
+
+    Ok(())
+}
+
+/// Copies the skin at `skin_path` into the game directory as `username`'s.
+pub fn install_skin(game_directory: &Path, username: &str, skin_path: &Path) -> Result<()> {
+    let data = fs::read(skin_path).with_path(skin_path)?;
+    check_skin(skin_path, &data)?;
+
+    for directory in SKIN_DIRECTORIES {
+        let directory = game_directory.join(directory);
+        let path = directory.join(format!("{}.png", username));
+
+        fs::create_dir_all(&directory).with_path(&directory)?;
+        fs::write(&path, &data).with_path(&path)?;
+    }
+
+    Ok(())
+}
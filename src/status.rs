@@ -0,0 +1,139 @@
+//! A local HTTP endpoint reporting what the launcher is doing, for tools that
+//! supervise it. Nothing is served unless [`serve_status`] is called.
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::progress::Progress;
+use crate::{Error, Result};
+
+/// How long a client gets to send its request before it's dropped.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// What the launcher is doing, reported as the status endpoint's `phase`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Activity {
+    Starting,
+    Downloading,
+    /// Installed and about to exit, for create.
+    Ready,
+    Launching,
+    Running,
+    Exited,
+}
+
+struct Status {
+    activity: Activity,
+    progress: Option<Arc<Progress>>,
+    pid: Option<u32>,
+    exit_code: Option<i32>,
+}
+
+// One launcher does one thing at a time, so one status covers the process.
+static STATUS: Mutex<Status> = Mutex::new(Status {
+    activity: Activity::Starting,
+    progress: None,
+    pid: None,
+    exit_code: None,
+});
+
+fn update(f: impl FnOnce(&mut Status)) {
+    if let Ok(mut status) = STATUS.lock() {
+        f(&mut status);
+    }
+}
+
+pub fn set_activity(activity: Activity) {
+    update(|status| status.activity = activity);
+}
+
+/// Makes `progress` the download the endpoint reports on, from the moment
+/// its progress line starts.
+pub(crate) fn set_progress(progress: Arc<Progress>) {
+    update(|status| status.progress = Some(progress));
+}
+
+/// Reports the game as running with process id `pid`.
+pub fn set_running(pid: u32) {
+    update(|status| {
+        status.activity = Activity::Running;
+        status.pid = Some(pid);
+    });
+}
+
+/// Reports the game as exited, with its exit code unless a signal killed it.
+pub fn set_exited(exit_code: Option<i32>) {
+    update(|status| {
+        status.activity = Activity::Exited;
+        status.exit_code = exit_code;
+    });
+}
+
+fn status_json() -> String {
+    let Ok(status) = STATUS.lock() else {
+        return String::from("{}");
+    };
+
+    serde_json::json!({
+        "phase": status.activity,
+        "progress": status.progress.as_ref().map(|progress| serde_json::json!({
+            "label": progress.label(),
+            "done": progress.done(),
+            "total": progress.total(),
+        })),
+        "pid": status.pid,
+        "exit_code": status.exit_code,
+    })
+    .to_string()
+}
+
+fn respond(mut stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+
+    // Only the request line matters; the headers are read and ignored.
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") && request.len() < 8192 {
+        let read = stream.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut request_line = request.lines().next().unwrap_or_default().split(' ');
+    let (status, body) = match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some("/" | "/status")) => ("200 OK", status_json()),
+        _ => ("404 Not Found", String::from("{\"error\":\"not found\"}")),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+/// Serves the launcher's status as JSON on `address` from a background
+/// thread, e.g. `{"phase": "running", "progress": null, "pid": 4242,
+/// "exit_code": null}`, until the process exits.
+pub fn serve_status(address: SocketAddr) -> Result<()> {
+    let listener = TcpListener::bind(address).map_err(|source| Error::Serve { address, source })?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming().map_while(|stream| stream.ok()) {
+            let _ = respond(stream);
+        }
+    });
+
+    eprintln!("serving status on http://{}/status", address);
+
+    Ok(())
+}
@@ -0,0 +1,151 @@
+//! A store of files taken from other launchers, keyed by their sha1, that
+//! creates link assets and libraries from instead of downloading them.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::error::PathContext;
+use crate::fsutil::{link_or_copy, temp_path};
+use crate::paths::data_directory;
+use crate::sha1::sha1_file;
+use crate::Result;
+
+/// Where the store lives, under the data directory.
+pub fn store_directory() -> PathBuf {
+    data_directory().join("store")
+}
+
+fn store_path(store: &Path, sha1: &str) -> PathBuf {
+    store.join(&sha1[0..2]).join(sha1)
+}
+
+fn is_sha1(name: &str) -> bool {
+    name.len() == 40 && name.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Links the stored file with hash `sha1` to `destination`, when the store
+/// has it at `size` (0 for any size). Returns whether it was there.
+pub(crate) fn take_from_store(sha1: &str, size: u64, destination: &Path) -> bool {
+    if !is_sha1(sha1) {
+        return false;
+    }
+
+    let source = store_path(&store_directory(), &sha1.to_ascii_lowercase());
+    match fs::metadata(&source) {
+        Ok(metadata) if size == 0 || metadata.len() == size => {}
+        _ => return false,
+    }
+
+    if let Some(parent) = destination.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    // A leftover from an interrupted download would stop the link.
+    let _ = fs::remove_file(destination);
+    link_or_copy(&source, destination, false).is_ok()
+}
+
+/// What [`seed_store`] did with the files it found.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SeedSummary {
+    pub added: usize,
+    /// Already in the store.
+    pub present: usize,
+    /// Files whose hash isn't the one their name or `.sha1` file gives.
+    pub corrupt: usize,
+}
+
+fn files_under(directory: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            files_under(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Hardlinks `source` into the store as `sha1`, copying when it's on another
+/// device. A symlink wouldn't survive the other launcher deleting its copy.
+fn add_to_store(store: &Path, sha1: &str, source: &Path) -> Result<bool> {
+    let path = store_path(store, sha1);
+    if path.exists() {
+        return Ok(false);
+    }
+
+    let parent = path.parent().expect("store paths have a parent");
+    fs::create_dir_all(parent).with_path(parent)?;
+
+    if fs::hard_link(source, &path).is_err() {
+        let temp = temp_path(&path);
+        fs::copy(source, &temp).with_path(&temp)?;
+        fs::rename(&temp, &path).with_path(&path)?;
+    }
+
+    Ok(true)
+}
+
+/// The hash a maven repository's `.sha1` file next to `path` gives for it.
+fn sidecar_sha1(path: &Path) -> Option<String> {
+    let mut name = path.file_name()?.to_owned();
+    name.push(".sha1");
+    let contents = fs::read_to_string(path.with_file_name(name)).ok()?;
+
+    // Some repositories follow the hash with the file name.
+    let hash = contents.split_whitespace().next()?;
+    is_sha1(hash).then(|| hash.to_ascii_lowercase())
+}
+
+/// Adds the asset objects and libraries of the launcher directory `from`,
+/// like a `.minecraft` or a MultiMC folder (anything with `assets/objects`
+/// or `libraries` in it), to the store. Each file is hashed first: objects
+/// are named after their hash and have to match it, and libraries have to
+/// match their `.sha1` file when there is one.
+pub fn seed_store(from: &Path) -> Result<SeedSummary> {
+    let store = store_directory();
+    let mut summary = SeedSummary::default();
+
+    let mut objects = Vec::new();
+    let objects_path = from.join("assets").join("objects");
+    files_under(&objects_path, &mut objects).with_path(&objects_path)?;
+
+    let mut libraries = Vec::new();
+    let libraries_path = from.join("libraries");
+    files_under(&libraries_path, &mut libraries).with_path(&libraries_path)?;
+
+    let objects = objects.into_iter().filter_map(|path| {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        is_sha1(&name).then_some((path, Some(name)))
+    });
+    let libraries = libraries
+        .into_iter()
+        .filter(|path| path.extension().is_some_and(|extension| extension == "jar"))
+        .map(|path| {
+            let expected = sidecar_sha1(&path);
+            (path, expected)
+        });
+
+    for (path, expected) in objects.chain(libraries) {
+        let actual = sha1_file(&path).with_path(&path)?;
+
+        if expected.is_some_and(|expected| expected != actual) {
+            eprintln!("skipping {}: its hash doesn't match", path.display());
+            summary.corrupt += 1;
+        } else if add_to_store(&store, &actual, &path)? {
+            summary.added += 1;
+        } else {
+            summary.present += 1;
+        }
+    }
+
+    Ok(summary)
+}
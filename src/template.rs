@@ -0,0 +1,140 @@
+//! One-file setups that seed a new instance: its config plus options.txt,
+//! keybinds, mods and resource packs.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::config::LauncherConfig;
+use crate::download::{download_file, DownloadRun};
+use crate::error::{ParseContext, PathContext};
+use crate::mods::MODS_DIRECTORY;
+use crate::options::merge_options;
+use crate::Result;
+
+pub const RESOURCE_PACKS_DIRECTORY: &str = "resourcepacks";
+
+/// A file the template wants in the instance.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct TemplateFile {
+    pub url: String,
+    /// Defaults to the last segment of `url`.
+    #[serde(default)]
+    pub file: Option<String>,
+    #[serde(default)]
+    pub sha1: Option<String>,
+}
+
+impl TemplateFile {
+    fn file_name(&self) -> &str {
+        self.file
+            .as_deref()
+            .unwrap_or_else(|| self.url.rsplit('/').next().unwrap_or(&self.url))
+    }
+}
+
+/// A [`LauncherConfig`] with optional sections for what goes in the instance
+/// beyond it.
+#[derive(Clone, Debug, Default, serde::Deserialize)]
+pub struct Template {
+    #[serde(flatten)]
+    pub config: LauncherConfig,
+    /// Written to options.txt, e.g. `renderDistance = 12`.
+    #[serde(default)]
+    pub options: BTreeMap<String, toml::Value>,
+    /// Keybinds by name, e.g. `"key.jump" = "key.keyboard.space"`.
+    #[serde(default)]
+    pub keybinds: BTreeMap<String, String>,
+    #[serde(default)]
+    pub mods: Vec<TemplateFile>,
+    /// Downloaded and enabled, in order, above the vanilla pack.
+    #[serde(default)]
+    pub resourcepacks: Vec<TemplateFile>,
+    /// Whatever else was in the file. Reported rather than rejected so
+    /// templates can be shared between launcher versions.
+    #[serde(flatten)]
+    unknown: toml::Table,
+}
+
+pub fn load_template(path: &Path) -> Result<Template> {
+    let template: Template =
+        toml::from_str(&fs::read_to_string(path).with_path(path)?).parsing(path.display())?;
+
+    for key in template.unknown.keys() {
+        eprintln!(
+            "warning: ignoring unknown template field {} in {}",
+            key,
+            path.display()
+        );
+    }
+
+    Ok(template)
+}
+
+/// How a template value is spelled in options.txt.
+fn option_value(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(string) => string.clone(),
+        toml::Value::Array(values) => serde_json::to_string(values).unwrap_or_default(),
+        value => value.to_string(),
+    }
+}
+
+impl Template {
+    /// Writes the template's options and downloads its mods and resource
+    /// packs into `instance_directory`. Options already in options.txt that
+    /// the template doesn't mention are kept. The downloads join `run`, the
+    /// create's own.
+    pub async fn apply(
+        &self,
+        http_client: &reqwest::Client,
+        instance_directory: &Path,
+        run: &DownloadRun,
+    ) -> Result<()> {
+        for (directory, files) in [
+            (MODS_DIRECTORY, &self.mods),
+            (RESOURCE_PACKS_DIRECTORY, &self.resourcepacks),
+        ] {
+            for file in files {
+                let path = instance_directory.join(directory).join(file.file_name());
+                download_file(
+                    http_client,
+                    &file.url,
+                    &path,
+                    file.sha1.as_deref(),
+                    &self.config,
+                    run,
+                )
+                .await?;
+                eprintln!("downloaded {}", path.display());
+            }
+        }
+
+        let mut options = self
+            .options
+            .iter()
+            .map(|(key, value)| (key.clone(), option_value(value)))
+            .collect::<BTreeMap<_, _>>();
+
+        for (name, key) in &self.keybinds {
+            options.insert(format!("key_{}", name), key.clone());
+        }
+
+        if !self.resourcepacks.is_empty() && !options.contains_key("resourcePacks") {
+            let packs = std::iter::once(String::from("vanilla"))
+                .chain(
+                    self.resourcepacks
+                        .iter()
+                        .rev()
+                        .map(|pack| format!("file/{}", pack.file_name())),
+                )
+                .collect::<Vec<_>>();
+            options.insert(
+                String::from("resourcePacks"),
+                serde_json::to_string(&packs)?,
+            );
+        }
+
+        merge_options(instance_directory, &options)
+    }
+}
@@ -0,0 +1,204 @@
+//! Download concurrency that starts low, ramps up to the configured maximum
+//! and halves when a server answers 429 Too Many Requests.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// How often the limit grows while downloads succeed.
+const RAMP_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long after being throttled the limit starts growing again. Also keeps
+/// one burst of 429s from halving it more than once.
+const RECOVERY_DELAY: Duration = Duration::from_secs(2);
+
+pub struct ConcurrencyLimit {
+    semaphore: Semaphore,
+    max: usize,
+    state: Mutex<LimitState>,
+}
+
+struct LimitState {
+    limit: usize,
+    /// Permits to forget as they come back, when the limit dropped below the
+    /// number in use.
+    owed: usize,
+    next_increase: Instant,
+    last_throttled: Option<Instant>,
+}
+
+/// A download slot, returned to the limit on drop.
+pub struct Permit<'a> {
+    limit: &'a ConcurrencyLimit,
+    permit: Option<SemaphorePermit<'a>>,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        let mut state = self.limit.state.lock().unwrap();
+
+        if state.owed > 0 {
+            state.owed -= 1;
+            if let Some(permit) = self.permit.take() {
+                permit.forget();
+            }
+        }
+    }
+}
+
+impl ConcurrencyLimit {
+    /// Starts at a quarter of `max`, at least one.
+    pub fn new(max: usize) -> Self {
+        Self::starting_at(max, max / 4)
+    }
+
+    /// Starts at `start` rather than a quarter, for when a good value is
+    /// already known.
+    pub fn starting_at(max: usize, start: usize) -> Self {
+        let max = max.max(1);
+        let limit = start.clamp(1, max);
+
+        Self {
+            semaphore: Semaphore::new(limit),
+            max,
+            state: Mutex::new(LimitState {
+                limit,
+                owed: 0,
+                next_increase: Instant::now() + RAMP_INTERVAL,
+                last_throttled: None,
+            }),
+        }
+    }
+
+    /// The most the limit ever grows to.
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    pub async fn acquire(&self) -> Permit<'_> {
+        Permit {
+            limit: self,
+            permit: Some(self.semaphore.acquire().await.unwrap()),
+        }
+    }
+
+    /// Grows the limit by an eighth of the maximum, at most every
+    /// [`RAMP_INTERVAL`].
+    pub fn succeeded(&self) {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+
+        if state.limit >= self.max || now < state.next_increase {
+            return;
+        }
+
+        let step = (self.max / 8).max(1).min(self.max - state.limit);
+        state.limit += step;
+        state.next_increase = now + RAMP_INTERVAL;
+
+        // Cancel permits still owed before handing out new ones.
+        let repaid = step.min(state.owed);
+        state.owed -= repaid;
+        self.semaphore.add_permits(step - repaid);
+    }
+
+    /// Halves the limit after a 429.
+    pub fn throttled(&self) {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+
+        // Ignore the 429s of requests sent before the last drop.
+        if state
+            .last_throttled
+            .is_some_and(|last| now < last + RECOVERY_DELAY)
+        {
+            return;
+        }
+
+        let limit = (state.limit / 2).max(1);
+        let dropped = state.limit - limit;
+        state.owed += dropped - self.semaphore.forget_permits(dropped);
+        state.limit = limit;
+        state.next_increase = now + RECOVERY_DELAY;
+        state.last_throttled = Some(now);
+
+        if dropped > 0 {
+            eprintln!("rate limited, dropping to {} downloads at once", limit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit_of(limit: &ConcurrencyLimit) -> usize {
+        limit.state.lock().unwrap().limit
+    }
+
+    #[test]
+    fn starts_at_a_quarter_or_the_given_start() {
+        let quarter = ConcurrencyLimit::new(16);
+        assert_eq!(limit_of(&quarter), 4);
+        assert_eq!(quarter.semaphore.available_permits(), 4);
+
+        assert_eq!(limit_of(&ConcurrencyLimit::new(2)), 1);
+        assert_eq!(limit_of(&ConcurrencyLimit::starting_at(16, 10)), 10);
+        assert_eq!(limit_of(&ConcurrencyLimit::starting_at(4, 10)), 4);
+    }
+
+    #[tokio::test]
+    async fn ramps_up_to_the_max_and_no_further() {
+        let limit = ConcurrencyLimit::starting_at(16, 13);
+
+        // Too soon after the start to grow.
+        limit.succeeded();
+        assert_eq!(limit_of(&limit), 13);
+
+        tokio::time::sleep(RAMP_INTERVAL).await;
+        limit.succeeded();
+        assert_eq!(limit_of(&limit), 15);
+
+        tokio::time::sleep(RAMP_INTERVAL).await;
+        limit.succeeded();
+        assert_eq!(limit_of(&limit), 16);
+
+        tokio::time::sleep(RAMP_INTERVAL).await;
+        limit.succeeded();
+        assert_eq!(limit_of(&limit), 16);
+        assert_eq!(limit.semaphore.available_permits(), 16);
+    }
+
+    #[tokio::test]
+    async fn halving_with_permits_in_use_forgets_them_as_they_return() {
+        let limit = ConcurrencyLimit::starting_at(8, 8);
+        let mut permits = Vec::new();
+        for _ in 0..6 {
+            permits.push(limit.acquire().await);
+        }
+
+        // Two free permits go straight away, two more are owed.
+        limit.throttled();
+        assert_eq!(limit_of(&limit), 4);
+        assert_eq!(limit.semaphore.available_permits(), 0);
+
+        permits.truncate(4);
+        assert_eq!(limit.semaphore.available_permits(), 0);
+
+        permits.clear();
+        assert_eq!(limit.semaphore.available_permits(), 4);
+    }
+
+    #[tokio::test]
+    async fn a_burst_of_429s_halves_once() {
+        let limit = ConcurrencyLimit::starting_at(16, 16);
+
+        for _ in 0..5 {
+            limit.throttled();
+        }
+
+        assert_eq!(limit_of(&limit), 8);
+        assert_eq!(limit.semaphore.available_permits(), 8);
+    }
+}
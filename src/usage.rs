@@ -0,0 +1,158 @@
+//! How much disk an instance takes up, and how much of that deleting it
+//! would actually free.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::config::load_config;
+use crate::error::PathContext;
+use crate::launch::list_files;
+use crate::Result;
+
+/// The parts [`disk_usage`] splits an instance into, in the order to show
+/// them.
+pub const USAGE_CATEGORIES: &[&str] = &["assets", "libraries", "mods", "saves", "other"];
+
+/// Bytes in one part of an instance.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CategoryUsage {
+    /// Files only this instance uses.
+    pub owned: u64,
+    /// Files also linked from outside the instance, like a shared reports
+    /// directory or hardlinks into another instance, which deleting this one
+    /// doesn't free.
+    pub shared: u64,
+}
+
+impl CategoryUsage {
+    pub fn total(&self) -> u64 {
+        self.owned + self.shared
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct DiskUsage {
+    /// Keyed by the names in [`USAGE_CATEGORIES`].
+    pub categories: BTreeMap<&'static str, CategoryUsage>,
+}
+
+impl DiskUsage {
+    pub fn total(&self) -> CategoryUsage {
+        self.categories
+            .values()
+            .fold(CategoryUsage::default(), |total, usage| CategoryUsage {
+                owned: total.owned + usage.owned,
+                shared: total.shared + usage.shared,
+            })
+    }
+}
+
+/// A file counted once however many links to it the walk comes across.
+struct FileUsage {
+    category: &'static str,
+    size: u64,
+    /// Links to the file anywhere on its filesystem.
+    links: u64,
+    /// Links to it inside the instance.
+    found: u64,
+    outside: bool,
+}
+
+#[cfg(unix)]
+type FileKey = (u64, u64);
+#[cfg(not(unix))]
+type FileKey = std::path::PathBuf;
+
+/// What identifies the file behind `path` and how many links it has.
+#[cfg(unix)]
+fn file_key(_path: &Path, metadata: &fs::Metadata) -> (FileKey, u64) {
+    use std::os::unix::fs::MetadataExt;
+
+    ((metadata.dev(), metadata.ino()), metadata.nlink())
+}
+
+#[cfg(not(unix))]
+fn file_key(path: &Path, _metadata: &fs::Metadata) -> (FileKey, u64) {
+    (path.to_owned(), 1)
+}
+
+fn first_component(path: &Path) -> Option<&str> {
+    path.components().next()?.as_os_str().to_str()
+}
+
+/// Adds up the files of the instance at `instance_directory` and its game
+/// directory, following links so that each file counts once and where it
+/// really lives.
+pub fn disk_usage(instance_directory: &Path) -> Result<DiskUsage> {
+    let config = load_config(instance_directory)?;
+    let instance = fs::canonicalize(instance_directory).with_path(instance_directory)?;
+    let game_directory = fs::canonicalize(config.game_directory(instance_directory))
+        .unwrap_or_else(|_| instance.clone());
+
+    let mut roots = vec![instance.clone()];
+    if !game_directory.starts_with(&instance) {
+        roots.push(game_directory.clone());
+    }
+
+    let mut seen = HashSet::new();
+    let mut files: HashMap<FileKey, FileUsage> = HashMap::new();
+
+    for root in &roots {
+        for path in list_files(root)? {
+            // Broken links have nothing to count.
+            let Ok(target) = fs::canonicalize(&path) else {
+                continue;
+            };
+            if !seen.insert(target.clone()) {
+                continue;
+            }
+            let Ok(metadata) = fs::metadata(&target) else {
+                continue;
+            };
+
+            let in_game_directory = path.strip_prefix(&game_directory).ok();
+            let in_instance = path.strip_prefix(&instance).ok();
+            let category = match (
+                in_game_directory.and_then(first_component),
+                in_instance.and_then(first_component),
+            ) {
+                (Some("mods"), _) => "mods",
+                (Some("saves"), _) => "saves",
+                (_, Some("assets")) => "assets",
+                (_, Some("libraries")) => "libraries",
+                _ => "other",
+            };
+            let outside = !roots.iter().any(|root| target.starts_with(root));
+            let (key, links) = file_key(&target, &metadata);
+
+            files
+                .entry(key)
+                .or_insert(FileUsage {
+                    category,
+                    size: metadata.len(),
+                    links,
+                    found: 0,
+                    outside,
+                })
+                .found += 1;
+        }
+    }
+
+    let mut usage = DiskUsage::default();
+    for name in USAGE_CATEGORIES {
+        usage.categories.insert(name, CategoryUsage::default());
+    }
+
+    for file in files.values() {
+        let category = usage.categories.entry(file.category).or_default();
+
+        if file.outside || file.found < file.links {
+            category.shared += file.size;
+        } else {
+            category.owned += file.size;
+        }
+    }
+
+    Ok(usage)
+}
@@ -0,0 +1,315 @@
+//! Integrity checking of an installed instance against its meta.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::load_config;
+use crate::download::asset_object_path;
+use crate::error::{ParseContext, PathContext};
+use crate::filter::AssetFilter;
+use crate::fsutil::{file_stamp, write_atomic};
+use crate::lock::installed_asset_index;
+use crate::meta::{load_instance_meta, parse_libraries, Library};
+use crate::sha1::sha1_file;
+use crate::Result;
+
+pub const CHECKSUM_CACHE_FILE_NAME: &str = "checksums.json";
+
+/// A file's hash as of the last time it verified, keyed by what its metadata
+/// looked like then.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct CachedChecksum {
+    size: u64,
+    modified_ns: u64,
+    sha1: String,
+}
+
+/// Remembers the sha1 of files that passed verification so unchanged files
+/// aren't hashed again. An entry only counts while the file's size and
+/// modification time are what they were when it was hashed.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct ChecksumCache {
+    files: BTreeMap<String, CachedChecksum>,
+}
+
+impl ChecksumCache {
+    /// Starts empty when the cache is missing or unreadable.
+    pub fn load(instance_directory: &Path) -> Self {
+        fs::read_to_string(instance_directory.join(CHECKSUM_CACHE_FILE_NAME))
+            .ok()
+            .and_then(|cache| serde_json::from_str(&cache).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, instance_directory: &Path) -> Result<()> {
+        let path = instance_directory.join(CHECKSUM_CACHE_FILE_NAME);
+        write_atomic(&path, serde_json::to_string(self)?).with_path(&path)?;
+
+        Ok(())
+    }
+
+    /// Whether the file at `path` (cached under `key`) hashes to `sha1`.
+    fn matches(&mut self, key: &str, path: &Path, sha1: &str) -> bool {
+        let Some((size, modified_ns)) = file_stamp(path) else {
+            self.files.remove(key);
+            return false;
+        };
+
+        if let Some(cached) = self.files.get(key) {
+            if cached.size == size && cached.modified_ns == modified_ns {
+                return cached.sha1.eq_ignore_ascii_case(sha1);
+            }
+        }
+
+        match sha1_file(path) {
+            Ok(actual) if actual.eq_ignore_ascii_case(sha1) => {
+                self.files.insert(
+                    key.to_owned(),
+                    CachedChecksum {
+                        size,
+                        modified_ns,
+                        sha1: actual,
+                    },
+                );
+                true
+            }
+            _ => {
+                self.files.remove(key);
+                false
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub checked: usize,
+    pub missing: Vec<PathBuf>,
+    pub corrupt: Vec<PathBuf>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.corrupt.is_empty()
+    }
+}
+
+/// Which parts of an instance [`verify_scope`] checks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerifyScope {
+    All,
+    Assets,
+    Libraries,
+}
+
+/// Instance-relative paths and expected hashes, one list per phase.
+type ExpectedFiles = Vec<(String, String)>;
+
+fn client_files(meta: &serde_json::Value) -> ExpectedFiles {
+    meta["downloads"]["client"]["sha1"]
+        .as_str()
+        .map(|sha1| (String::from("client.jar"), sha1.to_owned()))
+        .into_iter()
+        .collect()
+}
+
+fn library_files(meta: &serde_json::Value) -> Result<ExpectedFiles> {
+    let mut expected = Vec::new();
+
+    for artifact in parse_libraries(meta)?
+        .iter()
+        .filter(|library| library.applies())
+        .flat_map(Library::artifacts)
+    {
+        if let Some(sha1) = &artifact.sha1 {
+            expected.push((format!("libraries/{}", artifact.path), sha1.clone()));
+        }
+    }
+
+    Ok(expected)
+}
+
+fn asset_files(instance_directory: &Path, meta: &serde_json::Value) -> Result<ExpectedFiles> {
+    let layout = load_config(instance_directory)?.asset_layout;
+    let objects_path = Path::new("assets/objects");
+    let mut expected = Vec::new();
+
+    let Some(asset_index_id) = installed_asset_index(instance_directory, meta)? else {
+        return Ok(expected);
+    };
+    let index_path = instance_directory.join(format!("assets/indexes/{}.json", asset_index_id));
+    if let Ok(index) = fs::read_to_string(&index_path) {
+        let index: serde_json::Value =
+            serde_json::from_str(&index).parsing(index_path.display())?;
+
+        for object in index["objects"]
+            .as_object()
+            .into_iter()
+            .flat_map(|o| o.values())
+        {
+            if let Some(hash) = object["hash"].as_str() {
+                let path = asset_object_path(objects_path, hash, layout);
+                expected.push((path.to_string_lossy().into_owned(), hash.to_owned()));
+            }
+        }
+    }
+
+    Ok(expected)
+}
+
+/// Checks the client jar, libraries and asset objects of the instance against
+/// the hashes in its meta and asset index. With `use_cache`, files unchanged
+/// since they last verified aren't hashed again.
+pub fn verify_instance(instance_directory: &Path, use_cache: bool) -> Result<VerifyReport> {
+    verify_scope(instance_directory, use_cache, VerifyScope::All)
+}
+
+/// [`verify_instance`] for just the parts `scope` selects.
+pub fn verify_scope(
+    instance_directory: &Path,
+    use_cache: bool,
+    scope: VerifyScope,
+) -> Result<VerifyReport> {
+    let meta = load_instance_meta(instance_directory)?;
+    let mut cache = if use_cache {
+        ChecksumCache::load(instance_directory)
+    } else {
+        ChecksumCache::default()
+    };
+
+    let expected = match scope {
+        VerifyScope::All => {
+            let mut expected = client_files(&meta);
+            expected.extend(library_files(&meta)?);
+            expected.extend(asset_files(instance_directory, &meta)?);
+            expected
+        }
+        VerifyScope::Assets => asset_files(instance_directory, &meta)?,
+        VerifyScope::Libraries => library_files(&meta)?,
+    };
+
+    let mut report = VerifyReport::default();
+
+    for (key, sha1) in expected {
+        let path = instance_directory.join(&key);
+        report.checked += 1;
+
+        if !path.exists() {
+            report.missing.push(path);
+        } else if !cache.matches(&key, &path, &sha1) {
+            report.corrupt.push(path);
+        }
+    }
+
+    if use_cache {
+        cache.save(instance_directory)?;
+    }
+
+    Ok(report)
+}
+
+/// How many of one part's files are on disk, out of those the meta expects.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FileCount {
+    pub present: usize,
+    pub expected: usize,
+}
+
+impl FileCount {
+    fn of(instance_directory: &Path, paths: BTreeSet<PathBuf>) -> Self {
+        Self {
+            present: paths
+                .iter()
+                .filter(|path| instance_directory.join(path).is_file())
+                .count(),
+            expected: paths.len(),
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.present == self.expected
+    }
+}
+
+impl fmt::Display for FileCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.present, self.expected)
+    }
+}
+
+/// What [`count_installed`] found, shown as
+/// `libraries 42/42, assets 4187/4187, client 1/1`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InstallCounts {
+    pub libraries: FileCount,
+    /// `None` for versions without an asset index and installs without
+    /// assets.
+    pub assets: Option<FileCount>,
+    pub client: FileCount,
+}
+
+impl fmt::Display for InstallCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "libraries {}", self.libraries)?;
+        if let Some(assets) = &self.assets {
+            write!(f, ", assets {}", assets)?;
+        }
+        write!(f, ", client {}", self.client)
+    }
+}
+
+/// Counts the files the instance's meta and asset index expect, libraries
+/// for this platform and assets `filter` selects, and how many are on disk.
+/// Nothing is hashed, so it's cheap enough to run after every create.
+pub fn count_installed(
+    instance_directory: &Path,
+    filter: Option<&AssetFilter>,
+    with_assets: bool,
+) -> Result<InstallCounts> {
+    let meta = load_instance_meta(instance_directory)?;
+
+    let client = FileCount::of(
+        instance_directory,
+        BTreeSet::from([PathBuf::from("client.jar")]),
+    );
+
+    let libraries = parse_libraries(&meta)?
+        .iter()
+        .filter(|library| library.applies())
+        .flat_map(Library::artifacts)
+        .map(|artifact| Path::new("libraries").join(&artifact.path))
+        .collect();
+    let libraries = FileCount::of(instance_directory, libraries);
+
+    let asset_index_id = installed_asset_index(instance_directory, &meta)?;
+    let assets = match asset_index_id.filter(|_| with_assets) {
+        Some(asset_index_id) => {
+            let layout = load_config(instance_directory)?.asset_layout;
+            let index_path =
+                instance_directory.join(format!("assets/indexes/{}.json", asset_index_id));
+            let index: serde_json::Value =
+                serde_json::from_str(&fs::read_to_string(&index_path).with_path(&index_path)?)
+                    .parsing(index_path.display())?;
+
+            // Keys sharing an object are one file on disk.
+            let objects = index["objects"]
+                .as_object()
+                .into_iter()
+                .flatten()
+                .filter(|(key, _)| filter.is_none_or(|filter| filter.matches(key)))
+                .filter_map(|(_, object)| object["hash"].as_str())
+                .map(|hash| asset_object_path(Path::new("assets/objects"), hash, layout))
+                .collect();
+            Some(FileCount::of(instance_directory, objects))
+        }
+        None => None,
+    };
+
+    Ok(InstallCounts {
+        libraries,
+        assets,
+        client,
+    })
+}
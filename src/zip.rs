@@ -0,0 +1,565 @@
+//! Just enough zip support to read jars: the central directory, stored and
+//! deflated entries, and a CRC-32 check of what was read. Writing only
+//! stores entries, which is all exported instances need.
+
+use std::fs;
+use std::path::Path;
+
+use crate::{Error, Result};
+
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x06054b50;
+const CENTRAL_DIRECTORY_HEADER: u32 = 0x02014b50;
+const LOCAL_FILE_HEADER: u32 = 0x04034b50;
+
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+
+fn invalid(reason: &str) -> Error {
+    Error::InvalidArchive(reason.to_owned())
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Result<u16> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+        .ok_or_else(|| invalid("truncated header"))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Result<u32> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or_else(|| invalid("truncated header"))
+}
+
+#[derive(Clone, Debug)]
+pub struct ZipEntry {
+    pub name: String,
+    pub size: u64,
+    method: u16,
+    compressed_size: u64,
+    crc32: u32,
+    local_header_offset: u64,
+}
+
+impl ZipEntry {
+    pub fn is_dir(&self) -> bool {
+        self.name.ends_with('/')
+    }
+}
+
+pub struct ZipArchive {
+    data: Vec<u8>,
+    entries: Vec<ZipEntry>,
+}
+
+impl ZipArchive {
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::from_bytes(fs::read(path)?)
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self> {
+        // The end of central directory record is at least 22 bytes, followed
+        // by a comment of up to 64 KiB.
+        let search_start = data.len().saturating_sub(22 + 0xffff);
+        let end = (search_start..data.len().saturating_sub(21))
+            .rev()
+            .find(|&offset| u32_at(&data, offset).ok() == Some(END_OF_CENTRAL_DIRECTORY))
+            .ok_or_else(|| invalid("no end of central directory, not a zip file"))?;
+
+        let entry_count = u16_at(&data, end + 10)? as usize;
+        let mut offset = u32_at(&data, end + 16)? as usize;
+        let mut entries = Vec::with_capacity(entry_count);
+
+        for _ in 0..entry_count {
+            if u32_at(&data, offset)? != CENTRAL_DIRECTORY_HEADER {
+                return Err(invalid("corrupt central directory"));
+            }
+
+            let name_length = u16_at(&data, offset + 28)? as usize;
+            let extra_length = u16_at(&data, offset + 30)? as usize;
+            let comment_length = u16_at(&data, offset + 32)? as usize;
+
+            let name = data
+                .get(offset + 46..offset + 46 + name_length)
+                .ok_or_else(|| invalid("truncated entry name"))?;
+
+            entries.push(ZipEntry {
+                name: String::from_utf8_lossy(name).into_owned(),
+                method: u16_at(&data, offset + 10)?,
+                crc32: u32_at(&data, offset + 16)?,
+                compressed_size: u32_at(&data, offset + 20)? as u64,
+                size: u32_at(&data, offset + 24)? as u64,
+                local_header_offset: u32_at(&data, offset + 42)? as u64,
+            });
+
+            offset += 46 + name_length + extra_length + comment_length;
+        }
+
+        Ok(Self { data, entries })
+    }
+
+    pub fn entries(&self) -> &[ZipEntry] {
+        &self.entries
+    }
+
+    pub fn find(&self, name: &str) -> Option<&ZipEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    pub fn read(&self, entry: &ZipEntry) -> Result<Vec<u8>> {
+        let offset = entry.local_header_offset as usize;
+
+        if u32_at(&self.data, offset)? != LOCAL_FILE_HEADER {
+            return Err(invalid("corrupt local file header"));
+        }
+
+        let name_length = u16_at(&self.data, offset + 26)? as usize;
+        let extra_length = u16_at(&self.data, offset + 28)? as usize;
+        let start = offset + 30 + name_length + extra_length;
+
+        let compressed = self
+            .data
+            .get(start..start + entry.compressed_size as usize)
+            .ok_or_else(|| invalid("truncated entry data"))?;
+
+        let contents = match entry.method {
+            STORED => compressed.to_vec(),
+            DEFLATED => inflate(compressed, entry.size as usize)?,
+            method => {
+                return Err(invalid(&format!(
+                    "{} uses unsupported compression method {}",
+                    entry.name, method
+                )))
+            }
+        };
+
+        if crc32(&contents) != entry.crc32 {
+            return Err(invalid(&format!("{} fails its crc check", entry.name)));
+        }
+
+        Ok(contents)
+    }
+
+    pub fn read_by_name(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        self.find(name).map(|entry| self.read(entry)).transpose()
+    }
+}
+
+/// Builds a zip of stored entries in memory. Mods and resource packs are
+/// already compressed, so deflating them again would gain little.
+#[derive(Default)]
+pub struct ZipWriter {
+    data: Vec<u8>,
+    central_directory: Vec<u8>,
+    entry_count: u16,
+}
+
+fn push_u16(buffer: &mut Vec<u8>, value: u16) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+/// Language encoding flag: names are UTF-8.
+const UTF8_NAMES: u16 = 0x0800;
+/// 1980-01-01, the earliest date a zip can hold.
+const DOS_EPOCH: u16 = 0x0021;
+
+impl ZipWriter {
+    pub fn add(&mut self, name: &str, contents: &[u8]) -> Result<()> {
+        let offset = u32::try_from(self.data.len()).ok();
+        let size = u32::try_from(contents.len()).ok();
+        let name_length = u16::try_from(name.len()).ok();
+
+        let (Some(offset), Some(size), Some(name_length), Some(entry_count)) =
+            (offset, size, name_length, self.entry_count.checked_add(1))
+        else {
+            return Err(invalid("too large to write without zip64"));
+        };
+        let crc = crc32(contents);
+
+        push_u32(&mut self.data, LOCAL_FILE_HEADER);
+        push_u16(&mut self.data, 20);
+        push_u16(&mut self.data, UTF8_NAMES);
+        push_u16(&mut self.data, STORED);
+        push_u16(&mut self.data, 0);
+        push_u16(&mut self.data, DOS_EPOCH);
+        push_u32(&mut self.data, crc);
+        push_u32(&mut self.data, size);
+        push_u32(&mut self.data, size);
+        push_u16(&mut self.data, name_length);
+        push_u16(&mut self.data, 0);
+        self.data.extend_from_slice(name.as_bytes());
+        self.data.extend_from_slice(contents);
+
+        let header = &mut self.central_directory;
+        push_u32(header, CENTRAL_DIRECTORY_HEADER);
+        push_u16(header, 20);
+        push_u16(header, 20);
+        push_u16(header, UTF8_NAMES);
+        push_u16(header, STORED);
+        push_u16(header, 0);
+        push_u16(header, DOS_EPOCH);
+        push_u32(header, crc);
+        push_u32(header, size);
+        push_u32(header, size);
+        push_u16(header, name_length);
+        // Extra field, comment, disk number and internal attributes.
+        header.extend_from_slice(&[0; 8]);
+        push_u32(header, 0);
+        push_u32(header, offset);
+        header.extend_from_slice(name.as_bytes());
+
+        self.entry_count = entry_count;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<Vec<u8>> {
+        let (Ok(directory_offset), Ok(directory_size)) = (
+            u32::try_from(self.data.len()),
+            u32::try_from(self.central_directory.len()),
+        ) else {
+            return Err(invalid("too large to write without zip64"));
+        };
+
+        self.data.append(&mut self.central_directory);
+        push_u32(&mut self.data, END_OF_CENTRAL_DIRECTORY);
+        push_u32(&mut self.data, 0);
+        push_u16(&mut self.data, self.entry_count);
+        push_u16(&mut self.data, self.entry_count);
+        push_u32(&mut self.data, directory_size);
+        push_u32(&mut self.data, directory_offset);
+        push_u16(&mut self.data, 0);
+
+        Ok(self.data)
+    }
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffffffffu32;
+
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    !crc
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    bit_buffer: u32,
+    bit_count: u32,
+}
+
+impl BitReader<'_> {
+    fn bits(&mut self, count: u32) -> Result<u32> {
+        while self.bit_count < count {
+            let byte = *self
+                .data
+                .get(self.position)
+                .ok_or_else(|| invalid("deflate stream ended early"))?;
+            self.position += 1;
+            self.bit_buffer |= (byte as u32) << self.bit_count;
+            self.bit_count += 8;
+        }
+
+        let value = self.bit_buffer & ((1u32 << count) - 1);
+        self.bit_buffer >>= count;
+        self.bit_count -= count;
+        Ok(value)
+    }
+
+    fn align_to_byte(&mut self) {
+        self.bit_buffer = 0;
+        self.bit_count = 0;
+    }
+}
+
+/// A canonical Huffman code, decoded a bit at a time.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut offsets = [0u16; 16];
+        for length in 1..15 {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+
+        Self { counts, symbols }
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16> {
+        let mut code = 0i32;
+        let mut first = 0i32;
+        let mut index = 0i32;
+
+        for length in 1..16 {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[length] as i32;
+
+            if code - count < first {
+                return Ok(self.symbols[(index + (code - first)) as usize]);
+            }
+
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(invalid("invalid huffman code"))
+    }
+}
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+fn inflate_block(
+    reader: &mut BitReader,
+    output: &mut Vec<u8>,
+    max_size: usize,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<()> {
+    loop {
+        if output.len() > max_size {
+            return Err(longer_than_declared());
+        }
+
+        let symbol = literals.decode(reader)? as usize;
+
+        match symbol {
+            0..=255 => output.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let index = symbol - 257;
+                if index >= LENGTH_BASE.len() {
+                    return Err(invalid("invalid length code"));
+                }
+                let length =
+                    LENGTH_BASE[index] as usize + reader.bits(LENGTH_EXTRA[index] as u32)? as usize;
+
+                let index = distances.decode(reader)? as usize;
+                if index >= DISTANCE_BASE.len() {
+                    return Err(invalid("invalid distance code"));
+                }
+                let distance = DISTANCE_BASE[index] as usize
+                    + reader.bits(DISTANCE_EXTRA[index] as u32)? as usize;
+
+                if distance > output.len() {
+                    return Err(invalid("distance reaches before the start of the output"));
+                }
+
+                let start = output.len() - distance;
+                for i in 0..length {
+                    output.push(output[start + i]);
+                }
+            }
+        }
+    }
+}
+
+fn dynamic_tables(reader: &mut BitReader) -> Result<(Huffman, Huffman)> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for &position in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[position] = reader.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths);
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+
+    while lengths.len() < literal_count + distance_count {
+        let symbol = code_length_code.decode(reader)?;
+
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => (
+                *lengths
+                    .last()
+                    .ok_or_else(|| invalid("repeat with no previous length"))?,
+                3 + reader.bits(2)? as usize,
+            ),
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize),
+        };
+
+        lengths.extend(std::iter::repeat_n(value, repeat));
+    }
+
+    if lengths.len() > literal_count + distance_count {
+        return Err(invalid("too many code lengths"));
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literal_count]),
+        Huffman::new(&lengths[literal_count..]),
+    ))
+}
+
+fn fixed_tables() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+
+    (Huffman::new(&lengths), Huffman::new(&[5u8; 30]))
+}
+
+/// Deflate can't compress better than about 1032 to 1, so an entry claiming
+/// more than that is lying about its size.
+const MAX_DEFLATE_RATIO: usize = 1032;
+
+fn longer_than_declared() -> Error {
+    invalid("inflates to more than its declared size")
+}
+
+/// Decompresses a raw deflate stream of at most `max_size` bytes, failing
+/// once it goes past that rather than filling memory for a corrupt entry.
+pub fn inflate(data: &[u8], max_size: usize) -> Result<Vec<u8>> {
+    let mut reader = BitReader {
+        data,
+        position: 0,
+        bit_buffer: 0,
+        bit_count: 0,
+    };
+    let mut output = Vec::with_capacity(max_size.min(data.len().saturating_mul(MAX_DEFLATE_RATIO)));
+
+    loop {
+        let last = reader.bits(1)? == 1;
+
+        match reader.bits(2)? {
+            0 => {
+                reader.align_to_byte();
+                let start = reader.position;
+                let length = u16_at(data, start)? as usize;
+                let block = data
+                    .get(start + 4..start + 4 + length)
+                    .ok_or_else(|| invalid("truncated stored block"))?;
+                output.extend_from_slice(block);
+                reader.position = start + 4 + length;
+            }
+            1 => {
+                let (literals, distances) = fixed_tables();
+                inflate_block(&mut reader, &mut output, max_size, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_tables(&mut reader)?;
+                inflate_block(&mut reader, &mut output, max_size, &literals, &distances)?;
+            }
+            _ => return Err(invalid("invalid deflate block type")),
+        }
+
+        if output.len() > max_size {
+            return Err(longer_than_declared());
+        }
+        if last {
+            return Ok(output);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inflates_stored_blocks() {
+        let data = [0x01, 0x05, 0x00, 0xfa, 0xff, b'h', b'e', b'l', b'l', b'o'];
+
+        assert_eq!(inflate(&data, 5).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn inflates_fixed_huffman_blocks() {
+        let data = [0x4b, 0x4c, 0x4a, 0x06, 0x00];
+
+        assert_eq!(inflate(&data, 3).unwrap(), b"abc");
+    }
+
+    #[test]
+    fn inflates_dynamic_huffman_blocks() {
+        let data = [
+            0x15, 0xc9, 0x41, 0x0d, 0x00, 0x00, 0x0c, 0x83, 0x40, 0xad, 0xb0, 0xfa, 0xd7, 0xb0,
+            0xf0, 0x3b, 0x82, 0x8a, 0x13, 0x90, 0xc5, 0x74, 0x15, 0x7a, 0x36, 0x1e,
+        ];
+
+        assert_eq!(
+            inflate(&data, 35).unwrap(),
+            b"bbbabdbaaabadbabdaaabcaabaabbcbbaaa"
+        );
+    }
+
+    #[test]
+    fn refuses_output_past_the_declared_size() {
+        let data = [0x4b, 0x4c, 0x4a, 0x06, 0x00];
+
+        assert!(inflate(&data, 2).is_err());
+    }
+
+    #[test]
+    fn round_trips_through_the_writer() {
+        let mut writer = ZipWriter::default();
+        writer.add("config/mod.toml", b"enabled = true\n").unwrap();
+        writer.add("empty", b"").unwrap();
+        let archive = ZipArchive::from_bytes(writer.finish().unwrap()).unwrap();
+
+        let names = archive
+            .entries()
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["config/mod.toml", "empty"]);
+        assert_eq!(
+            archive.read_by_name("config/mod.toml").unwrap().unwrap(),
+            b"enabled = true\n"
+        );
+        assert_eq!(archive.read_by_name("empty").unwrap().unwrap(), b"");
+        assert!(archive.read_by_name("missing").unwrap().is_none());
+    }
+}